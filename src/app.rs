@@ -1,23 +1,94 @@
 use crate::{
-    cursor::GlobalCursor,
+    compression::Compression,
+    cursor::{GlobalCursor, LeafCursor, PathElement, ValuePath},
+    diff, duplicate_keys, edit,
+    events::{TaskProgress, TaskState},
+    formats::{self, InputFormat},
+    grep, inspect,
+    jq::{
+        jv::{JVArray, JV},
+        query::{run_jq_query, JQOptions, QueryRun, JQ},
+    },
     layout::{self, JexLayout},
+    lines::RenderOptions,
+    outline::{Outline, OutlineEntry},
+    path_glob::PathGlob,
+    schema::{self, ValidationError},
+    session::{self, FileStates},
+    streaming::StreamingLoad,
     view_tree::{
-        View, ViewForest, ViewForestIndex, ViewTree, ViewTreeIndex, ViewWithParent,
-        ViewWithParentMut,
+        JsonView, NamedView, Scroll, View, ViewForest, ViewForestIndex, ViewTree, ViewTreeIndex,
+        ViewWithParent, ViewWithParentMut,
     },
 };
 use log::{debug, trace};
 use regex::Regex;
-use std::{default::Default, fs, io};
+use serde::{Deserialize, Serialize};
+use std::{
+    cell::RefCell,
+    collections::{BTreeMap, HashSet},
+    default::Default,
+    fs,
+    io::{self, Write},
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+    rc::{Rc, Weak},
+    thread,
+    time::{Duration, Instant},
+};
 use tui::{
     layout::{Alignment, Rect},
-    text::Text,
+    style::{Color, Style},
+    text::{Span, Spans, Text},
     widgets::{Block, Borders, Clear, Paragraph, Wrap},
     Frame,
 };
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
 
 const README: &str = include_str!("../README.md");
 
+// The list of keys in the README, one `- key: what it does` per line.
+pub fn controls_help() -> &'static str {
+    README
+        .rsplit("<!-- START CONTROLS POPUP -->\n")
+        .next()
+        .unwrap()
+        .split("<!-- END CONTROLS POPUP -->")
+        .next()
+        .unwrap()
+}
+
+pub const READ_ONLY_MESSAGE: &str = "Read-only: edits are turned off. Press P to allow them.";
+pub const QUERY_RESULTS_MESSAGE: &str =
+    "Query results can't be edited, since rerunning the query would replace them. Press c to \
+     copy this value to a view with no query, and edit that.";
+
+// Read-only, saving can make new files but not overwrite any.
+pub fn refuses_to_save_over(read_only: bool, path: &str) -> bool {
+    read_only && Path::new(path).exists()
+}
+
+// Shown in place of doing something that safe mode turns off.
+pub fn disabled_in_safe_mode(feature: &str) -> String {
+    format!("{} is disabled in safe mode", feature)
+}
+
+pub const DEFAULT_HISTORY_LIMIT: usize = 100;
+pub const DEFAULT_AUTOSAVE_DELAY: Duration = Duration::from_secs(2);
+const STATUS_DURATION: Duration = Duration::from_millis(1500);
+// How long a query runs before its first results are shown, once there's a screenful of them.
+const FIRST_RESULTS_BUDGET: Duration = Duration::from_millis(50);
+// How long a query runs on each tick after that.
+const QUERY_TICK_BUDGET: Duration = Duration::from_millis(20);
+// Shown in place of the query for roots, which are unfiltered files.
+const ROOT_PLACEHOLDER: &str = "No query: this is the whole file, unfiltered";
+// The task streaming loads report their progress as.
+pub const LOADING_TASK: &str = "Loading";
+pub const ROOT_HAS_NO_QUERY: &str = "Files have no query: press + to query this one";
+// Stands in for the input file in focused_jq_command when there isn't a json file to name.
+pub const INPUT_PLACEHOLDER: &str = "<input>";
+const SPINNER: [char; 10] = ['⠋', '⠙', '⠹', '⠸', '⠼', '⠴', '⠦', '⠧', '⠇', '⠏'];
+
 pub struct App {
     pub views: ViewForest,
     pub left_index: ViewForestIndex,
@@ -25,7 +96,71 @@ pub struct App {
     pub focus: Focus,
     pub search_re: Option<Regex>,
     pub show_tree: bool,
+    // Shown in place of the edit tree while it's open.
+    pub outline: Option<Outline>,
     pub flash: Option<Flash>,
+    pub jq_options: JQOptions,
+    // Whether files are loaded with invalid UTF-8 replaced, rather than failing.
+    pub lossy_utf8: bool,
+    // How many edits each view remembers for undo.
+    pub history_limit: usize,
+    // Whether edits are turned off, so files opened just to look at can't be changed by accident.
+    pub read_only: bool,
+    // With --autosave, how long after the last edit files are saved over themselves. Paused while
+    // read-only.
+    pub autosave: Option<Duration>,
+    // A path from --at, to move the cursor to once the file has loaded.
+    pub(crate) pending_path: Option<String>,
+    // Whether features that run other programs, like piping to a command, are turned off.
+    pub safe_mode: bool,
+    // The most results a query computes at first, with more fetched a batch this size at a time.
+    // None to always compute them all.
+    pub max_results: Option<usize>,
+    // Whether moving the focused pane's cursor moves the other pane's cursor the same way.
+    pub scroll_lock: bool,
+    // Whether moving to a search match folds everything but the way to it, as f does.
+    pub isolate_matches: bool,
+    // The query most recently run, for . to run again on another view.
+    pub last_query: Option<String>,
+    // What's remembered of files from earlier runs, restored when they're opened. None to neither
+    // restore nor remember anything.
+    pub file_states: Option<FileStates>,
+    // Set through set_render_options, so existing views are updated too.
+    pub(crate) render_options: RenderOptions,
+    // Counting matches scans every value, so it's only redone when the search or values change.
+    pub(crate) search_matches: RefCell<Option<SearchMatches>>,
+    // The first tree's file, if it's still being read.
+    pub(crate) loading: Option<StreamingLoad>,
+    // Work reported by other threads, with how far along it is if that's known.
+    pub(crate) tasks: BTreeMap<String, Option<f32>>,
+    // Advanced on each tick while anything is in progress.
+    pub(crate) spinner_frame: usize,
+    // A short message for when a key does nothing, cleared by a tick after STATUS_DURATION.
+    pub(crate) status: Option<(String, Instant)>,
+    // A query whose first results are shown while the rest are computed on each tick.
+    pub(crate) running_query: Option<RunningQuery>,
+}
+
+pub(crate) struct RunningQuery {
+    index: ViewForestIndex,
+    // What it's running, to check the view at `index` is still showing it.
+    query: String,
+    run: QueryRun,
+    view_rect: Rect,
+}
+
+pub(crate) struct SearchMatches {
+    pattern: String,
+    values: Weak<[JV]>,
+    matches: Vec<ValuePath>,
+}
+
+// How the focused view was when a search was started, to go back to if it's cancelled.
+pub struct SearchOrigin {
+    cursor: LeafCursor,
+    scroll: GlobalCursor,
+    folds: HashSet<(usize, Vec<usize>)>,
+    search_re: Option<Regex>,
 }
 
 pub struct Flash {
@@ -33,12 +168,54 @@ pub struct Flash {
     pub scroll: u16,
 }
 
+// How long each phase of opening a file took, in milliseconds, for `jex bench` to report.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct LoadTimings {
+    pub parse_ms: f64,
+    // Building the views, including running the right pane's query.
+    pub query_ms: f64,
+    // Drawing the first frame. `App::new_timed` leaves this to whoever draws it.
+    pub render_ms: f64,
+}
+
+// How long a query took to compile and then run on a whole file, for `jex bench --query`.
+#[derive(Debug, Clone, Serialize)]
+pub struct QueryTimings {
+    pub query: String,
+    pub compile_ms: f64,
+    pub execute_ms: f64,
+    pub results: usize,
+}
+
+pub fn millis(duration: Duration) -> f64 {
+    duration.as_secs_f64() * 1000.0
+}
+
+pub fn time_query(
+    values: &[JV],
+    query: &str,
+    options: &JQOptions,
+) -> Result<QueryTimings, Vec<String>> {
+    let start = Instant::now();
+    let mut prog = JQ::compile_with(query, options)?;
+    let compiled = Instant::now();
+    let results = run_jq_query(values, &mut prog).map_err(|err| vec![err])?;
+    Ok(QueryTimings {
+        query: query.to_owned(),
+        compile_ms: millis(compiled - start),
+        execute_ms: millis(compiled.elapsed()),
+        results: results.len(),
+    })
+}
+
 pub enum AppRenderMode {
     Normal,
     InputEditor,
+    // A prompt jex draws itself, rather than rustyline, so the view can change as it's typed.
+    Prompt(String),
 }
 
-#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Serialize, Deserialize)]
 pub enum Focus {
     Left,
     Right,
@@ -55,9 +232,42 @@ impl Focus {
 
 impl App {
     pub fn new<R: io::Read>(r: R, name: String, layout: JexLayout) -> io::Result<Self> {
-        let views = ViewForest {
-            trees: vec![ViewTree::new_from_reader(r, name, layout)?],
+        let (app, _) = App::new_timed(r, name, layout)?;
+        Ok(app)
+    }
+    // For input whose name doesn't say what format it's in, like the output of a command.
+    pub fn new_with_format<R: io::Read>(
+        r: R,
+        format: InputFormat,
+        name: String,
+        layout: JexLayout,
+    ) -> io::Result<Self> {
+        let (values, duplicates) = format.read_checked(r)?;
+        let mut app = App::from_tree(ViewTree::new_from_values(values, name, layout));
+        app.warn_duplicates(&duplicates);
+        Ok(app)
+    }
+    pub fn new_timed<R: io::Read>(
+        r: R,
+        name: String,
+        layout: JexLayout,
+    ) -> io::Result<(Self, LoadTimings)> {
+        let format = InputFormat::from_path(&name);
+        let start = Instant::now();
+        let (values, duplicates) = format.read_checked(r)?;
+        let parsed = Instant::now();
+        let tree = ViewTree::new_from_values(values, name, layout);
+        let timings = LoadTimings {
+            parse_ms: millis(parsed - start),
+            query_ms: millis(parsed.elapsed()),
+            render_ms: 0.0,
         };
+        let mut app = App::from_tree(tree);
+        app.warn_duplicates(&duplicates);
+        Ok((app, timings))
+    }
+    pub(crate) fn from_tree(tree: ViewTree) -> Self {
+        let views = ViewForest::new(vec![tree]);
         let left_index = ViewForestIndex {
             tree: 0,
             within_tree: ViewTreeIndex { path: Vec::new() },
@@ -66,17 +276,263 @@ impl App {
             tree: 0,
             within_tree: ViewTreeIndex { path: vec![0] },
         };
-        let app = App {
+        App {
             views,
             left_index,
             right_index,
             focus: Focus::Left,
             search_re: None,
             show_tree: false,
+            outline: None,
             flash: None,
-        };
+            jq_options: JQOptions::default(),
+            lossy_utf8: false,
+            history_limit: DEFAULT_HISTORY_LIMIT,
+            read_only: false,
+            autosave: None,
+            pending_path: None,
+            safe_mode: false,
+            max_results: None,
+            scroll_lock: false,
+            isolate_matches: false,
+            last_query: None,
+            file_states: None,
+            render_options: RenderOptions::default(),
+            search_matches: RefCell::new(None),
+            loading: None,
+            tasks: BTreeMap::new(),
+            spinner_frame: 0,
+            status: None,
+            running_query: None,
+        }
+    }
+    // Like `new`, but for json it only waits for the first value to be parsed. The rest is added
+    // as it arrives, through `receive_loaded`.
+    pub fn new_streaming<R: io::Read + Send + 'static>(
+        r: R,
+        name: String,
+        layout: JexLayout,
+    ) -> io::Result<Self> {
+        if InputFormat::from_path(&name) != InputFormat::Json {
+            return App::new(r, name, layout);
+        }
+        App::new_streaming_json(r, name, layout)
+    }
+    // Like `new_streaming`, whatever the name, for input that's json but not a json file.
+    pub fn new_streaming_json<R: io::Read + Send + 'static>(
+        r: R,
+        name: String,
+        layout: JexLayout,
+    ) -> io::Result<Self> {
+        let mut load = StreamingLoad::spawn(r);
+        if let Some(err) = load.wait() {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, err));
+        }
+        let tree = ViewTree::new_from_values(load.values(), name, layout);
+        let mut app = App::from_tree(tree);
+        app.warn_duplicates(&load.take_duplicates());
+        if !load.is_done() {
+            app.loading = Some(load);
+        }
+        Ok(app)
+    }
+    // Like `new`, but json files are parsed from a memory map of the file, to save memory.
+    // Compressed files have to be decompressed as they're read, so they can't be.
+    pub fn new_mapped(path: String, layout: JexLayout) -> io::Result<Self> {
+        if InputFormat::from_path(&path) != InputFormat::Json
+            || Compression::of_file(&path)?.is_some()
+        {
+            return App::new(formats::open_input(&path, false)?, path, layout);
+        }
+        let (values, duplicates) = formats::read_mapped(&path)?;
+        let tree = ViewTree::new_from_values(values, path, layout);
+        let mut app = App::from_tree(tree);
+        app.warn_duplicates(&duplicates);
         Ok(app)
     }
+    // Json parsers keep the last value for a repeated key, so the others would go unnoticed.
+    fn warn_duplicates(&mut self, duplicates: &[String]) {
+        if let Some(warning) = duplicate_keys::duplicates_warning(duplicates) {
+            self.set_flash(warning);
+        }
+    }
+    pub fn is_loading(&self) -> bool {
+        self.loading.is_some()
+    }
+    // Whether there's anything in progress to animate.
+    pub fn is_busy(&self) -> bool {
+        self.is_loading()
+            || self
+                .running_query
+                .as_ref()
+                .is_some_and(|running| !running.run.is_capped())
+            || !self.tasks.is_empty()
+            || self.status.is_some()
+            || self.autosave_pending()
+    }
+    // Shows `message` in the status line for a moment. Unlike a flash, it doesn't need dismissing.
+    pub fn set_status<S: Into<String>>(&mut self, message: S) {
+        self.status = Some((message.into(), Instant::now()));
+    }
+    pub fn update_task(&mut self, progress: TaskProgress) {
+        match progress.state {
+            TaskState::Running(fraction) => {
+                self.tasks.insert(progress.task, fraction);
+            }
+            TaskState::Done => {
+                self.tasks.remove(&progress.task);
+            }
+        }
+    }
+    // Advances the spinner, and shows anything that's loaded since the last tick.
+    pub fn tick(&mut self, layout: JexLayout) {
+        self.spinner_frame = self.spinner_frame.wrapping_add(1);
+        if matches!(&self.status, Some((_, shown)) if shown.elapsed() >= STATUS_DURATION) {
+            self.status = None;
+        }
+        self.receive_loaded(layout);
+        self.continue_query(QUERY_TICK_BUDGET, layout);
+        self.autosave_edits();
+    }
+    // The views --autosave will save, once there's been a pause in editing them.
+    fn autosave_pending(&self) -> bool {
+        self.autosave.is_some()
+            && !self.read_only
+            && self.views.trees.iter().any(|tree| {
+                matches!(&tree.view_frame.view,
+                    View::Json(Some(view)) if view.modified && view.last_edit.is_some())
+            })
+    }
+    // Saves edited roots over the json files they were opened from, if they haven't been edited
+    // for the --autosave delay. Roots that aren't files, whatever their titles, are never saved,
+    // and files of other formats or compressed ones are left for s, since views save as json.
+    pub fn autosave_edits(&mut self) {
+        let delay = match self.autosave {
+            Some(delay) if !self.read_only => delay,
+            _ => return,
+        };
+        let mut saved = Vec::new();
+        let mut errors = Vec::new();
+        for tree in self.views.trees.iter_mut() {
+            let NamedView {
+                view, source_path, ..
+            } = &mut tree.view_frame;
+            let view = match view {
+                View::Json(Some(view)) if view.modified => view,
+                View::Json(_) | View::Error(_) => continue,
+            };
+            match view.last_edit {
+                Some(last_edit) if last_edit.elapsed() >= delay => {}
+                Some(_) | None => continue,
+            }
+            // Either way, it waits for the next edit to try again.
+            view.last_edit = None;
+            let path = match source_path {
+                Some(path) if formats::is_plain_json(&path) => path,
+                Some(_) | None => continue,
+            };
+            match view.save_to(&path) {
+                Ok(()) => {
+                    view.modified = false;
+                    saved.push(path.display().to_string());
+                }
+                Err(err) => errors.push(format!("{}: {}", path.display(), err)),
+            }
+        }
+        if !errors.is_empty() {
+            self.set_flash(format!("Error autosaving:\n{}", errors.join("\n")));
+        } else if !saved.is_empty() {
+            self.set_status(format!("Saved {}", saved.join(", ")));
+        }
+    }
+    // A spinner and what's in progress, like `⠹ Loading, Indexing 40%`.
+    fn activity_status(&self) -> Option<String> {
+        // A load that reports its progress is shown by that instead.
+        let loading = self
+            .loading
+            .as_ref()
+            .filter(|_| !self.tasks.contains_key(LOADING_TASK))
+            .map(|_| LOADING_TASK.to_owned());
+        let querying = self.running_query.as_ref().map(|running| {
+            let results = running.run.results().len();
+            if running.run.is_capped() {
+                format!(
+                    "Showing the first {} results: ] for more, }} for all",
+                    results
+                )
+            } else {
+                format!("Querying ({} results)", results)
+            }
+        });
+        let tasks = self.tasks.iter().map(|(task, fraction)| match fraction {
+            Some(fraction) => format!("{} {:.0}%", task, fraction * 100.0),
+            None => task.clone(),
+        });
+        let activities: Vec<String> = loading.into_iter().chain(querying).chain(tasks).collect();
+        if activities.is_empty() {
+            return None;
+        }
+        let spinner = SPINNER[self.spinner_frame % SPINNER.len()];
+        Some(format!("{} {}", spinner, activities.join(", ")))
+    }
+    // Shows whatever's been parsed since the last call. Queries are only rerun once it's all
+    // loaded, since each rerun starts again from the top. Returns whether anything changed.
+    pub fn receive_loaded(&mut self, layout: JexLayout) -> bool {
+        let load = match self.loading.as_mut() {
+            Some(load) => load,
+            None => return false,
+        };
+        let (changed, err) = load.receive();
+        let duplicates = load.take_duplicates();
+        let values = if changed { Some(load.values()) } else { None };
+        let done = load.is_done();
+        if done {
+            self.loading = None;
+        }
+        if let Some(err) = err {
+            self.set_flash(format!("Stopped loading, showing what was read:\n{}", err));
+        }
+        self.warn_duplicates(&duplicates);
+        if !changed && !done {
+            return false;
+        }
+        let tree = &mut self.views.trees[0];
+        if let Some(values) = values {
+            match &mut tree.view_frame.view {
+                View::Json(Some(view)) if !view.modified => view.extend_loaded(values),
+                _ => {
+                    self.loading = None;
+                    self.set_flash("Stopped loading, since the file has been edited".to_owned());
+                    return true;
+                }
+            }
+        }
+        if done {
+            tree.recompute_children(&self.jq_options, layout.right);
+        }
+        self.resize(layout);
+        if done {
+            self.finish_loading();
+        }
+        true
+    }
+    fn finish_loading(&mut self) {
+        self.report_size(0);
+        if let Some(path) = self.pending_path.take() {
+            self.jump_focused_to_path(&path);
+        }
+    }
+    // Says how big tree `tree` is as compact json, once it's done loading.
+    pub fn report_size(&mut self, tree: usize) {
+        if tree == 0 && self.loading.is_some() {
+            return;
+        }
+        let size: usize = match &self.views.trees[tree].view_frame.view {
+            View::Json(Some(view)) => view.values.iter().map(inspect::serialized_size).sum(),
+            View::Json(None) | View::Error(_) => return,
+        };
+        self.set_status(format!("Loaded {}", inspect::human_size(size)));
+    }
     fn current_views(&self) -> (ViewWithParent, ViewWithParent) {
         trace!(
             "current_views:\nleft:{:#?}\nright:{:#?}\ntree:{:#?}",
@@ -117,11 +573,89 @@ impl App {
             Focus::Right => self.right_view_mut(),
         }
     }
+    // None when the focused view is a root, which is an opened file rather than a query.
+    pub fn focused_query(&self) -> Option<&String> {
+        self.focused_view().query()
+    }
     pub fn focused_query_mut(&mut self) -> Option<&mut String> {
-        match self.focused_view_mut() {
-            ViewWithParentMut::Root { .. } => None,
-            ViewWithParentMut::Child { query, .. } => Some(query),
+        self.focused_view_mut().take_query()
+    }
+    // A jq command line that gives what the focused view shows, from its file and the queries
+    // leading to it, with the same -L, --arg and --argjson options. Edits aren't included. Views
+    // that jq can't read as they are, like ones from yaml or stdin, get INPUT_PLACEHOLDER instead.
+    pub fn focused_jq_command(&self) -> String {
+        let index = self.focused_index();
+        let mut tree = &self.views.trees[index.tree];
+        let mut queries = Vec::new();
+        for &child in index.within_tree.path.iter() {
+            let (query, child) = &tree.children[child];
+            queries.push(query.as_str());
+            tree = child;
         }
+        let input = match &self.views.trees[index.tree].view_frame.source_path {
+            Some(path) if formats::is_plain_json(path) => shell_quote(&path.to_string_lossy()),
+            _ => INPUT_PLACEHOLDER.to_owned(),
+        };
+        // A comment would swallow the rest of a line, so queries with them go on lines of their
+        // own.
+        let separator = if queries.iter().any(|query| query.contains('#')) {
+            "\n| "
+        } else {
+            " | "
+        };
+        let filter = match queries.as_slice() {
+            [] => ".".to_owned(),
+            queries => queries.join(separator),
+        };
+        let mut words = vec!["jq".to_owned()];
+        for path in self.jq_options.library_paths.iter() {
+            words.extend(vec!["-L".to_owned(), shell_quote(path)]);
+        }
+        for (name, value) in self.jq_options.named_args.iter() {
+            let (option, value) = match value {
+                JV::String(s) => ("--arg", s.value().to_owned()),
+                value => (
+                    "--argjson",
+                    serde_json::to_string(value).expect("json values should serialize"),
+                ),
+            };
+            words.extend(vec![
+                option.to_owned(),
+                shell_quote(name),
+                shell_quote(&value),
+            ]);
+        }
+        words.push(format!("'{}'", filter.replace('\'', "'\\''")));
+        words.push(input);
+        words.join(" ")
+    }
+    // Object keys the focused query is likely to index into: those of the parent's focused value,
+    // then those of the parent's top level values. Used for completion, so only the first few top
+    // level values are inspected.
+    pub fn focused_query_input_keys(&self) -> Vec<String> {
+        const MAX_VALUES_INSPECTED: usize = 100;
+        let parent = match self.focused_view() {
+            ViewWithParent::Root { .. } => return Vec::new(),
+            ViewWithParent::Child { parent, .. } => parent,
+        };
+        let view = match &parent.view {
+            View::Json(Some(view)) => view,
+            View::Json(None) | View::Error(_) => return Vec::new(),
+        };
+        let mut keys = Vec::new();
+        let mut seen = HashSet::new();
+        let values = std::iter::once(&view.cursor.focus)
+            .chain(view.values.iter().take(MAX_VALUES_INSPECTED));
+        for value in values {
+            if let JV::Object(obj) = value {
+                for (key, _) in obj.iter() {
+                    if seen.insert(key) {
+                        keys.push(key.to_owned());
+                    }
+                }
+            }
+        }
+        keys
     }
     pub fn focused_index(&self) -> &ViewForestIndex {
         match self.focus {
@@ -135,21 +669,536 @@ impl App {
             Focus::Right => &mut self.right_index,
         }
     }
-    pub fn recompute_focused_view(&mut self, focused_rect: Rect) {
-        match self.focused_view_mut() {
+    // Reruns the right pane with `query`, like entering it with q there. A query that fails shows
+    // its error in the pane.
+    pub fn set_right_query(&mut self, query: String, layout: JexLayout) {
+        let focus = self.focus;
+        self.focus = Focus::Right;
+        if let Some(focused_query) = self.focused_query_mut() {
+            *focused_query = query;
+            self.recompute_focused_view(layout);
+        }
+        self.focus = focus;
+    }
+    // Reruns the focused view's query on its parent in the edit tree, which needn't be the view in
+    // the other pane, or on screen at all.
+    pub fn recompute_focused_view(&mut self, layout: JexLayout) {
+        let focused_rect = match self.focus {
+            Focus::Left => layout.left,
+            Focus::Right => layout.right,
+        };
+        let jq_options = self.jq_options.clone();
+        let max_results = self.max_results;
+        let index = self.focused_index().clone();
+        let (query, run) = match self.focused_view_mut() {
             ViewWithParentMut::Root { .. } => panic!("Can't recompute root node"),
             ViewWithParentMut::Child {
                 parent,
                 query,
                 frame,
-            } => match &parent.view {
-                View::Json(Some(left)) => {
-                    frame.view = left.apply_query(query, focused_rect);
+            } => (
+                query.clone(),
+                frame.view.start_recompute_from(
+                    &parent.view,
+                    query,
+                    &jq_options,
+                    focused_rect,
+                    Some(FIRST_RESULTS_BUDGET),
+                    max_results,
+                ),
+            ),
+        };
+        if run.is_none() {
+            self.report_empty_results(&index);
+        }
+        self.last_query = Some(query.clone());
+        self.running_query = run.map(|run| RunningQuery {
+            index,
+            query,
+            run,
+            view_rect: focused_rect,
+        });
+    }
+    // Runs the last query again on the focused view. Roots have no query to replace, so it's run
+    // on a new view under them instead.
+    pub fn repeat_last_query(&mut self, layout: JexLayout) {
+        let query = match &self.last_query {
+            Some(query) => query.clone(),
+            None => return self.set_status("No query to repeat yet"),
+        };
+        if self.focused_query().is_none() {
+            let rect = match self.focus {
+                Focus::Left => layout.left,
+                Focus::Right => layout.right,
+            };
+            let index = self.focused_index().clone();
+            self.views
+                .folds
+                .remove(&(index.tree, index.within_tree.path.clone()));
+            let tree = self.views.trees[index.tree]
+                .index_tree_mut(&index.within_tree.path)
+                .expect("App index invalidated");
+            let children = tree.children.len();
+            tree.push_trivial_child(rect);
+            if tree.children.len() == children {
+                return self.set_status("There's nothing here to query");
+            }
+            self.focused_index_mut().within_tree.path.push(children);
+        }
+        *self.focused_query_mut().expect("Checked for a root above") = query;
+        self.recompute_focused_view(layout);
+    }
+    // Says so when a query finishes with nothing to show, or with a last result of null or false,
+    // which are when jq -e fails.
+    fn report_empty_results(&mut self, index: &ViewForestIndex) {
+        let message = match self.views.index(index).map(|view| &view.frame().view) {
+            Some(View::Json(None)) => "No results",
+            Some(View::Json(Some(view))) => match view.values.last() {
+                Some(JV::Null(_)) => "The last result is null",
+                Some(JV::Bool(b)) if !b.value() => "The last result is false",
+                _ => return,
+            },
+            Some(View::Error(_)) | None => return,
+        };
+        self.set_status(message);
+    }
+    // Runs the query that's still going for `budget`, showing its new results and rerunning the
+    // queries below it.
+    fn continue_query(&mut self, budget: Duration, layout: JexLayout) {
+        let mut running = match self.running_query.take() {
+            Some(running) => running,
+            None => return,
+        };
+        let before = running.run.results().len();
+        let render_options = self.render_options;
+        let mut stopped_by_edit = false;
+        let still_running = match self.views.index_mut(&running.index) {
+            Some(ViewWithParentMut::Child {
+                frame,
+                parent,
+                query,
+            }) if *query == running.query
+                && matches!(&parent.view, View::Json(Some(parent))
+                    if Rc::ptr_eq(&parent.values, running.run.inputs())) =>
+            {
+                match &frame.view {
+                    View::Json(Some(view)) if view.modified => {
+                        stopped_by_edit = true;
+                        false
+                    }
+                    _ => frame.view.continue_query(
+                        &mut running.run,
+                        budget,
+                        running.view_rect,
+                        render_options,
+                    ),
                 }
-                View::Json(None) | View::Error(_) => {
-                    frame.view = View::Json(None);
+            }
+            // The view's been requeried, or its parent changed, since it started.
+            _ => false,
+        };
+        if stopped_by_edit {
+            self.set_flash("Stopped the query, since its results have been edited".to_owned());
+        }
+        if running.run.results().len() > before {
+            let jq_options = self.jq_options.clone();
+            self.views.trees[running.index.tree]
+                .index_tree_mut(&running.index.within_tree.path)
+                .expect("Checked the index above")
+                .recompute_children(&jq_options, layout.right);
+            self.resize(layout);
+        }
+        if still_running {
+            self.running_query = Some(running);
+        } else if running.run.is_done() && !stopped_by_edit {
+            self.report_empty_results(&running.index);
+        }
+    }
+    // Lets a query stopped at max_results compute another batch of results, or all of them.
+    // Returns false if there isn't one.
+    pub fn fetch_more_results(&mut self, all: bool) -> bool {
+        let batch = self.max_results;
+        let run = match self.running_query.as_mut() {
+            Some(running) if running.run.is_capped() => &mut running.run,
+            _ => return false,
+        };
+        let limit = if all {
+            None
+        } else {
+            run.limit().zip(batch).map(|(limit, batch)| limit + batch)
+        };
+        run.set_limit(limit);
+        true
+    }
+    // Runs the query that's still going to the end, for things that need all of its results. One
+    // stopped at max_results stays stopped.
+    pub fn finish_query(&mut self, layout: JexLayout) {
+        self.continue_query(Duration::MAX, layout);
+    }
+    // Views with a query are recomputed whenever their parent changes, which would silently
+    // throw away any edits, so only roots can be edited. Flashes why if the focused view can't.
+    pub fn refuses_edit(&mut self) -> bool {
+        let refuses = self.focused_query().is_some();
+        if refuses {
+            self.set_flash(QUERY_RESULTS_MESSAGE.to_owned());
+        }
+        refuses
+    }
+    // Applies `f` to the focused json view, then reruns the queries that depend on it if it
+    // returned true.
+    fn change_focused_view<F: FnOnce(&mut JsonView, usize) -> bool>(
+        &mut self,
+        f: F,
+        layout: JexLayout,
+    ) -> bool {
+        if self.refuses_edit() {
+            return false;
+        }
+        let history_limit = self.history_limit;
+        let changed = match &mut self.focused_view_mut().frame().view {
+            View::Json(Some(view)) => f(view, history_limit),
+            View::Json(None) | View::Error(_) => false,
+        };
+        if changed {
+            let index = self.focused_index().clone();
+            let jq_options = self.jq_options.clone();
+            self.views.trees[index.tree]
+                .index_tree_mut(&index.within_tree.path)
+                .expect("App index invalidated")
+                .recompute_children(&jq_options, layout.right);
+            // The recomputed views might include the one in the left pane.
+            self.resize(layout);
+        }
+        changed
+    }
+    // Replaces the focused view's values with the result of `f`, which also gives the cursor's
+    // new position. Errors are shown in a flash.
+    fn edit_focused<F>(&mut self, f: F, layout: JexLayout)
+    where
+        F: FnOnce(&JsonView) -> Result<(Vec<JV>, (usize, Vec<PathElement>)), String>,
+    {
+        let mut error = None;
+        self.change_focused_view(
+            |view, history_limit| match f(view) {
+                Ok((values, cursor)) => view.edit_values(values, cursor, history_limit),
+                Err(err) => {
+                    error = Some(err);
+                    false
                 }
             },
+            layout,
+        );
+        if let Some(err) = error {
+            self.set_flash(err);
+        }
+    }
+    // The text to prefill when editing the focused leaf, if it is one.
+    pub fn focused_leaf_text(&self) -> Option<String> {
+        match &self.focused_view().frame().view {
+            // Match how numbers are displayed, rather than serde's `1.0`.
+            View::Json(Some(view)) => match &view.cursor.focus {
+                JV::Number(x) => Some(x.value().to_string()),
+                focus if edit::is_scalar(focus) => serde_json::to_string(focus).ok(),
+                _ => None,
+            },
+            View::Json(_) | View::Error(_) => None,
+        }
+    }
+    pub fn edit_focused_leaf(&mut self, text: &str, layout: JexLayout) {
+        self.edit_focused(
+            |view| {
+                let new_value = edit::parse_scalar(text)?;
+                let top_index = view.cursor.top_index;
+                let path = view.cursor.to_json_path();
+                let values =
+                    edit::modify_values(&view.values, top_index, &path, |_| Some(new_value))
+                        .ok_or("The value under the cursor no longer exists")?;
+                Ok((values, (top_index, path)))
+            },
+            layout,
+        );
+    }
+    // Removes the value under the cursor from its parent, moving the cursor to a sibling.
+    pub fn delete_focused(&mut self, layout: JexLayout) {
+        self.edit_focused(
+            |view| {
+                let top_index = view.cursor.top_index;
+                let mut path = view.cursor.to_json_path();
+                let child = match path.pop() {
+                    Some(child) => child,
+                    None if view.values.len() == 1 => {
+                        return Err("Can't delete the only value in a view".to_owned())
+                    }
+                    None => {
+                        let mut values = view.values.to_vec();
+                        values.remove(top_index);
+                        let new_top = std::cmp::min(top_index, values.len() - 1);
+                        return Ok((values, (new_top, path)));
+                    }
+                };
+                let mut neighbor = None;
+                let values = edit::modify_values(&view.values, top_index, &path, |parent| {
+                    let (parent, sibling) = edit::delete_child(parent, &child)?;
+                    neighbor = sibling;
+                    Some(parent)
+                })
+                .ok_or("The value under the cursor no longer exists")?;
+                path.extend(neighbor);
+                Ok((values, (top_index, path)))
+            },
+            layout,
+        );
+    }
+    // Sorts the array under the cursor, or the one containing it, by `key` (like `.a.b`, with a
+    // leading `-` to sort descending). The cursor stays on the same element.
+    pub fn sort_focused(&mut self, key: &str, layout: JexLayout) {
+        let (descending, key) = match key.trim().strip_prefix('-') {
+            Some(key) => (true, key),
+            None => (false, key),
+        };
+        self.edit_focused(
+            |view| {
+                let key = edit::parse_key_path(key)?;
+                let top_index = view.cursor.top_index;
+                let mut path = view.cursor.to_json_path();
+                // Sort the focused array itself, or else the innermost array around the cursor.
+                let array_depth = if matches!(view.cursor.focus, JV::Array(_)) {
+                    path.len()
+                } else {
+                    path.iter()
+                        .rposition(|element| matches!(element, PathElement::Index(_)))
+                        .ok_or("The cursor isn't in an array")?
+                };
+                let mut order = Vec::new();
+                let values =
+                    edit::modify_values(&view.values, top_index, &path[..array_depth], |arr| {
+                        let (arr, new_order) = edit::sort_array(arr, &key, descending)?;
+                        order = new_order;
+                        Some(arr)
+                    })
+                    .ok_or("The value under the cursor no longer exists")?;
+                if let Some(PathElement::Index(i)) = path.get_mut(array_depth) {
+                    *i = order.iter().position(|old| old == i).unwrap_or(*i);
+                }
+                Ok((values, (top_index, path)))
+            },
+            layout,
+        );
+    }
+    // Inserts a copy of the value under the cursor after it, and moves the cursor to the copy.
+    pub fn duplicate_focused(&mut self, layout: JexLayout) {
+        self.edit_focused(
+            |view| {
+                let top_index = view.cursor.top_index;
+                let mut path = view.cursor.to_json_path();
+                let child = match path.pop() {
+                    Some(child) => child,
+                    None => {
+                        let mut values = view.values.to_vec();
+                        values.insert(top_index + 1, values[top_index].clone());
+                        return Ok((values, (top_index + 1, path)));
+                    }
+                };
+                let mut copy = None;
+                let values = edit::modify_values(&view.values, top_index, &path, |parent| {
+                    let (parent, copy_element) = edit::duplicate_child(parent, &child)?;
+                    copy = Some(copy_element);
+                    Some(parent)
+                })
+                .ok_or("The value under the cursor no longer exists")?;
+                path.extend(copy);
+                Ok((values, (top_index, path)))
+            },
+            layout,
+        );
+    }
+    pub fn fold_focused_matching(&mut self, pattern: &str) {
+        let glob = match PathGlob::parse(pattern) {
+            Ok(glob) => glob,
+            Err(err) => return self.set_flash(err),
+        };
+        if let Some(err) = self.focused_depth_error() {
+            return self.set_flash(err);
+        }
+        let matched = match &mut self.focused_view_mut().frame().view {
+            View::Json(Some(view)) => view.fold_matching(&glob),
+            View::Json(None) | View::Error(_) => return,
+        };
+        if matched == 0 {
+            self.set_flash(format!("Nothing matches {}", pattern));
+        }
+    }
+    pub fn jump_focused_to_line(&mut self, text: &str) {
+        let line = match text.trim().parse::<usize>() {
+            Ok(line) if line > 0 => line,
+            _ => return self.set_flash(format!("Not a line number: {}", text)),
+        };
+        let result = match &mut self.focused_view_mut().frame().view {
+            View::Json(Some(view)) => view.jump_to_line(line),
+            View::Json(None) | View::Error(_) => return,
+        };
+        if let Err(line_count) = result {
+            self.set_flash(format!("There are only {} lines", line_count));
+        }
+    }
+    pub fn set_focused_mark(&mut self, mark: char) {
+        if !mark.is_ascii_alphabetic() {
+            return self.set_flash(format!("Marks are letters, not {:?}", mark));
+        }
+        if let View::Json(Some(view)) = &mut self.focused_view_mut().frame().view {
+            view.set_mark(mark);
+        }
+    }
+    pub fn jump_focused_to_mark(&mut self, mark: char) {
+        let jumped = match &mut self.focused_view_mut().frame().view {
+            View::Json(Some(view)) => view.jump_to_mark(mark),
+            View::Json(None) | View::Error(_) => return,
+        };
+        if !jumped {
+            self.set_flash(format!("No mark {:?}", mark));
+        }
+    }
+    // Opens the json in the clipboard as a new tree, then a structural diff of the focused view
+    // against it.
+    pub fn diff_focused_against_clipboard(&mut self, layout: JexLayout) {
+        if self.safe_mode {
+            return self.set_flash(disabled_in_safe_mode("Reading the clipboard"));
+        }
+        match read_clipboard() {
+            Ok(text) => self.diff_focused_against(&text, "Clipboard", layout),
+            Err(err) => self.set_flash(err),
+        }
+    }
+    // Like diff_focused_against_clipboard, with json from `source` that's already been read.
+    fn diff_focused_against(&mut self, text: &[u8], source: &str, layout: JexLayout) {
+        let described = source.to_lowercase();
+        let theirs = match InputFormat::Json.read_values(text) {
+            Ok(values) if !values.is_empty() => values,
+            Ok(_) => return self.set_flash(format!("There's no json in the {}", described)),
+            Err(err) => {
+                return self.set_flash(format!("Error parsing the {}:\n{}", described, err))
+            }
+        };
+        if let Some(err) = self
+            .focused_depth_error()
+            .or_else(|| self.depth_error(&theirs))
+        {
+            return self.set_flash(err);
+        }
+        let frame = self.focused_view().frame();
+        let ours = match &frame.view {
+            View::Json(Some(view)) => as_one_value(&view.values),
+            View::Json(None) | View::Error(_) => return,
+        };
+        let diff = diff::structural_diff(&ours, &as_one_value(&theirs));
+        let name = format!("Diff of {} and the {}", frame.name, described);
+        self.push_tree(ViewTree::new_from_values(theirs, source.to_owned(), layout));
+        self.push_tree(ViewTree::new_from_values(vec![diff], name, layout));
+    }
+    // Opens a structural diff of the values at marks `a` and `b` in the focused view as a new tree.
+    pub fn diff_focused_marks(&mut self, a: char, b: char, layout: JexLayout) {
+        if let Some(err) = self.focused_depth_error() {
+            return self.set_flash(err);
+        }
+        let frame = self.focused_view().frame();
+        let view = match &frame.view {
+            View::Json(Some(view)) => view,
+            View::Json(None) | View::Error(_) => return,
+        };
+        let value_at = |mark: char| {
+            let (top_index, path) = view.marks.get(&mark)?;
+            let cursor = LeafCursor::from_json_path(view.values.clone(), *top_index, path)?;
+            Some(cursor.focus)
+        };
+        let diff = match (value_at(a), value_at(b)) {
+            (Some(value_a), Some(value_b)) => Ok(diff::structural_diff(&value_a, &value_b)),
+            (None, _) => Err(a),
+            (_, None) => Err(b),
+        };
+        let name = format!("Diff of '{} and '{} in {}", a, b, frame.name);
+        match diff {
+            Ok(diff) => self.push_tree(ViewTree::new_from_values(vec![diff], name, layout)),
+            Err(mark) => self.set_flash(format!("No mark {:?}", mark)),
+        }
+    }
+    // With the scroll lock on, moves the unfocused pane's cursor as the focused one just moved.
+    // Each pane stops at its own end.
+    pub fn follow_scroll(&mut self, scroll: Scroll) {
+        if !self.scroll_lock {
+            return;
+        }
+        let mut other = match self.focus {
+            Focus::Left => self.right_view_mut(),
+            Focus::Right => self.left_view_mut(),
+        };
+        if let View::Json(Some(view)) = &mut other.frame().view {
+            view.scroll_by(scroll);
+        }
+    }
+    // Every view with edits that haven't been saved, to check before quitting.
+    pub fn unsaved_views(&self) -> Vec<ViewForestIndex> {
+        let mut unsaved = Vec::new();
+        for (tree, view_tree) in self.views.trees.iter().enumerate() {
+            for path in view_tree.unsaved_paths() {
+                unsaved.push(ViewForestIndex {
+                    tree,
+                    within_tree: ViewTreeIndex { path },
+                });
+            }
+        }
+        unsaved
+    }
+    // Lists the focused view's marks in a flash, to choose from when jumping.
+    pub fn show_focused_marks(&mut self) {
+        let marks = match &self.focused_view().frame().view {
+            View::Json(Some(view)) => view
+                .marks
+                .iter()
+                .map(|(mark, (top_index, path))| {
+                    let path: String = path.iter().map(|element| element.to_string()).collect();
+                    format!("{}  ${}{}", mark, top_index, path)
+                })
+                .collect::<Vec<_>>(),
+            View::Json(None) | View::Error(_) => Vec::new(),
+        };
+        if marks.is_empty() {
+            self.set_flash("No marks set. Press m and a letter to set one.".to_owned());
+        } else {
+            self.set_flash(marks.join("\n"));
+        }
+    }
+    pub fn focused_plain_text(&self, whole_document: bool) -> Option<Vec<String>> {
+        match &self.focused_view().frame().view {
+            View::Json(Some(view)) => Some(view.plain_text(whole_document)),
+            View::Json(None) | View::Error(_) => None,
+        }
+    }
+    // Writes the focused view's lines, as they're shown, to a text file.
+    pub fn export_focused(&self, path: &str, whole_document: bool) -> io::Result<()> {
+        let lines = match self.focused_plain_text(whole_document) {
+            Some(lines) => lines,
+            None => return Ok(()),
+        };
+        let mut file = fs::File::create(path)?;
+        for line in lines {
+            writeln!(file, "{}", line)?;
+        }
+        Ok(())
+    }
+    pub fn undo_focused(&mut self, layout: JexLayout) {
+        if self.refuses_edit() {
+            return;
+        }
+        if !self.change_focused_view(|view, _| view.undo(), layout) {
+            self.set_flash("Nothing to undo".to_owned());
+        }
+    }
+    pub fn redo_focused(&mut self, layout: JexLayout) {
+        if self.refuses_edit() {
+            return;
+        }
+        if !self.change_focused_view(|view, _| view.redo(), layout) {
+            self.set_flash("Nothing to redo".to_owned());
         }
     }
     pub fn re_root(&mut self, index: &ViewForestIndex) {
@@ -168,6 +1217,18 @@ impl App {
         };
         self.left_index.update_after_move(index, &destination);
         self.right_index.update_after_move(index, &destination);
+        let folds = std::mem::take(&mut self.views.folds);
+        self.views.folds = folds
+            .into_iter()
+            .map(|(tree, path)| {
+                let mut fold = ViewForestIndex {
+                    tree,
+                    within_tree: ViewTreeIndex { path },
+                };
+                fold.update_after_move(index, &destination);
+                (fold.tree, fold.within_tree.path)
+            })
+            .collect();
         trace!(
             "re_root:\nleft:{:#?}\nright:{:#?}\norigin:{:#?}\ndestination:{:#?}\ntree:{:#?}",
             self.left_index,
@@ -180,14 +1241,22 @@ impl App {
     pub fn render<B: tui::backend::Backend>(
         &self,
         mode: AppRenderMode,
+    ) -> impl FnMut(&mut Frame<B>) + '_ {
+        self.render_in(mode, None)
+    }
+    // Draws within `area`, or the whole frame if it's None.
+    pub fn render_in<B: tui::backend::Backend>(
+        &self,
+        mode: AppRenderMode,
+        area: Option<Rect>,
     ) -> impl FnMut(&mut Frame<B>) + '_ {
         let App { focus, .. } = self;
         let (left, right) = self.current_views();
         move |f| {
-            let size = f.size();
-            let layout = JexLayout::new(size, self.show_tree);
+            let size = area.unwrap_or_else(|| f.size());
+            let layout = self.layout(size);
             let left_block = Block::default()
-                .title(left.frame().name.to_owned())
+                .title(truncate_to_width(&left.title(), title_width(layout.left)))
                 .borders(Borders::ALL);
             let left_paragraph = left
                 .frame()
@@ -196,7 +1265,7 @@ impl App {
                 .block(left_block);
             f.render_widget(left_paragraph, layout.left);
             let right_block = Block::default()
-                .title(right.frame().name.to_owned())
+                .title(truncate_to_width(&right.title(), title_width(layout.right)))
                 .borders(Borders::ALL);
             let right_paragraph = right
                 .frame()
@@ -204,39 +1273,82 @@ impl App {
                 .render(right_block.inner(layout.right), *focus == Focus::Right)
                 .block(right_block);
             f.render_widget(right_paragraph, layout.right);
-            if let Some(tree_rect) = layout.tree {
-                let tree_block = Block::default().borders(Borders::ALL);
-                f.render_widget(
-                    self.views
-                        .render_tree(&self.left_index, &self.right_index)
-                        .block(tree_block),
-                    tree_rect,
-                );
+            match (layout.tree, &self.outline) {
+                (Some(tree_rect), Some(outline)) => {
+                    let outline_block = Block::default().title("Outline").borders(Borders::ALL);
+                    let entries = self.focused_outline_entries();
+                    let height = outline_block.inner(tree_rect).height;
+                    f.render_widget(
+                        outline.render(&entries, height).block(outline_block),
+                        tree_rect,
+                    );
+                }
+                (Some(tree_rect), None) => {
+                    let tree_block = Block::default().borders(Borders::ALL);
+                    f.render_widget(
+                        self.views
+                            .render_tree(&self.left_index, &self.right_index)
+                            .block(tree_block),
+                        tree_rect,
+                    );
+                }
+                (None, _) => {}
             }
-            match mode {
-                AppRenderMode::Normal => {
-                    let focused_view = match self.focus {
-                        Focus::Left => left,
-                        Focus::Right => right,
-                    };
-                    match focused_view {
-                        ViewWithParent::Root { .. } => {
-                            let placeholder = Paragraph::new("Root Node")
-                                .alignment(Alignment::Left)
-                                .wrap(Wrap { trim: false });
-                            f.render_widget(placeholder, layout.query);
-                        }
-                        ViewWithParent::Child { query, .. } => {
-                            let query = Paragraph::new(query.as_str())
-                                .alignment(Alignment::Left)
-                                .wrap(Wrap { trim: false });
-                            f.render_widget(query, layout.query);
-                        }
-                    }
+            let focused_view = match self.focus {
+                Focus::Left => left,
+                Focus::Right => right,
+            };
+            let segments = Self::breadcrumb_segments(&focused_view.frame().view);
+            let mut breadcrumb_rect = layout.breadcrumb;
+            let status: Vec<String> = self
+                .activity_status()
+                .into_iter()
+                .chain(self.search_status(&focused_view.frame().view))
+                .collect();
+            let message = self.status.as_ref().map(|(message, _)| message.as_str());
+            if message.is_some() || !status.is_empty() {
+                let mut status = vec![Span::raw(status.join("  "))];
+                if let Some(message) = message {
+                    let style = Style::default().fg(Color::Yellow);
+                    status.insert(0, Span::styled(format!("{}  ", message), style));
                 }
+                let status = Spans::from(status);
+                let status_width = std::cmp::min(status.width() as u16, breadcrumb_rect.width);
+                let status_rect = Rect {
+                    x: breadcrumb_rect.right() - status_width,
+                    width: status_width,
+                    ..breadcrumb_rect
+                };
+                breadcrumb_rect.width = breadcrumb_rect.width.saturating_sub(status_width + 1);
+                f.render_widget(Paragraph::new(status), status_rect);
+            }
+            let breadcrumb = elide_breadcrumb(&segments, breadcrumb_rect.width as usize);
+            let breadcrumb = Paragraph::new(breadcrumb).style(Style::default().fg(Color::Gray));
+            f.render_widget(breadcrumb, breadcrumb_rect);
+            match &mode {
+                AppRenderMode::Normal => match focused_view {
+                    ViewWithParent::Root { .. } => {
+                        let placeholder = Paragraph::new(ROOT_PLACEHOLDER)
+                            .style(Style::default().fg(Color::Gray))
+                            .alignment(Alignment::Left)
+                            .wrap(Wrap { trim: false });
+                        f.render_widget(placeholder, layout.query);
+                    }
+                    ViewWithParent::Child { query, .. } => {
+                        let query = Paragraph::new(query.as_str())
+                            .alignment(Alignment::Left)
+                            .wrap(Wrap { trim: false });
+                        f.render_widget(query, layout.query);
+                    }
+                },
                 AppRenderMode::InputEditor => {
                     f.set_cursor(0, layout.query.y);
                 }
+                AppRenderMode::Prompt(prompt) => {
+                    let x = layout.query.x + prompt.width() as u16;
+                    f.render_widget(Paragraph::new(prompt.as_str()), layout.query);
+                    f.set_cursor(std::cmp::min(x, layout.query.right()), layout.query.y);
+                }
             }
             if let Some(flash) = self.flash.as_ref() {
                 let area = layout::flash(size);
@@ -255,12 +1367,65 @@ impl App {
             }
         }
     }
+    // The path from the root to the cursor: `root`, or `$i` if the view has several values,
+    // followed by each key or index.
+    fn breadcrumb_segments(view: &View) -> Vec<String> {
+        let view = match view {
+            View::Json(Some(view)) => view,
+            View::Json(None) | View::Error(_) => return Vec::new(),
+        };
+        let root = if view.values.len() == 1 {
+            "root".to_owned()
+        } else {
+            format!("${}", view.cursor.top_index)
+        };
+        let path = view
+            .cursor
+            .to_json_path()
+            .into_iter()
+            .map(|element| match element {
+                PathElement::Key(key) => key,
+                PathElement::Index(i) => format!("[{}]", i),
+            });
+        std::iter::once(root).chain(path).collect()
+    }
+    // Where the cursor is among the search's matches, like `match 3 of 17`.
+    fn search_status(&self, view: &View) -> Option<String> {
+        let re = self.search_re.as_ref()?;
+        let view = match view {
+            View::Json(Some(view)) => view,
+            View::Json(None) | View::Error(_) => return None,
+        };
+        let mut cache = self.search_matches.borrow_mut();
+        let is_stale = match &*cache {
+            Some(cached) => {
+                cached.pattern != re.as_str()
+                    || !matches!(cached.values.upgrade(), Some(values) if Rc::ptr_eq(&values, &view.values))
+            }
+            None => true,
+        };
+        if is_stale {
+            *cache = Some(SearchMatches {
+                pattern: re.as_str().to_owned(),
+                values: Rc::downgrade(&view.values),
+                matches: LeafCursor::all_matches(view.values.clone(), re),
+            });
+        }
+        let matches = &cache.as_ref().expect("Just filled the cache").matches;
+        let cursor = view.cursor.to_path();
+        Some(match matches.iter().position(|path| *path == cursor) {
+            Some(i) => format!("match {} of {}", i + 1, matches.len()),
+            None if matches.len() == 1 => "1 match".to_owned(),
+            None => format!("{} matches", matches.len()),
+        })
+    }
     pub fn search(&mut self, reverse: bool) {
         let re = if let Some(re) = self.search_re.clone() {
             re
         } else {
-            return;
+            return self.set_status("No search yet: press / to search");
         };
+        let isolate = self.isolate_matches;
         let mut view_with_parents = self.focused_view_mut();
         let view_frame = view_with_parents.frame();
         let view = if let View::Json(Some(view)) = &mut view_frame.view {
@@ -273,18 +1438,94 @@ impl App {
         } else {
             view.cursor.clone().search(&re)
         };
-        if let Some(search_hit) = search_hit {
-            view.cursor = search_hit;
+        match search_hit {
+            Some(search_hit) => {
+                view.jump_to(search_hit);
+                if isolate {
+                    view.isolate_cursor();
+                }
+            }
+            None => self.set_status(format!("No matches for /{}/", re)),
+        }
+    }
+    // Turns isolating search matches on, folding everything but the way to the cursor, or off,
+    // leaving the folds as they are.
+    pub fn toggle_isolate_matches(&mut self) {
+        self.isolate_matches = !self.isolate_matches;
+        if !self.isolate_matches {
+            return self.set_status("Search matches are no longer isolated");
+        }
+        if let View::Json(Some(view)) = &mut self.focused_view_mut().frame().view {
+            view.isolate_cursor();
+        }
+        self.set_status("Isolating search matches: n and N fold everything else");
+    }
+    // Folds or unfolds the focused view's children in the edit tree, or with `all`, every view's.
+    pub fn toggle_view_tree_fold(&mut self, all: bool) {
+        let index = self.focused_index().clone();
+        let status = if all {
+            if self.views.toggle_fold_all() {
+                "Folded every view with queries under it"
+            } else {
+                "Unfolded the edit tree"
+            }
+        } else if self.views.toggle_fold(&index) {
+            "Toggled the fold in the edit tree"
         } else {
-            return;
+            "Only views with queries under them can be folded"
         };
-        view.unfold_around_cursor();
-        if !view
-            .visible_range(&view.folds)
-            .contains_value(&view.cursor.to_path())
-        {
-            view.scroll = GlobalCursor::new(view.values.clone(), view.rect.width, &view.folds)
-                .expect("values should still exist");
+        // Neither pane can be left on a view that's now hidden.
+        self.views.reveal(&mut self.left_index);
+        self.views.reveal(&mut self.right_index);
+        self.set_status(status);
+    }
+    pub fn search_origin(&self) -> Option<SearchOrigin> {
+        match &self.focused_view().frame().view {
+            View::Json(Some(view)) => Some(SearchOrigin {
+                cursor: view.cursor.clone(),
+                scroll: view.scroll.clone(),
+                folds: view.folds.clone(),
+                search_re: self.search_re.clone(),
+            }),
+            View::Json(None) | View::Error(_) => None,
+        }
+    }
+    // Searches for `pattern` as it's typed, from where the search started. Patterns that don't
+    // compile yet, like `a(`, leave the cursor where it is.
+    pub fn preview_search(&mut self, pattern: &str, origin: &SearchOrigin) {
+        let re = match Regex::new(pattern) {
+            Ok(re) => re,
+            Err(_) => return,
+        };
+        self.search_re = if pattern.is_empty() {
+            None
+        } else {
+            Some(re.clone())
+        };
+        let mut view_with_parents = self.focused_view_mut();
+        let view = match &mut view_with_parents.frame().view {
+            View::Json(Some(view)) => view,
+            View::Json(None) | View::Error(_) => return,
+        };
+        view.cursor = origin.cursor.clone();
+        view.scroll = origin.scroll.clone();
+        view.folds = origin.folds.clone();
+        if pattern.is_empty() {
+            return;
+        }
+        match origin.cursor.clone().search(&re) {
+            Some(search_hit) => view.jump_to(search_hit),
+            None => self.set_status(format!("No matches for /{}/", re)),
+        }
+    }
+    // Puts the cursor and the search back as they were before the search started.
+    pub fn cancel_search(&mut self, origin: SearchOrigin) {
+        self.search_re = origin.search_re;
+        let mut view_with_parents = self.focused_view_mut();
+        if let View::Json(Some(view)) = &mut view_with_parents.frame().view {
+            view.cursor = origin.cursor;
+            view.scroll = origin.scroll;
+            view.folds = origin.folds;
         }
     }
     pub fn resize(&mut self, layout: JexLayout) {
@@ -298,29 +1539,1583 @@ impl App {
             scroll: 0,
         });
     }
+    pub fn show_focused_info(&mut self) {
+        let info = match &self.focused_view().frame().view {
+            View::Json(Some(view)) => inspect::describe(&view.cursor.focus),
+            View::Json(None) | View::Error(_) => return,
+        };
+        self.set_flash(info);
+    }
+    // Checks the focused view's values against the schema in `schema_path`.
+    pub fn validate_focused(
+        &self,
+        schema_path: &str,
+    ) -> Result<Vec<ValidationError>, Box<dyn std::error::Error>> {
+        if let Some(err) = self.focused_depth_error() {
+            return Err(err.into());
+        }
+        let r = io::BufReader::new(fs::File::open(schema_path)?);
+        let schema_json: serde_json::Value = serde_json::from_reader(r)?;
+        match &self.focused_view().frame().view {
+            View::Json(Some(view)) => Ok(schema::validate(&schema_json, &view.values)?),
+            View::Json(None) | View::Error(_) => Ok(Vec::new()),
+        }
+    }
+    // Moves the focused view's cursor to `path`, returning whether it exists.
+    pub fn jump_focused_to(&mut self, top_index: usize, path: &[PathElement]) -> bool {
+        let mut view_with_parents = self.focused_view_mut();
+        let view = match &mut view_with_parents.frame().view {
+            View::Json(Some(view)) => view,
+            View::Json(None) | View::Error(_) => return false,
+        };
+        match LeafCursor::from_json_path(view.values.clone(), top_index, path) {
+            Some(cursor) => {
+                view.jump_to(cursor);
+                true
+            }
+            None => false,
+        }
+    }
+    // Moves the focused view's cursor to a path like `.a.b[0]`. If nothing's there, it says where
+    // the path stops matching and leaves the cursor at the top.
+    pub fn jump_focused_to_path(&mut self, text: &str) {
+        let path = match edit::parse_key_path(text) {
+            Ok(path) => path,
+            Err(err) => return self.set_flash(err),
+        };
+        if self.jump_focused_to(0, &path) {
+            return;
+        }
+        let found = match &self.focused_view().frame().view {
+            View::Json(Some(view)) => (0..path.len())
+                .rev()
+                .find(|&len| {
+                    LeafCursor::from_json_path(view.values.clone(), 0, &path[..len]).is_some()
+                })
+                .unwrap_or(0),
+            View::Json(None) | View::Error(_) => return,
+        };
+        self.jump_focused_to(0, &[]);
+        let prefix: String = path[..found].iter().map(|e| e.to_string()).collect();
+        self.set_flash(format!(
+            "Nothing at {}: .{} has no {}",
+            text.trim(),
+            prefix,
+            path[found]
+        ));
+    }
+    // Like jump_focused_to_path, but waits for the file if it's still loading.
+    pub fn jump_once_loaded(&mut self, path: String) {
+        if self.loading.is_some() {
+            self.pending_path = Some(path);
+        } else {
+            self.jump_focused_to_path(&path);
+        }
+    }
+    // The panel beside the views shows the outline if it's open, or else the edit tree if that's
+    // toggled on.
+    pub fn layout(&self, size: Rect) -> JexLayout {
+        JexLayout::new(size, self.show_tree || self.outline.is_some())
+    }
+    pub fn toggle_outline(&mut self) {
+        self.outline = match self.outline {
+            Some(_) => None,
+            None => Some(Outline::default()),
+        };
+    }
+    fn focused_outline_entries(&self) -> Vec<OutlineEntry> {
+        match (&self.outline, &self.focused_view().frame().view) {
+            (Some(outline), View::Json(Some(view))) => outline.entries(&view.values),
+            _ => Vec::new(),
+        }
+    }
+    fn selected_outline_entry(&self) -> Option<OutlineEntry> {
+        let selected = self.outline.as_ref()?.selected;
+        let mut entries = self.focused_outline_entries();
+        if entries.is_empty() {
+            return None;
+        }
+        Some(entries.swap_remove(selected.min(entries.len() - 1)))
+    }
+    pub fn move_outline_selection(&mut self, delta: isize) {
+        let len = self.focused_outline_entries().len();
+        if let Some(outline) = self.outline.as_mut() {
+            outline.move_selection(delta, len);
+        }
+    }
+    // Moves the focused view's cursor to the selected outline entry.
+    pub fn jump_to_outline_selection(&mut self) {
+        if let Some(entry) = self.selected_outline_entry() {
+            self.jump_focused_to(entry.top_index, &entry.path);
+        }
+    }
+    // Shows or hides the children of the selected outline entry, returning false if it has none.
+    pub fn expand_outline_selection(&mut self, expanded: bool) -> bool {
+        match (self.selected_outline_entry(), self.outline.as_mut()) {
+            (Some(entry), Some(outline)) => outline.set_expanded(&entry, expanded),
+            _ => false,
+        }
+    }
     pub fn show_help(&mut self) {
-        let controls = README
-            .rsplit("<!-- START CONTROLS POPUP -->\n")
-            .next()
-            .unwrap()
-            .split("<!-- END CONTROLS POPUP -->")
-            .next()
-            .unwrap();
-        self.set_flash(controls.to_string());
+        self.set_flash(controls_help().to_string());
     }
+    // The new tree is called `name`, or by its path if that's empty.
     pub fn open_file(
         &mut self,
         path: String,
+        name: String,
         layout: JexLayout,
     ) -> Result<(), Box<dyn std::error::Error>> {
-        let f = fs::File::open(&path)?;
-        let r = io::BufReader::new(f);
-        let new_tree = ViewTree::new_from_reader(r, path, layout)?;
-        self.views.trees.push(new_tree);
+        let r = formats::open_input(&path, self.lossy_utf8)?;
+        let format = InputFormat::from_path(&path);
+        let name = if name.trim().is_empty() {
+            path.clone()
+        } else {
+            name
+        };
+        let (values, duplicates) = format.read_checked(r)?;
+        let mut tree = ViewTree::new_from_values(values, name, layout);
+        tree.view_frame.source_path = Some(PathBuf::from(&path));
+        self.push_tree(tree);
+        self.report_size(self.views.trees.len() - 1);
+        self.restore_file_state(self.views.trees.len() - 1, &path, layout);
+        self.restore_fold_sidecar(self.views.trees.len() - 1, &path);
+        self.warn_duplicates(&duplicates);
+        Ok(())
+    }
+    // Picks up where the file at `path`, just loaded as tree `tree`, was left when it was last
+    // open: its folds, cursor and queries.
+    pub fn restore_file_state(&mut self, tree: usize, path: &str, layout: JexLayout) {
+        let states = match &self.file_states {
+            Some(states) => states,
+            None => return,
+        };
+        let tree = &mut self.views.trees[tree];
+        if states.restore(path, tree, &self.jq_options, layout) && tree.children.is_empty() {
+            // The right pane needs something to show.
+            tree.push_trivial_child(layout.right);
+        }
+    }
+    // Folds tree `tree`, just loaded from `path`, as its fold sidecar says, if it has one.
+    pub fn restore_fold_sidecar(&mut self, tree: usize, path: &str) {
+        let view = match &mut self.views.trees[tree].view_frame.view {
+            View::Json(Some(view)) => view,
+            View::Json(None) | View::Error(_) => return,
+        };
+        match session::load_fold_sidecar(path, view) {
+            Ok(Some(folds)) => {
+                let cursor = (view.cursor.top_index, view.cursor.to_json_path());
+                view.restore_position(&folds, &cursor);
+            }
+            Ok(None) => {}
+            Err(err) => self.set_flash(format!(
+                "Error loading folds from {}:\n{}",
+                session::fold_sidecar_path(path),
+                err
+            )),
+        }
+    }
+    // Why the focused view's values are too deeply nested for the commands that walk them
+    // recursively, if they are.
+    fn focused_depth_error(&self) -> Option<String> {
+        match &self.focused_view().frame().view {
+            View::Json(Some(view)) => self.depth_error(&view.values),
+            View::Json(None) | View::Error(_) => None,
+        }
+    }
+    fn depth_error(&self, values: &[JV]) -> Option<String> {
+        let max_depth = self.render_options.max_depth;
+        if values.iter().any(|jv| inspect::deeper_than(jv, max_depth)) {
+            Some(format!(
+                "This is nested more than {} levels deep, which is too deep for this command (see --max-depth)",
+                max_depth
+            ))
+        } else {
+            None
+        }
+    }
+    // Opens the result of transforming the focused view's values as a new tree.
+    pub fn open_transformed<F: FnOnce(&[JV]) -> Vec<JV>>(
+        &mut self,
+        name_prefix: &str,
+        transform: F,
+        layout: JexLayout,
+    ) {
+        if let Some(err) = self.focused_depth_error() {
+            return self.set_flash(err);
+        }
+        let frame = self.focused_view().frame();
+        let (values, name) = match &frame.view {
+            View::Json(Some(view)) => (
+                transform(&view.values),
+                format!("{} {}", name_prefix, frame.name),
+            ),
+            View::Json(None) | View::Error(_) => return,
+        };
+        self.push_tree(ViewTree::new_from_values(values, name, layout));
+    }
+    // Opens a copy of the value under the cursor as a new tree. Edits to a query's results are
+    // lost when the query reruns, but a root's are kept, so this is how to edit them.
+    pub fn copy_focused_to_root(&mut self, layout: JexLayout) {
+        let frame = self.focused_view().frame();
+        let (value, name) = match &frame.view {
+            View::Json(Some(view)) => {
+                let path = Self::breadcrumb_segments(&frame.view).join(BREADCRUMB_SEPARATOR);
+                (
+                    view.cursor.focus.clone(),
+                    format!("Copy of {} in {}", path, frame.name),
+                )
+            }
+            View::Json(None) | View::Error(_) => return,
+        };
+        self.push_tree(ViewTree::new_from_values(vec![value], name, layout));
+    }
+    // Opens the parts of the focused view's values that match the search as a new tree.
+    pub fn open_search_matches(&mut self, layout: JexLayout) {
+        let re = match self.search_re.clone() {
+            Some(re) => re,
+            None => return self.set_flash("Search for something first".to_owned()),
+        };
+        if let Some(err) = self.focused_depth_error() {
+            return self.set_flash(err);
+        }
+        let frame = self.focused_view().frame();
+        let values: Vec<JV> = match &frame.view {
+            View::Json(Some(view)) => view
+                .values
+                .iter()
+                .filter_map(|jv| grep::grep(jv, &re))
+                .collect(),
+            View::Json(None) | View::Error(_) => return,
+        };
+        if values.is_empty() {
+            return self.set_flash(format!("Nothing matches /{}/", re));
+        }
+        let name = format!("Matching /{}/ in {}", re, frame.name);
+        self.push_tree(ViewTree::new_from_values(values, name, layout));
+    }
+    // Applies `options` to every view, and to views opened later.
+    pub fn set_render_options(&mut self, options: RenderOptions) {
+        self.render_options = options;
+        for tree in self.views.trees.iter_mut() {
+            tree.set_render_options(options);
+        }
+    }
+    fn push_tree(&mut self, mut tree: ViewTree) {
+        tree.set_render_options(self.render_options);
+        self.views.trees.push(tree);
         self.left_index = ViewForestIndex {
             tree: self.views.trees.len() - 1,
             within_tree: ViewTreeIndex { path: Vec::new() },
         };
+    }
+    // Runs `command` through the shell with the focused value on stdin. Json output is opened as
+    // a new tree, anything else is shown in a flash. A command that stops reading early, like
+    // `head`, still has its output shown, with a status saying so.
+    pub fn pipe_focused_value(
+        &mut self,
+        command: String,
+        layout: JexLayout,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        if self.safe_mode {
+            return Err(disabled_in_safe_mode("Piping to a command").into());
+        }
+        // Strings are written raw, like `jq -r` would, so they can be piped to things like
+        // `base64 -d`.
+        let input = match &self.focused_view().frame().view {
+            View::Json(Some(view)) => match &view.cursor.focus {
+                JV::String(s) => format!("{}\n", s.value()).into_bytes(),
+                focus => serde_json::to_vec_pretty(focus)?,
+            },
+            View::Json(None) | View::Error(_) => return Ok(()),
+        };
+        let mut child = Command::new("sh")
+            .arg("-c")
+            .arg(&command)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()?;
+        // Write from another thread so a command that fills its stdout pipe before reading all of
+        // stdin can't deadlock us.
+        let mut stdin = child.stdin.take().expect("stdin should be piped");
+        let writer = thread::spawn(move || stdin.write_all(&input));
+        let output = child.wait_with_output()?;
+        let write_error = writer.join().expect("stdin writer panicked").err();
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            let mut message = format!("`{}` failed ({}):\n{}", command, output.status, stderr);
+            if let Some(err) = write_error {
+                message.push_str(&format!("\nWriting the value to it failed too: {}", err));
+            }
+            return Err(message.into());
+        }
+        match write_error {
+            Some(err) if err.kind() == io::ErrorKind::BrokenPipe => {
+                self.set_status(format!("`{}` didn't read all of the value", command));
+            }
+            Some(err) => {
+                return Err(format!("Couldn't write the value to `{}`: {}", command, err).into())
+            }
+            None => {}
+        }
+        match InputFormat::Json.read_values(output.stdout.as_slice()) {
+            Ok(values) if !values.is_empty() => {
+                let tree = ViewTree::new_from_values(values, command, layout);
+                self.push_tree(tree);
+            }
+            _ if output.stdout.is_empty() => {
+                self.set_flash("(no output)".to_owned());
+            }
+            _ => {
+                self.set_flash(String::from_utf8_lossy(&output.stdout).into_owned());
+            }
+        }
         Ok(())
     }
 }
+
+// Values to diff as one: the value itself if there's just one, or else an array of them.
+fn as_one_value(values: &[JV]) -> JV {
+    if let [value] = values {
+        return value.clone();
+    }
+    let mut arr = JVArray::new();
+    for value in values {
+        arr.set(arr.len(), value.clone());
+    }
+    arr.into()
+}
+
+// Commands that print the clipboard, tried in order until one is installed.
+const PASTE_COMMANDS: &[&[&str]] = &[
+    &["pbpaste"],
+    &["wl-paste", "--no-newline"],
+    &["xclip", "-selection", "clipboard", "-out"],
+    &["xsel", "--clipboard", "--output"],
+    &["powershell", "-NoProfile", "-Command", "Get-Clipboard"],
+];
+
+fn read_clipboard() -> Result<Vec<u8>, String> {
+    for command in PASTE_COMMANDS {
+        let output = match Command::new(command[0]).args(&command[1..]).output() {
+            Ok(output) => output,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => continue,
+            Err(err) => return Err(format!("Error running {}:\n{}", command[0], err)),
+        };
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            return Err(format!(
+                "`{}` failed ({}):\n{}",
+                command.join(" "),
+                output.status,
+                stderr
+            ));
+        }
+        return Ok(output.stdout);
+    }
+    Err("Couldn't read the clipboard: install pbpaste, wl-paste, xclip or xsel".to_owned())
+}
+
+// Quotes a word for sh, unless it's plain enough not to need it.
+fn shell_quote(word: &str) -> String {
+    let plain = |c: char| c.is_ascii_alphanumeric() || "_-./=:,+@%".contains(c);
+    if !word.is_empty() && word.chars().all(plain) {
+        word.to_owned()
+    } else {
+        format!("'{}'", word.replace('\'', "'\\''"))
+    }
+}
+
+// How much of a pane's top border its title can use.
+fn title_width(pane: Rect) -> usize {
+    pane.width.saturating_sub(2) as usize
+}
+
+// Cuts `s` off with `…` if it's wider than `width` columns.
+fn truncate_to_width(s: &str, width: usize) -> String {
+    if s.width() <= width {
+        return s.to_owned();
+    }
+    let mut truncated = String::new();
+    let mut used = 0;
+    for c in s.chars() {
+        let c_width = c.width().unwrap_or(0);
+        if used + c_width + 1 > width {
+            break;
+        }
+        truncated.push(c);
+        used += c_width;
+    }
+    if width > 0 {
+        truncated.push('…');
+    }
+    truncated
+}
+
+const BREADCRUMB_SEPARATOR: &str = " › ";
+
+// Joins breadcrumb segments, replacing segments after the first with `…` until the rest fit in
+// `width` columns.
+fn elide_breadcrumb(segments: &[String], width: usize) -> String {
+    let full = segments.join(BREADCRUMB_SEPARATOR);
+    if full.width() <= width || segments.len() <= 2 {
+        return full;
+    }
+    let (first, rest) = segments
+        .split_first()
+        .expect("Checked that there are segments");
+    for skip in 1..rest.len() {
+        let elided = std::iter::once(first.as_str())
+            .chain(std::iter::once("…"))
+            .chain(rest[skip..].iter().map(|segment| segment.as_str()))
+            .collect::<Vec<_>>()
+            .join(BREADCRUMB_SEPARATOR);
+        if elided.width() <= width {
+            return elided;
+        }
+    }
+    // Always show the focused segment, even if the bar clips it.
+    format!(
+        "{}{sep}…{sep}{}",
+        first,
+        rest[rest.len() - 1],
+        sep = BREADCRUMB_SEPARATOR
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        elide_breadcrumb, refuses_to_save_over, time_query, truncate_to_width, App, AppRenderMode,
+        Focus, RunningQuery, STATUS_DURATION,
+    };
+    use crate::{
+        cursor::PathElement,
+        events::{TaskProgress, TaskState},
+        jq::{
+            jv::{JVString, JV},
+            query::JQOptions,
+        },
+        layout::JexLayout,
+        lines::AutoRaw,
+        view_tree::{Scroll, View, ViewWithParent, ViewWithParentMut},
+    };
+    use regex::Regex;
+    use serde_json::{json, Value};
+    use std::{path::PathBuf, time::Duration};
+    use tui::{backend::TestBackend, layout::Rect, Terminal};
+    const DUMMY_RECT: Rect = Rect {
+        x: 0,
+        y: 0,
+        width: 100,
+        height: 50,
+    };
+    fn focused_json_path(app: &App) -> Vec<PathElement> {
+        match &app.focused_view().frame().view {
+            View::Json(Some(view)) => view.cursor.to_json_path(),
+            _ => panic!("Expected a json view"),
+        }
+    }
+    // The cursor, top line and folds of the view in `pane`.
+    fn pane_state(app: &App, pane: Focus) -> (Vec<PathElement>, Vec<PathElement>, usize) {
+        let index = match pane {
+            Focus::Left => &app.left_index,
+            Focus::Right => &app.right_index,
+        };
+        match &app.views.index(index).unwrap().frame().view {
+            View::Json(Some(view)) => (
+                view.cursor.to_json_path(),
+                view.scroll.value_cursor.to_json_path(),
+                view.folds.len(),
+            ),
+            _ => panic!("Expected a json view"),
+        }
+    }
+    #[test]
+    fn unit_scroll_lock() {
+        let layout = JexLayout::new(DUMMY_RECT, false);
+        let json = serde_json::to_vec(&json!((0..200).collect::<Vec<_>>())).unwrap();
+        let mut app = App::new(&json[..], "test.json".to_owned(), layout).unwrap();
+        app.set_right_query(".[:3]".to_owned(), layout);
+        app.finish_query(layout);
+        let page_down = |app: &mut App| {
+            if let View::Json(Some(view)) = &mut app.focused_view_mut().frame().view {
+                view.scroll_by(Scroll::PageDown(2));
+            }
+            app.follow_scroll(Scroll::PageDown(2));
+        };
+        page_down(&mut app);
+        assert_eq!(pane_state(&app, Focus::Right).0, vec![]);
+        app.scroll_lock = true;
+        page_down(&mut app);
+        assert_eq!(pane_state(&app, Focus::Left).0, vec![PathElement::Index(3)]);
+        assert_eq!(
+            pane_state(&app, Focus::Right).0,
+            vec![PathElement::Index(1)]
+        );
+        // The other pane stops at its end, and the lock works from either side.
+        app.focus = app.focus.swap();
+        page_down(&mut app);
+        page_down(&mut app);
+        assert_eq!(pane_state(&app, Focus::Right).0, vec![]);
+        assert_eq!(pane_state(&app, Focus::Left).0, vec![PathElement::Index(7)]);
+    }
+    #[test]
+    fn unit_independent_panes() {
+        let layout = JexLayout::new(DUMMY_RECT, false);
+        let json = serde_json::to_vec(&json!({ "a": (0..200).collect::<Vec<_>>() })).unwrap();
+        let mut app = App::new(&json[..], "test.json".to_owned(), layout).unwrap();
+        let a = |i| vec![PathElement::Key("a".to_owned()), PathElement::Index(i)];
+        assert!(app.jump_focused_to(0, &a(150)));
+        let left = pane_state(&app, Focus::Left);
+        app.focus = Focus::Right;
+        if let View::Json(Some(view)) = &mut app.focused_view_mut().frame().view {
+            view.page_down(None);
+            view.page_down(None);
+            for _ in 0..3 {
+                view.advance_cursor();
+            }
+        }
+        let right = pane_state(&app, Focus::Right);
+        assert_ne!(right.0, right.1);
+        assert_eq!(pane_state(&app, Focus::Left), left);
+        // Editing the left pane reruns the right pane's query, which keeps its place.
+        app.focus = Focus::Left;
+        app.edit_focused_leaf("\"edited\"", layout);
+        assert_eq!(pane_state(&app, Focus::Right), right);
+        app.undo_focused(layout);
+        assert_eq!(pane_state(&app, Focus::Right), right);
+        assert_eq!(pane_state(&app, Focus::Left), left);
+    }
+    #[test]
+    fn unit_recompute_keeps_cursor() {
+        let layout = JexLayout::new(DUMMY_RECT, false);
+        let mut app = App::new(
+            &br#"{"a": {"x": 1, "y": 2}, "b": 3}"#[..],
+            "test.json".to_owned(),
+            layout,
+        )
+        .unwrap();
+        app.focus = Focus::Right;
+        let path = vec![
+            PathElement::Key("a".to_owned()),
+            PathElement::Key("y".to_owned()),
+        ];
+        assert!(app.jump_focused_to(0, &path));
+        *app.focused_query_mut().unwrap() = "del(.b)".to_owned();
+        app.recompute_focused_view(layout);
+        assert_eq!(focused_json_path(&app), path);
+        *app.focused_query_mut().unwrap() = ".b".to_owned();
+        app.recompute_focused_view(layout);
+        assert_eq!(focused_json_path(&app), vec![]);
+    }
+    fn focused_json_folds(app: &App) -> Vec<(usize, Vec<PathElement>)> {
+        match &app.focused_view().frame().view {
+            View::Json(Some(view)) => view.json_folds(),
+            _ => panic!("Expected a json view"),
+        }
+    }
+    #[test]
+    fn unit_recompute_keeps_folds() {
+        let layout = JexLayout::new(DUMMY_RECT, false);
+        let mut app = App::new(
+            &br#"{"a": {"x": 1}, "b": [2], "c": [3]}"#[..],
+            "test.json".to_owned(),
+            layout,
+        )
+        .unwrap();
+        app.focus = Focus::Right;
+        for key in &["b", "c"] {
+            assert!(app.jump_focused_to(0, &[PathElement::Key(key.to_string())]));
+            if let View::Json(Some(view)) = &mut app.focused_view_mut().frame().view {
+                view.toggle_fold();
+            }
+        }
+        *app.focused_query_mut().unwrap() = "del(.a, .c)".to_owned();
+        app.recompute_focused_view(layout);
+        assert_eq!(
+            focused_json_folds(&app),
+            vec![(0, vec![PathElement::Key("b".to_owned())])]
+        );
+    }
+    fn focused_values(app: &App) -> Vec<Value> {
+        match &app.focused_view().frame().view {
+            View::Json(Some(view)) => view.values.iter().map(|v| v.into()).collect(),
+            _ => panic!("Expected a json view"),
+        }
+    }
+    #[test]
+    fn unit_edit_leaf() {
+        let layout = JexLayout::new(DUMMY_RECT, false);
+        let mut app = App::new(&br#"{"a": [1, 2]}"#[..], "test.json".to_owned(), layout).unwrap();
+        let path = vec![PathElement::Key("a".to_owned()), PathElement::Index(1)];
+        assert!(app.jump_focused_to(0, &path));
+        assert_eq!(app.focused_leaf_text(), Some("2".to_owned()));
+        app.edit_focused_leaf("\"two\"", layout);
+        assert_eq!(focused_values(&app), vec![json!({"a": [1.0, "two"]})]);
+        assert_eq!(focused_json_path(&app), path);
+        // Invalid edits leave the value alone.
+        app.edit_focused_leaf("[3]", layout);
+        assert!(app.flash.is_some());
+        assert_eq!(focused_values(&app), vec![json!({"a": [1.0, "two"]})]);
+        // The query below the edited view is rerun.
+        app.focus = Focus::Right;
+        assert_eq!(focused_values(&app), vec![json!({"a": [1.0, "two"]})]);
+        app.focus = Focus::Left;
+        app.undo_focused(layout);
+        assert_eq!(focused_values(&app), vec![json!({"a": [1.0, 2.0]})]);
+    }
+    #[test]
+    fn unit_unsaved_views() {
+        let layout = JexLayout::new(DUMMY_RECT, false);
+        let mut app = App::new(&b"[1, 2, 3]"[..], "test.json".to_owned(), layout).unwrap();
+        assert!(app.unsaved_views().is_empty());
+        assert!(app.jump_focused_to(0, &[PathElement::Index(0)]));
+        app.delete_focused(layout);
+        let paths: Vec<(usize, Vec<usize>)> = app
+            .unsaved_views()
+            .into_iter()
+            .map(|index| (index.tree, index.within_tree.path))
+            .collect();
+        assert_eq!(paths, vec![(0, vec![])]);
+    }
+    #[test]
+    fn unit_query_results_not_edited() {
+        let layout = JexLayout::new(DUMMY_RECT, false);
+        let mut app = App::new(&b"[1, 2, 3]"[..], "test.json".to_owned(), layout).unwrap();
+        app.focus = Focus::Right;
+        assert!(app.jump_focused_to(0, &[PathElement::Index(0)]));
+        app.delete_focused(layout);
+        assert!(app.flash.is_some());
+        assert_eq!(focused_values(&app), vec![json!([1.0, 2.0, 3.0])]);
+        app.flash = None;
+        app.undo_focused(layout);
+        assert!(app.flash.is_some());
+        // Changing the parent reruns the query, with nothing of the child's to lose.
+        app.focus = Focus::Left;
+        assert!(app.jump_focused_to(0, &[PathElement::Index(2)]));
+        app.delete_focused(layout);
+        app.focus = Focus::Right;
+        assert_eq!(focused_values(&app), vec![json!([1.0, 2.0])]);
+        assert!(app
+            .unsaved_views()
+            .iter()
+            .all(|index| index.within_tree.path.is_empty()));
+    }
+    #[test]
+    fn unit_focused_jq_command() {
+        let layout = JexLayout::new(DUMMY_RECT, false);
+        let path = std::env::temp_dir().join(format!("jex jq command {}.json", std::process::id()));
+        std::fs::write(&path, "[1, 2]").unwrap();
+        let mut app = App::new(&b"[1, 2]"[..], "my data.json".to_owned(), layout).unwrap();
+        // The title isn't necessarily a file.
+        assert_eq!(app.focused_jq_command(), "jq '.' <input>");
+        app.views.trees[0].view_frame.source_path = Some(path.clone());
+        let quoted_path = format!("'{}'", path.to_str().unwrap());
+        assert_eq!(app.focused_jq_command(), format!("jq '.' {}", quoted_path));
+        app.jq_options = JQOptions {
+            library_paths: vec!["lib".to_owned()],
+            named_args: vec![
+                ("who".to_owned(), JVString::new("it's").into()),
+                ("n".to_owned(), (&json!([1])).into()),
+            ],
+        };
+        app.set_right_query("map(. + $n[0]) # add".to_owned(), layout);
+        app.focus = Focus::Right;
+        app.views.trees[0].children[0]
+            .1
+            .push_trivial_child(layout.right);
+        app.right_index.within_tree.path.push(0);
+        assert_eq!(
+            app.focused_jq_command(),
+            format!(
+                "jq -L lib --arg who 'it'\\''s' --argjson n '[1.0]' 'map(. + $n[0]) # add\n| .' {}",
+                quoted_path
+            )
+        );
+        std::fs::remove_file(&path).unwrap();
+    }
+    #[test]
+    fn unit_delete() {
+        let layout = JexLayout::new(DUMMY_RECT, false);
+        let mut app = App::new(
+            &br#"[1, 2, 3] {"a": 4}"#[..],
+            "test.json".to_owned(),
+            layout,
+        )
+        .unwrap();
+        assert!(app.jump_focused_to(0, &[PathElement::Index(2)]));
+        app.delete_focused(layout);
+        assert_eq!(
+            focused_values(&app),
+            vec![json!([1.0, 2.0]), json!({"a": 4.0})]
+        );
+        assert_eq!(focused_json_path(&app), vec![PathElement::Index(1)]);
+        assert_eq!(app.focused_view().frame().title(), "test.json (modified)");
+        assert!(app.jump_focused_to(1, &[]));
+        app.delete_focused(layout);
+        assert_eq!(focused_values(&app), vec![json!([1.0, 2.0])]);
+        app.delete_focused(layout);
+        assert!(app.flash.is_some());
+        assert_eq!(focused_values(&app), vec![json!([1.0, 2.0])]);
+    }
+    #[test]
+    fn unit_autosave() {
+        let layout = JexLayout::new(DUMMY_RECT, false);
+        let dir = std::env::temp_dir();
+        let json_path = dir.join(format!("jex_autosave_{}.json", std::process::id()));
+        let yaml_path = dir.join(format!("jex_autosave_{}.yaml", std::process::id()));
+        let json_name = json_path.to_str().unwrap().to_owned();
+        // (title, source path, file that mustn't change unless it's the source, saved)
+        let cases = [
+            ("Data", Some(&json_path), &json_path, true),
+            // It'd be saved as json, so yaml is left alone.
+            ("Data", Some(&yaml_path), &yaml_path, false),
+            // A title that happens to be a file isn't where the view came from.
+            (json_name.as_str(), None, &json_path, false),
+        ];
+        for &(name, source_path, file, saved) in cases.iter() {
+            std::fs::write(file, "[1, 2]").unwrap();
+            let mut app = App::new(&b"[1, 2]"[..], name.to_owned(), layout).unwrap();
+            app.views.trees[0].view_frame.source_path = source_path.cloned();
+            app.autosave = Some(Duration::from_secs(0));
+            assert!(app.jump_focused_to(0, &[PathElement::Index(1)]));
+            app.delete_focused(layout);
+            // Paused while read-only.
+            app.read_only = true;
+            assert!(!app.autosave_pending());
+            app.tick(layout);
+            assert_eq!(std::fs::read_to_string(file).unwrap(), "[1, 2]");
+            app.read_only = false;
+            assert!(app.autosave_pending());
+            app.tick(layout);
+            assert!(!app.autosave_pending());
+            let contents = std::fs::read_to_string(file).unwrap();
+            std::fs::remove_file(file).unwrap();
+            if saved {
+                assert!(!contents.contains('2'));
+                assert_eq!(app.focused_view().frame().title(), name);
+            } else {
+                assert_eq!(contents, "[1, 2]");
+                assert!(app.focused_view().frame().title().ends_with("(modified)"));
+            }
+        }
+    }
+    #[test]
+    fn unit_repeat_last_query() {
+        let layout = JexLayout::new(DUMMY_RECT, false);
+        let mut app = App::new(&br#"{"a": 1}"#[..], "test.json".to_owned(), layout).unwrap();
+        app.repeat_last_query(layout);
+        assert_eq!(app.status.as_ref().unwrap().0, "No query to repeat yet");
+        app.set_right_query(".a".to_owned(), layout);
+        app.finish_query(layout);
+        // The left pane is on the root, so it gets a new view to run the query in.
+        app.repeat_last_query(layout);
+        app.finish_query(layout);
+        assert_eq!(app.left_index.within_tree.path, vec![1]);
+        assert_eq!(app.focused_query().unwrap(), ".a");
+        assert_eq!(focused_values(&app), vec![json!(1.0)]);
+        *app.focused_query_mut().unwrap() = ".a + 1".to_owned();
+        app.recompute_focused_view(layout);
+        app.finish_query(layout);
+        app.focus = Focus::Right;
+        app.repeat_last_query(layout);
+        app.finish_query(layout);
+        assert_eq!(app.focused_query().unwrap(), ".a + 1");
+        assert_eq!(focused_values(&app), vec![json!(2.0)]);
+    }
+    #[test]
+    fn unit_duplicate() {
+        let layout = JexLayout::new(DUMMY_RECT, false);
+        let mut app = App::new(&br#"{"a": [1]}"#[..], "test.json".to_owned(), layout).unwrap();
+        let path = vec![PathElement::Key("a".to_owned()), PathElement::Index(0)];
+        assert!(app.jump_focused_to(0, &path));
+        app.duplicate_focused(layout);
+        assert_eq!(focused_values(&app), vec![json!({"a": [1.0, 1.0]})]);
+        assert_eq!(focused_json_path(&app)[1], PathElement::Index(1));
+        assert!(app.jump_focused_to(0, &[]));
+        app.duplicate_focused(layout);
+        assert_eq!(focused_values(&app).len(), 2);
+        assert_eq!(focused_json_path(&app), vec![]);
+    }
+    #[test]
+    fn unit_sort() {
+        let layout = JexLayout::new(DUMMY_RECT, false);
+        let json = br#"[{"a": 2}, {"a": 3}, {"a": 1}]"#;
+        let mut app = App::new(&json[..], "test.json".to_owned(), layout).unwrap();
+        let path = vec![PathElement::Index(0), PathElement::Key("a".to_owned())];
+        assert!(app.jump_focused_to(0, &path));
+        app.sort_focused(".a", layout);
+        assert_eq!(
+            focused_values(&app),
+            vec![json!([{"a": 1.0}, {"a": 2.0}, {"a": 3.0}])]
+        );
+        assert_eq!(focused_json_path(&app)[0], PathElement::Index(1));
+        app.sort_focused("-a", layout);
+        assert_eq!(
+            focused_values(&app),
+            vec![json!([{"a": 3.0}, {"a": 2.0}, {"a": 1.0}])]
+        );
+        assert_eq!(focused_json_path(&app)[0], PathElement::Index(1));
+    }
+    #[test]
+    fn unit_breadcrumb() {
+        let layout = JexLayout::new(DUMMY_RECT, false);
+        let json = br#"{"data": {"users": [0, 1, 2, {"name": "x"}]}}"#;
+        let mut app = App::new(&json[..], "test.json".to_owned(), layout).unwrap();
+        let path = vec![
+            PathElement::Key("data".to_owned()),
+            PathElement::Key("users".to_owned()),
+            PathElement::Index(3),
+            PathElement::Key("name".to_owned()),
+        ];
+        assert!(app.jump_focused_to(0, &path));
+        let segments = App::breadcrumb_segments(&app.focused_view().frame().view);
+        assert_eq!(segments, vec!["root", "data", "users", "[3]", "name"]);
+        assert_eq!(
+            elide_breadcrumb(&segments, 100),
+            "root › data › users › [3] › name"
+        );
+        assert_eq!(elide_breadcrumb(&segments, 21), "root › … › [3] › name");
+        assert_eq!(elide_breadcrumb(&segments, 5), "root › … › name");
+    }
+    #[test]
+    fn unit_pane_titles() {
+        let layout = JexLayout::new(DUMMY_RECT, false);
+        let mut app = App::new(&br#"{"a": 1}"#[..], "test.json".to_owned(), layout).unwrap();
+        app.set_right_query(".a\n  | tostring".to_owned(), layout);
+        let (left, right) = app.current_views();
+        assert_eq!(left.title(), "test.json");
+        assert_eq!(right.title(), "New Query: .a | tostring");
+        assert_eq!(truncate_to_width(&right.title(), 12), "New Query: …");
+        assert_eq!(truncate_to_width("test.json", 9), "test.json");
+    }
+    #[test]
+    fn unit_fold_matching() {
+        let layout = JexLayout::new(DUMMY_RECT, false);
+        let json = br#"[{"meta": {"x": 1}, "y": 2}, {"meta": [3]}]"#;
+        let mut app = App::new(&json[..], "test.json".to_owned(), layout).unwrap();
+        let path = vec![
+            PathElement::Index(1),
+            PathElement::Key("meta".to_owned()),
+            PathElement::Index(0),
+        ];
+        assert!(app.jump_focused_to(0, &path));
+        app.fold_focused_matching("[*].meta");
+        assert_eq!(focused_json_folds(&app).len(), 2);
+        // The cursor was hidden by a fold, so it moves onto it.
+        assert_eq!(focused_json_path(&app), path[..2].to_vec());
+        app.fold_focused_matching("**.nothing");
+        assert!(app.flash.is_some());
+    }
+    #[test]
+    fn unit_view_tree_folds() {
+        let layout = JexLayout::new(DUMMY_RECT, true);
+        let mut app = App::new(&b"[1, 2]"[..], "test.json".to_owned(), layout).unwrap();
+        // test.json
+        // ├ New Query
+        // │ └ New Query
+        // └ New Query
+        app.views.trees[0].children[0]
+            .1
+            .push_trivial_child(layout.right);
+        app.views.trees[0].push_trivial_child(layout.right);
+        app.focus = Focus::Right;
+        app.toggle_view_tree_fold(false);
+        assert!(app.views.is_folded(0, &[0]));
+        app.left_index.advance(&app.views);
+        assert_eq!(app.left_index.within_tree.path, vec![0]);
+        app.left_index.advance(&app.views);
+        assert_eq!(app.left_index.within_tree.path, vec![1]);
+        app.left_index.regress(&app.views);
+        assert_eq!(app.left_index.within_tree.path, vec![0]);
+        app.show_tree = true;
+        let mut terminal = Terminal::new(TestBackend::new(100, 50)).unwrap();
+        terminal.draw(app.render(AppRenderMode::Normal)).unwrap();
+        // Unfolding shows the grandchild again.
+        app.toggle_view_tree_fold(false);
+        app.left_index.advance(&app.views);
+        assert_eq!(app.left_index.within_tree.path, vec![0, 0]);
+        // Panes on views that get hidden move up to the folded view.
+        app.toggle_view_tree_fold(true);
+        assert_eq!(app.left_index.within_tree.path, Vec::<usize>::new());
+        assert_eq!(app.right_index.within_tree.path, Vec::<usize>::new());
+        assert!(app.left_index.advance(&app.views).is_none());
+        app.toggle_view_tree_fold(true);
+        assert!(app.views.folds.is_empty());
+        app.right_index.within_tree.path = vec![1];
+        app.toggle_view_tree_fold(false);
+        assert_eq!(
+            app.status.as_ref().unwrap().0,
+            "Only views with queries under them can be folded"
+        );
+        // Folds follow views moved into their own trees.
+        app.right_index.within_tree.path = vec![0];
+        app.toggle_view_tree_fold(false);
+        let index = app.right_index.clone();
+        app.re_root(&index);
+        assert!(app.views.is_folded(1, &[]));
+        assert_eq!(app.views.folds.len(), 1);
+    }
+    #[test]
+    fn unit_isolate_matches() {
+        let layout = JexLayout::new(DUMMY_RECT, false);
+        let json = br#"{"a": {"x": "cat", "y": [1]}, "b": [2], "c": ["cat", {"z": 3}]}"#;
+        let mut app = App::new(&json[..], "test.json".to_owned(), layout).unwrap();
+        let key = |k: &str| PathElement::Key(k.to_owned());
+        let sorted_folds = |app: &App| {
+            let mut folds = focused_json_folds(app);
+            folds.sort_by_key(|(_, path)| format!("{:?}", path));
+            folds
+        };
+        app.search_re = Some(Regex::new("cat").unwrap());
+        app.toggle_isolate_matches();
+        app.search(false);
+        assert_eq!(focused_json_path(&app), vec![key("a"), key("x")]);
+        assert_eq!(
+            sorted_folds(&app),
+            vec![
+                (0, vec![key("a"), key("y")]),
+                (0, vec![key("b")]),
+                (0, vec![key("c")]),
+            ]
+        );
+        // The next match is isolated in turn.
+        app.search(false);
+        assert_eq!(
+            focused_json_path(&app),
+            vec![key("c"), PathElement::Index(0)]
+        );
+        assert_eq!(
+            sorted_folds(&app),
+            vec![
+                (0, vec![key("a")]),
+                (0, vec![key("b")]),
+                (0, vec![key("c"), PathElement::Index(1)]),
+            ]
+        );
+        app.toggle_isolate_matches();
+        assert!(!app.isolate_matches);
+    }
+    #[test]
+    fn unit_search_status() {
+        let layout = JexLayout::new(DUMMY_RECT, false);
+        let json = br#"{"a": "cat", "b": ["dog", "catalog"], "c": 1}"#;
+        let mut app = App::new(&json[..], "test.json".to_owned(), layout).unwrap();
+        let status = |app: &App| app.search_status(&app.focused_view().frame().view);
+        assert_eq!(status(&app), None);
+        app.search_re = Some(Regex::new("cat").unwrap());
+        assert_eq!(status(&app).unwrap(), "2 matches");
+        app.search(false);
+        assert_eq!(status(&app).unwrap(), "match 1 of 2");
+        app.search(false);
+        assert_eq!(status(&app).unwrap(), "match 2 of 2");
+        // Edits invalidate the count.
+        app.delete_focused(layout);
+        assert_eq!(status(&app).unwrap(), "1 match");
+    }
+    #[test]
+    fn unit_query_hidden_parent() {
+        let layout = JexLayout::new(DUMMY_RECT, false);
+        let json = br#"{"a": {"b": [1, 2, 3]}, "b": [4]}"#;
+        let mut app = App::new(&json[..], "test.json".to_owned(), layout).unwrap();
+        app.set_right_query(".a".to_owned(), layout);
+        app.views.trees[0]
+            .index_tree_mut(&[0])
+            .unwrap()
+            .push_trivial_child(layout.right);
+        // The left pane shows the root and the right the new grandchild, so `.a` isn't drawn.
+        app.right_index.advance(&app.views);
+        assert_eq!(app.right_index.within_tree.path, vec![0, 0]);
+        app.focus = Focus::Right;
+        *app.focused_query_mut().unwrap() = ".b | length".to_owned();
+        app.recompute_focused_view(layout);
+        app.finish_query(layout);
+        match &app.focused_view().frame().view {
+            View::Json(Some(view)) => assert_eq!(&*view.values, &[JV::number(3.0)][..]),
+            View::Json(None) | View::Error(_) => panic!("Expected results"),
+        }
+    }
+    #[test]
+    fn unit_max_depth() {
+        let layout = JexLayout::new(DUMMY_RECT, false);
+        let json = br#"{"a": [[1]]}"#;
+        let mut app = App::new(&json[..], "test.json".to_owned(), layout).unwrap();
+        let mut options = app.render_options;
+        options.max_depth = 2;
+        app.set_render_options(options);
+        app.open_transformed("Copy of", |values| values.to_vec(), layout);
+        assert!(app.flash.take().is_some());
+        assert_eq!(app.views.trees.len(), 1);
+        options.max_depth = 3;
+        app.set_render_options(options);
+        app.open_transformed("Copy of", |values| values.to_vec(), layout);
+        assert!(app.flash.is_none());
+        assert_eq!(app.views.trees.len(), 2);
+    }
+    #[test]
+    fn unit_scalar_root() {
+        let layout = JexLayout::new(DUMMY_RECT, false);
+        let plain_text = |app: &App| match &app.focused_view().frame().view {
+            View::Json(Some(view)) => view.plain_text(true),
+            View::Json(None) | View::Error(_) => panic!("Expected a json view"),
+        };
+        for (json, type_name) in &[("42", "number"), ("\"hello\"", "string"), ("null", "null")] {
+            let mut app = App::new(json.as_bytes(), "test.json".to_owned(), layout).unwrap();
+            assert_eq!(plain_text(&app), vec![json.to_string()]);
+            // There's nowhere to move to.
+            if let View::Json(Some(view)) = &mut app.focused_view_mut().frame().view {
+                assert!(!view.scroll_by(Scroll::PageDown(10)));
+                assert!(!view.scroll_by(Scroll::PageUp(10)));
+                for scroll in &[Scroll::Down, Scroll::Up, Scroll::Bottom, Scroll::Top] {
+                    view.scroll_by(*scroll);
+                    assert_eq!(view.cursor.to_json_path(), vec![]);
+                }
+            }
+            let mut terminal = Terminal::new(TestBackend::new(100, 50)).unwrap();
+            terminal.draw(app.render(AppRenderMode::Normal)).unwrap();
+            // Queries get the scalar as `.`.
+            app.set_right_query("[type]".to_owned(), layout);
+            app.finish_query(layout);
+            app.focus = Focus::Right;
+            let type_line = format!("  {:?}", type_name);
+            assert_eq!(plain_text(&app), vec!["[", type_line.as_str(), "]"]);
+        }
+    }
+    #[test]
+    fn unit_jump_focused_to_path() {
+        let layout = JexLayout::new(DUMMY_RECT, false);
+        let json = br#"{"data": {"items": [{"id": 1}, {"id": 2}]}}"#;
+        let mut app = App::new(&json[..], "test.json".to_owned(), layout).unwrap();
+        let path = |app: &App| match &app.focused_view().frame().view {
+            View::Json(Some(view)) => view.cursor.to_json_path(),
+            View::Json(None) | View::Error(_) => unreachable!(),
+        };
+        app.jump_once_loaded(".data.items[1].id".to_owned());
+        assert_eq!(
+            path(&app),
+            vec![
+                PathElement::Key("data".to_owned()),
+                PathElement::Key("items".to_owned()),
+                PathElement::Index(1),
+                PathElement::Key("id".to_owned()),
+            ]
+        );
+        assert!(app.flash.is_none());
+        app.jump_focused_to_path(".data.items[5].id");
+        assert_eq!(path(&app), vec![]);
+        assert!(app.flash.is_some());
+    }
+    #[test]
+    fn unit_preview_search() {
+        let layout = JexLayout::new(DUMMY_RECT, false);
+        let json = br#"{"a": "cat", "b": {"c": "dog"}, "d": "door"}"#;
+        let mut app = App::new(&json[..], "test.json".to_owned(), layout).unwrap();
+        let path = |app: &App| match &app.focused_view().frame().view {
+            View::Json(Some(view)) => view.cursor.to_json_path(),
+            View::Json(None) | View::Error(_) => unreachable!(),
+        };
+        let start = path(&app);
+        let origin = app.search_origin().unwrap();
+        app.preview_search("do", &origin);
+        let b_c = vec![
+            PathElement::Key("b".to_owned()),
+            PathElement::Key("c".to_owned()),
+        ];
+        assert_eq!(path(&app), b_c);
+        app.preview_search("doo", &origin);
+        assert_eq!(path(&app), vec![PathElement::Key("d".to_owned())]);
+        // Half typed patterns don't move it.
+        app.preview_search("doo(", &origin);
+        assert_eq!(path(&app), vec![PathElement::Key("d".to_owned())]);
+        app.preview_search("", &origin);
+        assert_eq!(path(&app), start);
+        app.preview_search("dog", &origin);
+        app.cancel_search(origin);
+        assert_eq!(path(&app), start);
+        assert!(app.search_re.is_none());
+    }
+    #[test]
+    fn unit_search_across_values() {
+        let layout = JexLayout::new(DUMMY_RECT, false);
+        let json = br#"{"a": "dog"} {"b": {"c": "cat"}} {"d": "catalog"}"#;
+        let mut app = App::new(&json[..], "test.json".to_owned(), layout).unwrap();
+        let position = |app: &App| match &app.focused_view().frame().view {
+            View::Json(Some(view)) => (view.cursor.top_index, view.cursor.to_json_path()),
+            View::Json(None) | View::Error(_) => unreachable!(),
+        };
+        let b_c = vec![
+            PathElement::Key("b".to_owned()),
+            PathElement::Key("c".to_owned()),
+        ];
+        let d = vec![PathElement::Key("d".to_owned())];
+        app.search_re = Some(Regex::new("cat").unwrap());
+        app.search(false);
+        assert_eq!(position(&app), (1, b_c.clone()));
+        app.search(false);
+        assert_eq!(position(&app), (2, d.clone()));
+        // Past the last match, it wraps around to the first, and back again.
+        app.search(false);
+        assert_eq!(position(&app), (1, b_c.clone()));
+        app.search(true);
+        assert_eq!(position(&app), (2, d));
+        app.search(true);
+        assert_eq!(position(&app), (1, b_c.clone()));
+        // The only match stays found rather than being reported missing.
+        app.search_re = Some(Regex::new("^cat$").unwrap());
+        app.search(false);
+        assert_eq!(position(&app), (1, b_c));
+        assert!(app.status.is_none());
+    }
+    #[test]
+    fn unit_open_search_matches() {
+        let layout = JexLayout::new(DUMMY_RECT, false);
+        let json = br#"{"a": "cat", "b": ["dog", "catalog"], "c": 1}"#;
+        let mut app = App::new(&json[..], "test.json".to_owned(), layout).unwrap();
+        app.open_search_matches(layout);
+        assert!(app.flash.take().is_some());
+        app.search_re = Some(Regex::new("cat").unwrap());
+        app.open_search_matches(layout);
+        assert_eq!(app.views.trees.len(), 2);
+        assert_eq!(
+            focused_values(&app),
+            vec![json!({"a": "cat", "b": ["catalog"]})]
+        );
+    }
+    #[test]
+    fn unit_jump_to_line() {
+        let layout = JexLayout::new(DUMMY_RECT, false);
+        let json = br#"{"a": {"x": 1}, "b": [2, 3]}"#;
+        let mut app = App::new(&json[..], "test.json".to_owned(), layout).unwrap();
+        app.jump_focused_to_line("5");
+        assert_eq!(
+            focused_json_path(&app),
+            vec![PathElement::Key("b".to_owned())]
+        );
+        assert!(app.jump_focused_to(0, &[PathElement::Key("a".to_owned())]));
+        match &mut app.focused_view_mut().frame().view {
+            View::Json(Some(view)) => assert!(view.toggle_fold()),
+            _ => panic!("Expected a json view"),
+        }
+        // The folded object is one line.
+        app.jump_focused_to_line("3");
+        assert_eq!(
+            focused_json_path(&app),
+            vec![PathElement::Key("b".to_owned())]
+        );
+        assert!(app.flash.is_none());
+        app.jump_focused_to_line("100");
+        assert!(app.flash.take().is_some());
+        assert_eq!(focused_json_path(&app), vec![]);
+        app.jump_focused_to_line("x");
+        assert!(app.flash.is_some());
+    }
+    #[test]
+    fn unit_marks() {
+        let layout = JexLayout::new(DUMMY_RECT, false);
+        let json = br#"{"a": [1, 2], "b": 3}"#;
+        let mut app = App::new(&json[..], "test.json".to_owned(), layout).unwrap();
+        let a1 = vec![PathElement::Key("a".to_owned()), PathElement::Index(1)];
+        assert!(app.jump_focused_to(0, &a1));
+        app.set_focused_mark('x');
+        assert!(app.jump_focused_to(0, &[PathElement::Key("b".to_owned())]));
+        app.set_focused_mark('y');
+        app.jump_focused_to_mark('x');
+        assert_eq!(focused_json_path(&app), a1);
+        app.show_focused_marks();
+        assert!(app.flash.take().is_some());
+        // Deleting b clears its mark.
+        assert!(app.jump_focused_to(0, &[PathElement::Key("b".to_owned())]));
+        app.delete_focused(layout);
+        app.jump_focused_to_mark('y');
+        assert!(app.flash.take().is_some());
+        app.jump_focused_to_mark('x');
+        assert_eq!(focused_json_path(&app), a1);
+    }
+    #[test]
+    fn unit_diff_focused_marks() {
+        let layout = JexLayout::new(DUMMY_RECT, false);
+        let json = br#"[{"id": 1, "a": true}, {"id": 2}]"#;
+        let mut app = App::new(&json[..], "test.json".to_owned(), layout).unwrap();
+        assert!(app.jump_focused_to(0, &[PathElement::Index(0)]));
+        app.set_focused_mark('a');
+        assert!(app.jump_focused_to(0, &[PathElement::Index(1)]));
+        app.set_focused_mark('b');
+        app.diff_focused_marks('a', 'c', layout);
+        assert!(app.flash.take().is_some());
+        app.diff_focused_marks('a', 'b', layout);
+        assert_eq!(app.views.trees.len(), 2);
+        assert_eq!(
+            focused_values(&app),
+            vec![json!({
+                "only_in_a": {".[\"a\"]": true},
+                "only_in_b": {},
+                "changed": {".[\"id\"]": {"a": 1.0, "b": 2.0}},
+            })]
+        );
+    }
+    #[test]
+    fn unit_diff_focused_against() {
+        let layout = JexLayout::new(DUMMY_RECT, false);
+        let json = br#"{"id": 1, "a": true}"#;
+        let mut app = App::new(&json[..], "test.json".to_owned(), layout).unwrap();
+        app.diff_focused_against(b"{\"id\": ", "Clipboard", layout);
+        assert!(app.flash.take().is_some());
+        assert_eq!(app.views.trees.len(), 1);
+        app.diff_focused_against(br#"{"id": 2}"#, "Clipboard", layout);
+        assert_eq!(app.views.trees.len(), 3);
+        assert_eq!(
+            focused_values(&app),
+            vec![json!({
+                "only_in_a": {".[\"a\"]": true},
+                "only_in_b": {},
+                "changed": {".[\"id\"]": {"a": 1.0, "b": 2.0}},
+            })]
+        );
+        assert_eq!(app.views.trees[1].view_frame.name, "Clipboard");
+    }
+    #[test]
+    fn unit_streaming() {
+        let layout = JexLayout::new(DUMMY_RECT, false);
+        let json = &br#"{"a": 1} [1, 2, 3]"#[..];
+        let mut app = App::new_streaming(json, "test.json".to_owned(), layout).unwrap();
+        while app.is_loading() {
+            app.receive_loaded(layout);
+        }
+        let expected: Vec<JV> = vec![(&json!({"a": 1})).into(), (&json!([1, 2, 3])).into()];
+        // The query's results are rerun once everything has arrived.
+        let tree = &app.views.trees[0];
+        for frame in [&tree.view_frame, &tree.children[0].1.view_frame] {
+            match &frame.view {
+                View::Json(Some(view)) => assert_eq!(&*view.values, &expected[..]),
+                _ => panic!("Expected a json view"),
+            }
+        }
+        assert!(App::new_streaming(&b"{"[..], "test.json".to_owned(), layout).is_err());
+    }
+    #[test]
+    fn unit_activity_status() {
+        let layout = JexLayout::new(DUMMY_RECT, false);
+        let mut app = App::new(&b"1"[..], "test.json".to_owned(), layout).unwrap();
+        assert!(!app.is_busy());
+        let progress = |task: &str, state| TaskProgress {
+            task: task.to_owned(),
+            state,
+        };
+        app.update_task(progress("Indexing", TaskState::Running(Some(0.4))));
+        app.update_task(progress("Watching", TaskState::Running(None)));
+        assert_eq!(
+            app.activity_status().as_deref(),
+            Some("⠋ Indexing 40%, Watching")
+        );
+        app.tick(layout);
+        app.update_task(progress("Indexing", TaskState::Done));
+        assert_eq!(app.activity_status().as_deref(), Some("⠙ Watching"));
+        app.update_task(progress("Watching", TaskState::Done));
+        assert!(!app.is_busy());
+    }
+    #[test]
+    fn unit_status() {
+        let layout = JexLayout::new(DUMMY_RECT, false);
+        let mut app = App::new(&br#"{"a": 1}"#[..], "test.json".to_owned(), layout).unwrap();
+        app.search_re = Some(Regex::new("b").unwrap());
+        app.search(false);
+        assert_eq!(app.status.as_ref().unwrap().0, "No matches for /b/");
+        assert!(app.is_busy());
+        app.tick(layout);
+        assert!(app.status.is_some());
+        app.status.as_mut().unwrap().1 -= STATUS_DURATION;
+        app.tick(layout);
+        assert!(app.status.is_none());
+        assert!(!app.is_busy());
+    }
+    #[test]
+    fn unit_copy_to_root() {
+        let layout = JexLayout::new(DUMMY_RECT, false);
+        let json = br#"{"a": {"b": 1}}"#;
+        let mut app = App::new(&json[..], "test.json".to_owned(), layout).unwrap();
+        app.focus = Focus::Right;
+        *app.focused_query_mut().unwrap() = ".a".to_owned();
+        app.recompute_focused_view(layout);
+        app.copy_focused_to_root(layout);
+        assert_eq!(app.views.trees.len(), 2);
+        app.focus = Focus::Left;
+        assert_eq!(app.focused_view().frame().name, "Copy of root in New Query");
+        assert!(app.focused_query().is_none());
+        assert!(app.jump_focused_to(0, &[PathElement::Key("b".to_owned())]));
+        app.edit_focused_leaf("2", layout);
+        assert_eq!(focused_values(&app), vec![json!({"b": 2.0})]);
+        // The query it was copied from is untouched.
+        app.focus = Focus::Right;
+        assert_eq!(focused_values(&app), vec![json!({"b": 1.0})]);
+    }
+    #[test]
+    fn unit_startup_query() {
+        let layout = JexLayout::new(DUMMY_RECT, false);
+        let json = br#"{"data": {"items": [1]}}"#;
+        let mut app = App::new(&json[..], "test.json".to_owned(), layout).unwrap();
+        app.set_right_query(".data.items".to_owned(), layout);
+        assert!(matches!(app.focus, Focus::Left));
+        app.focus = Focus::Right;
+        assert_eq!(focused_values(&app), vec![json!([1.0])]);
+        // A query that doesn't compile leaves the file as it is, with the error on the right.
+        app.set_right_query(".data.[".to_owned(), layout);
+        assert!(matches!(app.focused_view().frame().view, View::Error(_)));
+        assert_eq!(app.focused_query().unwrap(), ".data.[");
+        app.focus = Focus::Left;
+        assert_eq!(
+            focused_values(&app),
+            vec![json!({"data": {"items": [1.0]}})]
+        );
+    }
+    #[test]
+    fn unit_load_timings() {
+        let layout = JexLayout::new(DUMMY_RECT, false);
+        let (app, timings) =
+            App::new_timed(&br#"[1, 2]"#[..], "test.json".to_owned(), layout).unwrap();
+        assert_eq!(focused_values(&app), vec![json!([1.0, 2.0])]);
+        let report = serde_json::to_value(&timings).unwrap();
+        for phase in &["parse_ms", "query_ms", "render_ms"] {
+            assert!(report[phase].as_f64().unwrap() >= 0.0);
+        }
+    }
+    #[test]
+    fn unit_open_file_named() {
+        let layout = JexLayout::new(DUMMY_RECT, false);
+        let mut app = App::new(&b"1"[..], "first.json".to_owned(), layout).unwrap();
+        let path = std::env::temp_dir().join("jex_unit_open_file_named.json");
+        std::fs::write(&path, "[2]").unwrap();
+        let path = path.to_str().unwrap().to_owned();
+        app.open_file(path.clone(), "Users".to_owned(), layout)
+            .unwrap();
+        app.open_file(path.clone(), " ".to_owned(), layout).unwrap();
+        let names: Vec<&str> = app
+            .views
+            .trees
+            .iter()
+            .map(|tree| tree.view_frame.name.as_str())
+            .collect();
+        assert_eq!(names, vec!["first.json", "Users", path.as_str()]);
+        // Titles are only for show: both are still the file.
+        for tree in app.views.trees[1..].iter() {
+            assert_eq!(tree.view_frame.source_path, Some(PathBuf::from(&path)));
+        }
+    }
+    #[test]
+    fn unit_warn_duplicates() {
+        let layout = JexLayout::new(DUMMY_RECT, false);
+        let app = App::new(&br#"{"a": 1}"#[..], "test.json".to_owned(), layout).unwrap();
+        assert!(app.flash.is_none());
+        let json = br#"{"a": 1, "a": 2}"#;
+        let app = App::new(&json[..], "test.json".to_owned(), layout).unwrap();
+        assert!(app.flash.is_some());
+        let mut app = App::new_streaming(&json[..], "test.json".to_owned(), layout).unwrap();
+        while app.is_loading() {
+            app.receive_loaded(layout);
+        }
+        assert!(app.flash.is_some());
+    }
+    #[test]
+    fn unit_time_query() {
+        let values: Vec<JV> = vec![(&json!([1, 2, 3])).into(), (&json!([4])).into()];
+        let timings = time_query(&values, ".[]", &JQOptions::default()).unwrap();
+        assert_eq!(timings.results, 4);
+        assert!(time_query(&values, ".[", &JQOptions::default()).is_err());
+    }
+    #[test]
+    fn unit_running_query() {
+        let layout = JexLayout::new(DUMMY_RECT, false);
+        let json = serde_json::to_vec(&(0..1000).collect::<Vec<_>>()).unwrap();
+        let mut app = App::new(&json[..], "test.json".to_owned(), layout).unwrap();
+        app.focus = Focus::Right;
+        *app.focused_query_mut().unwrap() = ".[]".to_owned();
+        // Start it like recompute_focused_view, but without time to finish.
+        let jq_options = app.jq_options.clone();
+        let run = match app.focused_view_mut() {
+            ViewWithParentMut::Child { frame, parent, .. } => frame.view.start_recompute_from(
+                &parent.view,
+                ".[]",
+                &jq_options,
+                layout.right,
+                Some(Duration::from_secs(0)),
+                None,
+            ),
+            ViewWithParentMut::Root { .. } => unreachable!(),
+        };
+        app.running_query = Some(RunningQuery {
+            index: app.right_index.clone(),
+            query: ".[]".to_owned(),
+            run: run.unwrap(),
+            view_rect: layout.right,
+        });
+        // A screenful is shown first.
+        let first = focused_values(&app).len();
+        assert!(first < 1000);
+        assert!(app.is_busy());
+        assert!(app.activity_status().unwrap().contains("Querying"));
+        while app.running_query.is_some() {
+            app.tick(layout);
+        }
+        assert_eq!(focused_values(&app).len(), 1000);
+        assert_eq!(focused_values(&app)[999], json!(999.0));
+    }
+    #[test]
+    fn unit_auto_raw() {
+        let layout = JexLayout::new(DUMMY_RECT, false);
+        let json = br#"{"a": "x", "b": ["y", "z"], "c": null}"#;
+        let mut app = App::new(&json[..], "test.json".to_owned(), layout).unwrap();
+        app.focus = Focus::Right;
+        let raw = |app: &mut App, query: &str| {
+            app.set_right_query(query.to_owned(), layout);
+            match &app.focused_view().frame().view {
+                View::Json(Some(view)) => view.scroll.options.raw_strings,
+                View::Json(None) | View::Error(_) => false,
+            }
+        };
+        assert!(raw(&mut app, ".a"));
+        assert!(!raw(&mut app, ".b[]"));
+        assert!(!raw(&mut app, "."));
+        let mut options = app.render_options;
+        options.auto_raw = AutoRaw::AllStrings;
+        app.set_render_options(options);
+        assert!(raw(&mut app, ".b[]"));
+        // Like jq -e, null, false and nothing at all are called out.
+        assert!(app.status.is_none());
+        raw(&mut app, ".c");
+        assert_eq!(app.status.take().unwrap().0, "The last result is null");
+        raw(&mut app, "empty");
+        assert_eq!(app.status.take().unwrap().0, "No results");
+    }
+    #[test]
+    fn unit_report_size() {
+        let layout = JexLayout::new(DUMMY_RECT, false);
+        let mut app = App::new(&b"{\"a\": 1}\n[2]"[..], "test.json".to_owned(), layout).unwrap();
+        app.report_size(0);
+        assert_eq!(app.status.take().unwrap().0, "Loaded 10 B");
+    }
+    #[test]
+    fn unit_refuses_to_save_over() {
+        let existing = std::env::temp_dir();
+        let existing = existing.to_str().unwrap();
+        let new = format!("{}/jex_read_only_{}.json", existing, std::process::id());
+        assert!(!refuses_to_save_over(false, existing));
+        assert!(refuses_to_save_over(true, existing));
+        assert!(!refuses_to_save_over(true, &new));
+    }
+    #[test]
+    fn unit_pipe_focused_value() {
+        let layout = JexLayout::new(DUMMY_RECT, false);
+        let json = serde_json::to_vec(&(0..100_000).collect::<Vec<_>>()).unwrap();
+        let mut app = App::new(&json[..], "test.json".to_owned(), layout).unwrap();
+        app.pipe_focused_value("cat".to_owned(), layout).unwrap();
+        assert_eq!(app.views.trees.len(), 2);
+        let err = app.pipe_focused_value("exit 3".to_owned(), layout);
+        assert!(err.unwrap_err().to_string().starts_with("`exit 3` failed"));
+        // The value is too big for the pipe's buffer, so writing the rest of it fails.
+        app.pipe_focused_value("true".to_owned(), layout).unwrap();
+        assert_eq!(app.views.trees.len(), 2);
+        assert_eq!(
+            app.status.as_ref().unwrap().0,
+            "`true` didn't read all of the value"
+        );
+        assert!(app.flash.is_some());
+        // Strings go without their quotes.
+        let mut app = App::new(&br#"{"a": "aGk="}"#[..], "test.json".to_owned(), layout).unwrap();
+        assert!(app.jump_focused_to(0, &[PathElement::Key("a".to_owned())]));
+        let path = std::env::temp_dir().join(format!("jex_pipe_{}.txt", std::process::id()));
+        let command = format!("cat > '{}'", path.display());
+        app.pipe_focused_value(command, layout).unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "aGk=\n");
+        std::fs::remove_file(&path).unwrap();
+    }
+    #[test]
+    fn unit_safe_mode() {
+        let layout = JexLayout::new(DUMMY_RECT, false);
+        let mut app = App::new(&br#"{"a": 1}"#[..], "test.json".to_owned(), layout).unwrap();
+        app.safe_mode = true;
+        let err = app.pipe_focused_value("touch /tmp/jex_unsafe".to_owned(), layout);
+        assert_eq!(
+            err.unwrap_err().to_string(),
+            "Piping to a command is disabled in safe mode"
+        );
+    }
+    #[test]
+    fn unit_max_results() {
+        let layout = JexLayout::new(DUMMY_RECT, false);
+        let json = serde_json::to_vec(&(0..100).collect::<Vec<_>>()).unwrap();
+        let mut app = App::new(&json[..], "test.json".to_owned(), layout).unwrap();
+        app.max_results = Some(30);
+        app.set_right_query(".[]".to_owned(), layout);
+        app.focus = Focus::Right;
+        let tick_until_idle = |app: &mut App| {
+            while app.is_busy() {
+                app.tick(layout);
+            }
+        };
+        tick_until_idle(&mut app);
+        assert_eq!(focused_values(&app).len(), 30);
+        assert!(app.activity_status().unwrap().contains("first 30 results"));
+        assert!(app.fetch_more_results(false));
+        tick_until_idle(&mut app);
+        assert_eq!(focused_values(&app).len(), 60);
+        assert!(app.fetch_more_results(true));
+        tick_until_idle(&mut app);
+        assert_eq!(focused_values(&app).len(), 100);
+        assert!(app.running_query.is_none());
+        assert!(!app.fetch_more_results(false));
+    }
+    #[test]
+    fn unit_outline() {
+        let layout = JexLayout::new(DUMMY_RECT, false);
+        let json = br#"{"a": 1, "b": {"c": [true]}}"#;
+        let mut app = App::new(&json[..], "test.json".to_owned(), layout).unwrap();
+        app.toggle_outline();
+        assert!(app.layout(DUMMY_RECT).tree.is_some());
+        app.move_outline_selection(5);
+        assert!(app.expand_outline_selection(true));
+        app.move_outline_selection(1);
+        app.jump_to_outline_selection();
+        let path = vec![
+            PathElement::Key("b".to_owned()),
+            PathElement::Key("c".to_owned()),
+        ];
+        assert_eq!(focused_json_path(&app), path);
+        app.toggle_outline();
+        assert!(app.layout(DUMMY_RECT).tree.is_none());
+    }
+    #[test]
+    fn unit_root_has_no_query() {
+        let layout = JexLayout::new(DUMMY_RECT, false);
+        let mut app = App::new(&br#"{"a": 1}"#[..], "test.json".to_owned(), layout).unwrap();
+        app.focus = Focus::Left;
+        assert!(matches!(app.focused_view(), ViewWithParent::Root { .. }));
+        assert_eq!(app.focused_query(), None);
+        assert!(app.focused_query_mut().is_none());
+        app.focus = Focus::Right;
+        assert_eq!(app.focused_query().map(String::as_str), Some("."));
+    }
+    #[test]
+    fn unit_plain_text() {
+        let layout = JexLayout::new(DUMMY_RECT, false);
+        let json = br#"{"a": [1, 2], "b": 3}"#;
+        let mut app = App::new(&json[..], "test.json".to_owned(), layout).unwrap();
+        assert!(app.jump_focused_to(0, &[PathElement::Key("a".to_owned())]));
+        if let View::Json(Some(view)) = &mut app.focused_view_mut().frame().view {
+            view.toggle_fold();
+        }
+        let lines = app.focused_plain_text(true).unwrap();
+        let expected = vec!["{", "  \"a\" : [ 1, … ] (2 children)", "  \"b\" : 3", "}"];
+        assert_eq!(lines, expected);
+        // Only as many lines as fit on screen.
+        let layout = JexLayout::new(
+            Rect {
+                height: 7,
+                ..DUMMY_RECT
+            },
+            false,
+        );
+        app.resize(layout);
+        assert_eq!(app.focused_plain_text(false).unwrap().len(), 3);
+    }
+}
@@ -0,0 +1,100 @@
+use flate2::bufread::MultiGzDecoder;
+use std::{
+    fs,
+    io::{self, BufRead, Read},
+    path::Path,
+};
+
+const GZIP_MAGIC: &[u8] = b"\x1F\x8B";
+const ZSTD_MAGIC: &[u8] = b"\x28\xB5\x2F\xFD";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Compression {
+    Gzip,
+    Zstd,
+}
+
+impl Compression {
+    // Going by the magic bytes rather than the extension means renamed files still load.
+    fn from_start(start: &[u8]) -> Option<Self> {
+        if start.starts_with(GZIP_MAGIC) {
+            Some(Compression::Gzip)
+        } else if start.starts_with(ZSTD_MAGIC) {
+            Some(Compression::Zstd)
+        } else {
+            None
+        }
+    }
+    pub fn of_file<P: AsRef<Path>>(path: P) -> io::Result<Option<Self>> {
+        let mut start = Vec::with_capacity(ZSTD_MAGIC.len());
+        fs::File::open(path)?
+            .take(ZSTD_MAGIC.len() as u64)
+            .read_to_end(&mut start)?;
+        Ok(Compression::from_start(&start))
+    }
+    // The file extensions compressed files usually have, which are dropped to get the format.
+    pub fn is_extension(extension: &str) -> bool {
+        matches!(extension, "gz" | "gzip" | "zst" | "zstd")
+    }
+}
+
+// Decompresses the input if it starts like a compressed file, and otherwise passes it through.
+// Files that are several gzip files back to back, like `cat a.gz b.gz` makes, are read as one, as
+// gunzip does.
+pub fn decompress<R: BufRead + Send + 'static>(mut r: R) -> io::Result<Box<dyn Read + Send>> {
+    let decompressed: Box<dyn Read + Send> = match Compression::from_start(r.fill_buf()?) {
+        None => Box::new(r),
+        Some(Compression::Gzip) => Box::new(io::BufReader::new(MultiGzDecoder::new(r))),
+        Some(Compression::Zstd) => Box::new(io::BufReader::new(
+            zstd::stream::read::Decoder::with_buffer(r)?,
+        )),
+    };
+    Ok(decompressed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::decompress;
+    use flate2::{write::GzEncoder, Compression as GzLevel};
+    use std::io::{Read, Write};
+
+    fn gzip(data: &[u8]) -> Vec<u8> {
+        let mut encoder = GzEncoder::new(Vec::new(), GzLevel::default());
+        encoder.write_all(data).unwrap();
+        encoder.finish().unwrap()
+    }
+    fn read_all(compressed: Vec<u8>) -> std::io::Result<Vec<u8>> {
+        let mut out = Vec::new();
+        decompress(std::io::Cursor::new(compressed))?.read_to_end(&mut out)?;
+        Ok(out)
+    }
+    #[test]
+    fn unit_gzip() {
+        // Big enough to take many reads.
+        let json: Vec<u8> = (0..20_000)
+            .flat_map(|i| format!("{{\"i\": {}, \"s\": \"{}\"}}\n", i, i * 7919).into_bytes())
+            .collect();
+        let gzipped = gzip(&json);
+        assert_eq!(read_all(gzipped.clone()).unwrap(), json);
+        let mut twice = gzipped.clone();
+        twice.extend_from_slice(&gzipped);
+        assert_eq!(read_all(twice).unwrap(), [&json[..], &json[..]].concat());
+        assert!(read_all(gzipped[..gzipped.len() - 4].to_vec()).is_err());
+    }
+    #[test]
+    fn unit_zstd() {
+        let json = b"{\"a\": [1, 2, 3]}\n".repeat(1000);
+        let compressed = zstd::stream::encode_all(&json[..], 0).unwrap();
+        assert_eq!(read_all(compressed.clone()).unwrap(), json);
+        assert!(read_all(compressed[..compressed.len() / 2].to_vec()).is_err());
+    }
+    #[test]
+    fn unit_decompress_passthrough() {
+        let mut out = String::new();
+        decompress(&b"{\"a\": 1}"[..])
+            .unwrap()
+            .read_to_string(&mut out)
+            .unwrap();
+        assert_eq!(out, "{\"a\": 1}");
+    }
+}
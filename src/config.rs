@@ -0,0 +1,193 @@
+use crate::{
+    app::DEFAULT_AUTOSAVE_DELAY,
+    lines::{parse_color, DepthPalette, GlyphMode, ScalarGlyphs},
+};
+use serde::Deserialize;
+use std::{
+    collections::BTreeMap,
+    fs, io,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+// Settings from config.json in jex's config directory. Everything in it is optional.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub history: HistoryConfig,
+    // Strings longer than this many characters are shown cut short.
+    pub max_value_chars: Option<usize>,
+    // Lines moved by PageUp and PageDown, instead of the height of the pane, so panes of
+    // different heights page in step.
+    pub page_size: Option<usize>,
+    // The depth ruler's colors, from the top level down, like `["red", "208", "#00ff88"]`.
+    pub depth_colors: Option<Vec<String>>,
+    // Whether booleans and null start out shown with symbols: "off", "beside" or "instead" of the
+    // words.
+    pub scalar_glyphs: Option<String>,
+    // The symbols for true, false and null, like "✓✗∅".
+    pub glyphs: Option<String>,
+    // Whether the tree panel is open from the start, as with --tree.
+    pub show_tree: bool,
+    // Seconds without an edit before --autosave saves, 2 by default.
+    pub autosave_seconds: Option<f64>,
+}
+
+// Where the histories of the prompts (query, search, open, rename, save, ...) are kept, and how
+// long they can get.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct HistoryConfig {
+    // Instead of the cache directory.
+    pub dir: Option<PathBuf>,
+    // Most entries kept in each history, unless it has its own limit in `limits`.
+    pub max_entries: Option<usize>,
+    // Limits for particular histories, by prompt name, like `"query": 1000`.
+    pub limits: BTreeMap<String, usize>,
+    // Prompts that use another's history instead of their own, like `"search": "query"`.
+    pub share: BTreeMap<String, String>,
+}
+
+impl Config {
+    pub fn load(path: &Path) -> io::Result<Self> {
+        match fs::File::open(path) {
+            Ok(f) => Ok(serde_json::from_reader(io::BufReader::new(f))?),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(Config::default()),
+            Err(err) => Err(err),
+        }
+    }
+    pub fn scalar_glyphs(&self) -> Result<ScalarGlyphs, String> {
+        let mut glyphs = ScalarGlyphs::default();
+        if let Some(mode) = &self.scalar_glyphs {
+            glyphs.mode = mode.parse::<GlyphMode>()?;
+        }
+        match &self.glyphs {
+            Some(symbols) => glyphs.with_glyphs(symbols),
+            None => Ok(glyphs),
+        }
+    }
+    pub fn depth_palette(&self) -> Result<Option<DepthPalette>, String> {
+        let colors = match &self.depth_colors {
+            Some(colors) => colors,
+            None => return Ok(None),
+        };
+        let colors = colors
+            .iter()
+            .map(|color| parse_color(color))
+            .collect::<Result<Vec<_>, _>>()?;
+        match DepthPalette::new(&colors) {
+            Some(palette) => Ok(Some(palette)),
+            None => Err("depth_colors needs at least one color".to_owned()),
+        }
+    }
+    pub fn autosave_delay(&self) -> Result<Duration, String> {
+        match self.autosave_seconds {
+            None => Ok(DEFAULT_AUTOSAVE_DELAY),
+            Some(seconds) if seconds.is_finite() && seconds >= 0.0 => {
+                Ok(Duration::from_secs_f64(seconds))
+            }
+            Some(seconds) => Err(format!("autosave_seconds can't be {}", seconds)),
+        }
+    }
+}
+
+impl HistoryConfig {
+    // The name of the history a prompt uses.
+    fn history_name<'a>(&'a self, name: &'a str) -> &'a str {
+        self.share.get(name).map_or(name, String::as_str)
+    }
+    pub fn path(&self, default_dir: &Path, name: &str) -> PathBuf {
+        let dir = self.dir.as_deref().unwrap_or(default_dir);
+        dir.join(format!("{}_history", self.history_name(name)))
+    }
+    pub fn max_entries(&self, name: &str) -> Option<usize> {
+        let name = self.history_name(name);
+        self.limits.get(name).copied().or(self.max_entries)
+    }
+    // Whether any other prompt uses the same history as this one.
+    pub fn is_shared(&self, name: &str) -> bool {
+        let history_name = self.history_name(name);
+        self.share.contains_key(name) || self.share.values().any(|shared| shared == history_name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Config;
+    use crate::lines::GlyphMode;
+    use std::path::Path;
+    use tui::style::Color;
+    #[test]
+    fn unit_history_config() {
+        let path = std::env::temp_dir().join("jex_unit_history_config.json");
+        std::fs::write(
+            &path,
+            r#"{"history": {"dir": "/sync/jex", "max_entries": 500, "limits": {"query": 2000}}}"#,
+        )
+        .unwrap();
+        let config = Config::load(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        let history = &config.history;
+        let cache = Path::new("/cache");
+        assert_eq!(
+            history.path(cache, "search"),
+            Path::new("/sync/jex/search_history")
+        );
+        assert_eq!(history.max_entries("query"), Some(2000));
+        assert_eq!(history.max_entries("search"), Some(500));
+        let missing = Config::load(Path::new("/nonexistent/jex/config.json")).unwrap();
+        assert_eq!(missing, Config::default());
+        assert_eq!(
+            missing.history.path(cache, "search"),
+            Path::new("/cache/search_history")
+        );
+        assert_eq!(missing.history.max_entries("search"), None);
+    }
+    #[test]
+    fn unit_shared_history() {
+        let config: Config = serde_json::from_str(
+            r#"{"history": {"limits": {"query": 50}, "share": {"search": "query"}}, "max_value_chars": 80}"#,
+        )
+        .unwrap();
+        let history = &config.history;
+        let cache = Path::new("/cache");
+        assert_eq!(history.path(cache, "search"), history.path(cache, "query"));
+        assert_eq!(history.max_entries("search"), Some(50));
+        assert_eq!(config.max_value_chars, Some(80));
+        assert_eq!(config.page_size, None);
+        assert!(!config.show_tree);
+        assert_eq!(config.depth_palette(), Ok(None));
+        let config: Config =
+            serde_json::from_str(r##"{"depth_colors": ["light-blue", "208", "#00ff88"]}"##)
+                .unwrap();
+        let palette = config.depth_palette().unwrap().unwrap();
+        assert_eq!(
+            (0..4).map(|depth| palette.color(depth)).collect::<Vec<_>>(),
+            vec![
+                Color::LightBlue,
+                Color::Indexed(208),
+                Color::Rgb(0, 255, 136),
+                Color::LightBlue
+            ]
+        );
+        let config: Config =
+            serde_json::from_str(r#"{"scalar_glyphs": "beside", "glyphs": "yn-"}"#).unwrap();
+        let glyphs = config.scalar_glyphs().unwrap();
+        assert_eq!(glyphs.mode, GlyphMode::Beside);
+        assert_eq!(
+            (glyphs.true_glyph, glyphs.false_glyph, glyphs.null_glyph),
+            ('y', 'n', '-')
+        );
+        let config: Config = serde_json::from_str(r#"{"glyphs": "yn"}"#).unwrap();
+        assert!(config.scalar_glyphs().is_err());
+        let config: Config = serde_json::from_str(r#"{"depth_colors": ["mauve"]}"#).unwrap();
+        assert!(config.depth_palette().is_err());
+        assert!(history.is_shared("search"));
+        assert!(history.is_shared("query"));
+        assert!(!history.is_shared("open"));
+        assert_eq!(
+            history.path(cache, "open"),
+            Path::new("/cache/open_history")
+        );
+    }
+}
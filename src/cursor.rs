@@ -1,11 +1,19 @@
 use crate::{
     jq::jv::{JVArray, JVObject, JVString, OwnedObjectIterator, JV},
-    lines::{Leaf, LeafContent, LineCursor, UnstyledSpans},
+    lines::{
+        fold_preview, format_number, GlyphMode, Leaf, LeafContent, LineCursor, RenderOptions,
+        UnstyledSpans, INDEX_GUTTER_WIDTH,
+    },
 };
 use log::trace;
 use regex::Regex;
+use serde::{Deserialize, Serialize};
 use std::{borrow::Cow, cmp::Ordering, collections::HashSet, fmt, rc::Rc};
-use tui::{layout::Rect, text::Spans};
+use tui::{
+    layout::Rect,
+    style::{Color, Style},
+    text::{Span, Spans},
+};
 
 // Requirements:
 // * Produce the current line
@@ -273,39 +281,119 @@ impl CursorFrame {
     }
 }
 
+// The text searches match scalars against.
+pub fn scalar_to_string(jv: &JV) -> Option<Cow<'_, str>> {
+    match jv {
+        JV::Null(_) => Some("null".into()),
+        JV::Bool(b) => Some(b.value().to_string().into()),
+        JV::Number(x) => Some(x.value().to_string().into()),
+        JV::String(s) => Some(s.value().into()),
+        _ => None,
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct GlobalCursor {
     pub value_cursor: LeafCursor,
     pub line_cursor: LineCursor,
+    pub options: RenderOptions,
 }
 impl GlobalCursor {
-    pub fn new(jsons: Rc<[JV]>, width: u16, folds: &HashSet<(usize, Vec<usize>)>) -> Option<Self> {
+    pub fn new(
+        jsons: Rc<[JV]>,
+        width: u16,
+        folds: &HashSet<(usize, Vec<usize>)>,
+        options: RenderOptions,
+    ) -> Option<Self> {
         let cursor = LeafCursor::new(jsons)?;
-        let line = cursor.current_line(folds, width);
-        let line_cursor = LineCursor::new_at_start(line.render(), width);
-        Some(GlobalCursor {
-            value_cursor: cursor,
-            line_cursor,
-        })
+        Some(GlobalCursor::new_at(cursor, width, folds, options))
     }
     pub fn new_end(
         jsons: Rc<[JV]>,
         width: u16,
         folds: &HashSet<(usize, Vec<usize>)>,
+        options: RenderOptions,
     ) -> Option<Self> {
         let cursor = LeafCursor::new_end(jsons)?;
-        let line = cursor.current_line(folds, width);
-        let line_cursor = LineCursor::new_at_start(line.render(), width);
-        Some(GlobalCursor {
+        Some(GlobalCursor::new_at(cursor, width, folds, options))
+    }
+    // Starts at the first line of `cursor`'s leaf.
+    pub fn new_at(
+        cursor: LeafCursor,
+        width: u16,
+        folds: &HashSet<(usize, Vec<usize>)>,
+        options: RenderOptions,
+    ) -> Self {
+        let width = options.text_width(width);
+        let line = cursor.current_line(folds, width, options);
+        let wrapping = options.wrapping(&line);
+        let line_cursor = LineCursor::new_at_start_with(line.render(), width, wrapping);
+        GlobalCursor {
             value_cursor: cursor,
             line_cursor,
-        })
+            options,
+        }
     }
     pub fn current_line(&self) -> UnstyledSpans {
         self.line_cursor
             .current()
             .expect("Global cursor should not be able to have invalid line cursor")
     }
+    // The index to show in the gutter, on the first line of each array element.
+    fn gutter(&self) -> String {
+        let index = match self.value_cursor.frames.last() {
+            Some(CursorFrame::Array { index, .. })
+                if self.value_cursor.focus_position != FocusPosition::End
+                    && self.line_cursor.current_line() == Some(0) =>
+            {
+                format!("[{}]", index)
+            }
+            _ => String::new(),
+        };
+        format!(
+            "{:>width$} ",
+            index,
+            width = INDEX_GUTTER_WIDTH as usize - 1
+        )
+    }
+    fn render_line(&self, cursor: Option<&LeafCursor>) -> Spans<'static> {
+        let mut line = self
+            .current_line()
+            .to_spans(Some(&self.value_cursor) == cursor);
+        if self.options.depth_ruler {
+            let depth = self.value_cursor.frames.len();
+            let style = Style::default().fg(self.options.depth_palette.color(depth));
+            line.0.insert(0, Span::styled("▎ ", style));
+        }
+        if self.options.index_gutter {
+            let style = Style::default().fg(Color::DarkGray);
+            line.0.insert(0, Span::styled(self.gutter(), style));
+        }
+        line
+    }
+    fn render_plain_line(&self) -> String {
+        let mut line = self.current_line().to_plain();
+        if self.options.index_gutter {
+            line.insert_str(0, &self.gutter());
+        }
+        line.trim_end().to_owned()
+    }
+    // Like `render_lines` without styling, for `height` lines, or to the end if it's `None`.
+    pub fn render_plain_lines(
+        &mut self,
+        folds: &HashSet<(usize, Vec<usize>)>,
+        width: u16,
+        height: Option<usize>,
+    ) -> Vec<String> {
+        let mut lines = vec![self.render_plain_line()];
+        while height.is_none_or(|height| lines.len() < height) {
+            if self.advance(folds, width).is_none() {
+                break;
+            }
+            lines.push(self.render_plain_line());
+        }
+        lines
+    }
     pub fn render_lines(
         &mut self,
         cursor: Option<&LeafCursor>,
@@ -314,18 +402,12 @@ impl GlobalCursor {
     ) -> Vec<Spans<'static>> {
         let mut lines = Vec::with_capacity(rect.height as usize);
         self.resize_to(rect);
-        lines.push(
-            self.current_line()
-                .to_spans(Some(&self.value_cursor) == cursor),
-        );
+        lines.push(self.render_line(cursor));
         while lines.len() < rect.height as usize {
             if let None = self.advance(folds, rect.width) {
                 break;
             };
-            lines.push(
-                self.current_line()
-                    .to_spans(Some(&self.value_cursor) == cursor),
-            );
+            lines.push(self.render_line(cursor));
         }
         lines
     }
@@ -339,9 +421,11 @@ impl GlobalCursor {
         } else {
             lc.move_prev();
         }
-        self.value_cursor.advance(folds)?;
-        let line = self.value_cursor.current_line(folds, width);
-        self.line_cursor = LineCursor::new_at_start(line.render(), width);
+        self.value_cursor.advance_visible(folds, self.options)?;
+        let width = self.options.text_width(width);
+        let line = self.value_cursor.current_line(folds, width, self.options);
+        let wrapping = self.options.wrapping(&line);
+        self.line_cursor = LineCursor::new_at_start_with(line.render(), width, wrapping);
         trace!("Advanced global cursor {:#?}", self);
         Some(())
     }
@@ -353,9 +437,11 @@ impl GlobalCursor {
         } else {
             lc.move_next();
         }
-        self.value_cursor.regress(folds)?;
-        let line = self.value_cursor.current_line(folds, width);
-        self.line_cursor = LineCursor::new_at_end(line.render(), width);
+        self.value_cursor.regress_visible(folds, self.options)?;
+        let width = self.options.text_width(width);
+        let line = self.value_cursor.current_line(folds, width, self.options);
+        let wrapping = self.options.wrapping(&line);
+        self.line_cursor = LineCursor::new_at_end_with(line.render(), width, wrapping);
         Some(())
     }
     pub fn to_path(&self) -> GlobalPath {
@@ -369,7 +455,8 @@ impl GlobalCursor {
         }
     }
     pub fn resize_to(&mut self, rect: Rect) {
-        self.line_cursor.set_width(rect.width);
+        self.line_cursor
+            .set_width(self.options.text_width(rect.width));
     }
     pub fn at_line_end(&self) -> bool {
         self.line_cursor
@@ -462,6 +549,40 @@ impl LeafCursor {
             focus_position: path.focus_position,
         }
     }
+    // Finds the value at `path` within the top level json at `top_index`, if it exists.
+    pub fn from_json_path(jsons: Rc<[JV]>, top_index: usize, path: &[PathElement]) -> Option<Self> {
+        let mut focus = jsons.get(top_index)?.clone();
+        let mut frames = Vec::with_capacity(path.len());
+        for element in path {
+            let (index, child) = match (focus, element) {
+                (JV::Array(arr), PathElement::Index(i)) => (*i, arr.get(*i as i32)?),
+                (JV::Object(obj), PathElement::Key(key)) => obj
+                    .into_iter()
+                    .enumerate()
+                    .find(|(_, (k, _))| k.value() == key)
+                    .map(|(i, (_, v))| (i, v))?,
+                _ => return None,
+            };
+            frames.push(index);
+            focus = child;
+        }
+        let path = ValuePath {
+            top_index,
+            frames,
+            focus_position: FocusPosition::starting(&focus),
+        };
+        Some(LeafCursor::from_path(jsons, &path))
+    }
+    // The inverse of from_json_path, for the value the cursor is on.
+    pub fn to_json_path(&self) -> Vec<PathElement> {
+        self.frames
+            .iter()
+            .map(|frame| match frame {
+                CursorFrame::Array { index, .. } => PathElement::Index(*index),
+                CursorFrame::Object { key, .. } => PathElement::Key(key.value().to_owned()),
+            })
+            .collect()
+    }
     pub fn current_key(&self) -> Option<JVString> {
         match self.focus_position {
             FocusPosition::End => None,
@@ -472,36 +593,111 @@ impl LeafCursor {
             },
         }
     }
-    pub fn current_indent(&self, width: u16) -> u16 {
-        let desired_indent = (self.frames.len() * 2) as u16;
-        std::cmp::min(desired_indent, width - 7)
+    // Whether this is a top level value, or an element of a top level array.
+    fn is_top_level_item(&self) -> bool {
+        matches!(self.frames.as_slice(), [] | [CursorFrame::Array { .. }])
+    }
+    pub fn current_indent(&self, width: u16, indent_width: u16) -> u16 {
+        let desired_indent = self.frames.len() * indent_width as usize;
+        std::cmp::min(desired_indent, (width - 7) as usize) as u16
     }
-    pub fn current_line<'a>(&'a self, folds: &HashSet<(usize, Vec<usize>)>, width: u16) -> Leaf {
+    pub fn current_line<'a>(
+        &'a self,
+        folds: &HashSet<(usize, Vec<usize>)>,
+        width: u16,
+        options: RenderOptions,
+    ) -> Leaf {
         use FocusPosition::*;
         let folded = folds.contains(&self.to_path().strip_position());
         let content = match (&self.focus, self.focus_position, folded) {
+            (JV::Object(_), Start, _) if self.is_depth_limited(options.max_depth) => {
+                LeafContent::DepthLimited("{…(depth limit)}")
+            }
+            (JV::Array(_), Start, _) if self.is_depth_limited(options.max_depth) => {
+                LeafContent::DepthLimited("[…(depth limit)]")
+            }
+            (JV::Object(obj), Start, false) if options.minimal_punctuation => {
+                LeafContent::Bracketless(if obj.is_empty() { "{}" } else { "" })
+            }
+            (JV::Array(arr), Start, false) if options.minimal_punctuation => {
+                LeafContent::Bracketless(if arr.is_empty() { "[]" } else { "" })
+            }
+            (JV::Object(_), End, false) | (JV::Array(_), End, false)
+                if options.minimal_punctuation =>
+            {
+                LeafContent::Bracketless("")
+            }
             (JV::Object(_), Start, false) => LeafContent::ObjectStart,
             (JV::Object(_), End, false) => LeafContent::ObjectEnd,
-            (JV::Object(obj), Start, true) => LeafContent::FoldedObject(obj.len() as usize),
+            (JV::Object(obj), Start, true) => {
+                let first = obj.iter().next().filter(|_| options.fold_preview_width > 0);
+                let preview = first.map(|(key, child)| {
+                    fold_preview(Some(key), &child, options.fold_preview_width)
+                });
+                LeafContent::FoldedObject(obj.len() as usize, preview)
+            }
             (JV::Array(_), Start, false) => LeafContent::ArrayStart,
             (JV::Array(_), End, false) => LeafContent::ArrayEnd,
-            (JV::Array(arr), Start, true) => LeafContent::FoldedArray(arr.len() as usize),
+            (JV::Array(arr), Start, true) => {
+                let first = arr.get(0).filter(|_| options.fold_preview_width > 0);
+                let preview =
+                    first.map(|child| fold_preview(None, &child, options.fold_preview_width));
+                LeafContent::FoldedArray(arr.len() as usize, preview)
+            }
+            (JV::Null(_), Value, _) if options.scalar_glyphs.mode != GlyphMode::Off => {
+                LeafContent::Glyph(None, options.scalar_glyphs)
+            }
             (JV::Null(_), Value, _) => LeafContent::Null,
+            (JV::Bool(_), Value, _) if options.abstracted.booleans => {
+                LeafContent::Placeholder("true/false")
+            }
+            (JV::Number(_), Value, _) if options.abstracted.numbers => {
+                LeafContent::Placeholder("#")
+            }
+            (JV::String(_), Value, _) if options.abstracted.strings => {
+                LeafContent::Placeholder("\"…\"")
+            }
+            (JV::Bool(b), Value, _) if options.scalar_glyphs.mode != GlyphMode::Off => {
+                LeafContent::Glyph(Some(b.value()), options.scalar_glyphs)
+            }
             (JV::Bool(b), Value, _) => LeafContent::Bool(b.value()),
+            (JV::Number(x), Value, _) if options.format_numbers => LeafContent::FormattedNumber(
+                x.value(),
+                format_number(x.value(), options.float_precision),
+            ),
             (JV::Number(x), Value, _) => LeafContent::Number(x.value()),
+            // Checked first, since the point is to not draw the whole string.
+            (JV::String(s), Value, _) if options.truncated(s.value()).is_some() => {
+                let prefix = options.truncated(s.value()).unwrap_or_default();
+                LeafContent::TruncatedString(prefix.to_owned())
+            }
+            (JV::String(s), Value, _) if options.reveal_whitespace => {
+                LeafContent::RevealedString(s.clone())
+            }
+            (JV::String(s), Value, _) if options.raw_strings && self.is_top_level_item() => {
+                LeafContent::RawString(s.clone())
+            }
             (JV::String(s), Value, _) => LeafContent::String(s.clone()),
             triple => panic!("Illegal json/focus_position/folded triple: {:?}", triple),
         };
         let key = self.current_key();
         let comma = match self.focus_position {
+            _ if options.minimal_punctuation => false,
             FocusPosition::Start => false,
             _ => match self.frames.last() {
                 None => false,
                 Some(CursorFrame::Array { json, index, .. }) => *index != json.len() as usize - 1,
+                Some(CursorFrame::Object { iterator, .. })
+                    if options.hide_nulls || options.hide_empty =>
+                {
+                    iterator
+                        .clone()
+                        .any(|(_, value)| !options.hides_member(&value))
+                }
                 Some(CursorFrame::Object { iterator, .. }) => iterator.len() != 0,
             },
         };
-        let indent = self.current_indent(width);
+        let indent = self.current_indent(width, options.indent_width);
         Leaf {
             content,
             key,
@@ -510,6 +706,15 @@ impl LeafCursor {
         }
     }
     pub fn advance(&mut self, folds: &HashSet<(usize, Vec<usize>)>) -> Option<()> {
+        self.advance_within(folds, usize::MAX)
+    }
+    // Like `advance`, but not opening arrays and objects `max_depth` levels down, as if they were
+    // folded.
+    fn advance_within(
+        &mut self,
+        folds: &HashSet<(usize, Vec<usize>)>,
+        max_depth: usize,
+    ) -> Option<()> {
         // This gets pretty deep into nested match statements, so an english guide to what's going
         // on here.
         // Cases:
@@ -521,7 +726,8 @@ impl LeafCursor {
         //     * and there are more leaves, so focus on the next leaf.
         //     * and there are no more leaves, so pop the frame, focus on the parent's close bracket
         // * We're focused on a close bracket. Advance the parent as if we were focused on a leaf.
-        let is_folded = folds.contains(&self.to_path().strip_position());
+        let is_folded =
+            self.is_depth_limited(max_depth) || folds.contains(&self.to_path().strip_position());
         match self.focus_position {
             FocusPosition::Start if !is_folded => {
                 let (new_frame, new_focus, new_focus_position) = open_container(self.focus.clone());
@@ -550,6 +756,13 @@ impl LeafCursor {
         Some(())
     }
     pub fn regress(&mut self, folds: &HashSet<(usize, Vec<usize>)>) -> Option<()> {
+        self.regress_within(folds, usize::MAX)
+    }
+    fn regress_within(
+        &mut self,
+        folds: &HashSet<(usize, Vec<usize>)>,
+        max_depth: usize,
+    ) -> Option<()> {
         // Pretty mechanical opposite of advance
         match self.focus_position {
             FocusPosition::End => {
@@ -577,20 +790,54 @@ impl LeafCursor {
                 }
             },
         }
-        let is_folded = folds.contains(&self.to_path().strip_position());
+        let is_folded =
+            self.is_depth_limited(max_depth) || folds.contains(&self.to_path().strip_position());
         if is_folded {
             self.focus_position = FocusPosition::Start;
         }
         Some(())
     }
-    fn leaf_to_string(&self) -> Option<Cow<str>> {
-        match &self.focus {
-            JV::Null(_) => Some("null".into()),
-            JV::Bool(b) => Some(b.value().to_string().into()),
-            JV::Number(x) => Some(x.value().to_string().into()),
-            JV::String(s) => Some(s.value().into()),
-            _ => None,
+    // Whether this is an array or object too deeply nested to open. Empty ones have nothing to
+    // open, so they're shown as usual.
+    pub fn is_depth_limited(&self, max_depth: usize) -> bool {
+        let container = match &self.focus {
+            JV::Array(arr) => !arr.is_empty(),
+            JV::Object(obj) => !obj.is_empty(),
+            _ => false,
+        };
+        container && self.frames.len() >= max_depth
+    }
+    // Whether this is an object member that `options` leaves out.
+    pub fn is_hidden(&self, options: RenderOptions) -> bool {
+        matches!(self.frames.last(), Some(CursorFrame::Object { .. }))
+            && options.hides_member(&self.focus)
+    }
+    // Like `advance`, but skipping what `options` leaves out. Hidden members always have their
+    // object's brackets around them, so this never stops on one.
+    pub fn advance_visible(
+        &mut self,
+        folds: &HashSet<(usize, Vec<usize>)>,
+        options: RenderOptions,
+    ) -> Option<()> {
+        self.advance_within(folds, options.max_depth)?;
+        while self.is_hidden(options) {
+            self.advance_within(folds, options.max_depth)?;
+        }
+        Some(())
+    }
+    pub fn regress_visible(
+        &mut self,
+        folds: &HashSet<(usize, Vec<usize>)>,
+        options: RenderOptions,
+    ) -> Option<()> {
+        self.regress_within(folds, options.max_depth)?;
+        while self.is_hidden(options) {
+            self.regress_within(folds, options.max_depth)?;
         }
+        Some(())
+    }
+    fn leaf_to_string(&self) -> Option<Cow<str>> {
+        scalar_to_string(&self.focus)
     }
     // TODO: do something more efficient
     pub fn matches_path(&self, path: &ValuePath) -> bool {
@@ -609,9 +856,27 @@ impl LeafCursor {
         }
         false
     }
+    // Every position `search` can stop at, in order.
+    pub fn all_matches(jsons: Rc<[JV]>, re: &Regex) -> Vec<ValuePath> {
+        let mock_folds = HashSet::new();
+        let mut matches = Vec::new();
+        let mut cursor = match LeafCursor::new(jsons) {
+            Some(cursor) => cursor,
+            None => return matches,
+        };
+        loop {
+            if cursor.regex_matches(re) {
+                matches.push(cursor.to_path());
+            }
+            if cursor.advance(&mock_folds).is_none() {
+                return matches;
+            }
+        }
+    }
     pub fn search(mut self, re: &Regex) -> Option<Self> {
         let mock_folds = HashSet::new();
         let start = self.to_path();
+        let start_matches = self.regex_matches(re);
         while let Some(()) = self.advance(&mock_folds) {
             if self.regex_matches(re) {
                 return Some(self);
@@ -626,11 +891,16 @@ impl LeafCursor {
                 .advance(&mock_folds)
                 .expect("Shouldn't hit end again before hitting initial position");
         }
+        // Back where it started, which is the only match if it matches at all.
+        if start_matches {
+            return Some(cursor);
+        }
         None
     }
     pub fn search_back(mut self, re: &Regex) -> Option<Self> {
         let mock_folds = HashSet::new();
         let start = self.to_path();
+        let start_matches = self.regex_matches(re);
         while let Some(()) = self.regress(&mock_folds) {
             if self.regex_matches(re) {
                 return Some(self);
@@ -645,6 +915,10 @@ impl LeafCursor {
                 .regress(&mock_folds)
                 .expect("Shouldn't hit start again before hitting initial position");
         }
+        // Back where it started, which is the only match if it matches at all.
+        if start_matches {
+            return Some(cursor);
+        }
         None
     }
     pub fn descends_from_or_matches(&self, other: &Self) -> bool {
@@ -661,6 +935,24 @@ impl LeafCursor {
     }
 }
 
+// One step into a json value, as used by tools like jq's `path` and JSON pointers. Saved as the
+// key or index itself, like jq's paths.
+#[derive(PartialEq, Eq, Hash, Debug, Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum PathElement {
+    Key(String),
+    Index(usize),
+}
+
+impl fmt::Display for PathElement {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PathElement::Key(key) => write!(f, "[{:?}]", key),
+            PathElement::Index(i) => write!(f, "[{}]", i),
+        }
+    }
+}
+
 #[derive(PartialEq, Eq, Hash, Debug, Clone)]
 pub struct ValuePath {
     top_index: usize,
@@ -668,6 +960,14 @@ pub struct ValuePath {
     focus_position: FocusPosition,
 }
 impl ValuePath {
+    // The inverse of strip_position, for the start of the value.
+    pub fn from_stripped((top_index, frames): (usize, Vec<usize>)) -> Self {
+        ValuePath {
+            top_index,
+            frames,
+            focus_position: FocusPosition::Start,
+        }
+    }
     pub fn strip_position(self) -> (usize, Vec<usize>) {
         let ValuePath {
             top_index,
@@ -725,16 +1025,17 @@ pub struct GlobalPath {
 
 #[cfg(test)]
 mod tests {
-    use super::{GlobalCursor, LeafCursor};
+    use super::{GlobalCursor, LeafCursor, PathElement};
     use crate::{
         jq::jv::JV,
-        lines::LineCursor,
+        lines::{LineCursor, RenderOptions},
         testing::{arb_json, json_to_lines},
     };
     use pretty_assertions::assert_eq;
     use proptest::proptest;
     use serde_json::{json, Value};
     use std::{collections::HashSet, rc::Rc};
+    use tui::{layout::Rect, style::Color};
 
     fn check_advancing_terminates(jsons: Vec<Value>) {
         let jsons: Vec<JV> = jsons.iter().map(|v| v.into()).collect();
@@ -757,7 +1058,9 @@ mod tests {
         let folds = HashSet::new();
         let width = u16::MAX;
         let mut expected_lines = json_to_lines(values.iter()).into_iter();
-        if let Some(mut cursor) = GlobalCursor::new(jsons.into(), width, &folds) {
+        if let Some(mut cursor) =
+            GlobalCursor::new(jsons.into(), width, &folds, RenderOptions::default())
+        {
             let mut actual_lines = Vec::new();
             actual_lines.push(cursor.current_line());
             let expected_line = expected_lines
@@ -806,6 +1109,131 @@ mod tests {
         }
     }
     #[test]
+    fn unit_from_json_path() {
+        let jsons: Vec<JV> = vec![(&json!({"a": 1, "b": [true, {"c": null}]})).into()];
+        let jsons: Rc<[JV]> = jsons.into();
+        let path = vec![
+            PathElement::Key("b".to_owned()),
+            PathElement::Index(1),
+            PathElement::Key("c".to_owned()),
+        ];
+        let cursor = LeafCursor::from_json_path(jsons.clone(), 0, &path).unwrap();
+        assert_eq!(cursor.focus, (&json!(null)).into());
+        assert_eq!(cursor.current_key().unwrap().value(), "c");
+        assert_eq!(cursor.to_json_path(), path);
+        assert!(LeafCursor::from_json_path(jsons.clone(), 0, &path[..1]).is_some());
+        assert!(LeafCursor::from_json_path(jsons.clone(), 0, &[PathElement::Index(0)]).is_none());
+        assert!(LeafCursor::from_json_path(jsons, 1, &[]).is_none());
+    }
+    #[test]
+    fn unit_indent_width() {
+        let jsons: Vec<JV> = vec![(&json!({"a": [[null]]})).into()];
+        let jsons: Rc<[JV]> = jsons.into();
+        let path = vec![
+            PathElement::Key("a".to_owned()),
+            PathElement::Index(0),
+            PathElement::Index(0),
+        ];
+        let cursor = LeafCursor::from_json_path(jsons, 0, &path).unwrap();
+        let folds = HashSet::new();
+        let options = |indent_width| RenderOptions {
+            indent_width,
+            ..RenderOptions::default()
+        };
+        assert_eq!(cursor.current_line(&folds, 80, options(1)).indent, 3);
+        assert_eq!(cursor.current_line(&folds, 80, options(4)).indent, 12);
+        // The indent never leaves less than 7 columns for content.
+        assert_eq!(cursor.current_line(&folds, 10, options(4)).indent, 3);
+    }
+    #[test]
+    fn unit_index_gutter() {
+        let jsons: Vec<JV> = vec![(&json!({"a": [null, "a long string that wraps", {}]})).into()];
+        let options = RenderOptions {
+            index_gutter: true,
+            ..RenderOptions::default()
+        };
+        let rect = Rect::new(0, 0, 24, 10);
+        let folds = HashSet::new();
+        let mut cursor = GlobalCursor::new(jsons.into(), rect.width, &folds, options).unwrap();
+        let lines: Vec<String> = cursor
+            .render_lines(None, &folds, rect)
+            .into_iter()
+            .map(|line| line.0.into_iter().map(|span| span.content).collect())
+            .collect();
+        let expected = vec![
+            "        {",
+            "          \"a\" : [",
+            "    [0]     null,",
+            "    [1]     \"a long stri",
+            "              ng that wr",
+            "              aps\",",
+            "    [2]     {",
+            "            }",
+            "          ]",
+            "        }",
+        ];
+        assert_eq!(lines, expected);
+    }
+    #[test]
+    fn unit_max_depth() {
+        let jsons: Vec<JV> = vec![(&json!({"a": [2, [[1]], []]})).into()];
+        let jsons: Rc<[JV]> = jsons.into();
+        let options = RenderOptions {
+            max_depth: 2,
+            ..RenderOptions::default()
+        };
+        let folds = HashSet::new();
+        let mut cursor = GlobalCursor::new(jsons.clone(), 80, &folds, options).unwrap();
+        let lines = cursor.render_plain_lines(&folds, 80, None);
+        let expected = vec![
+            "{",
+            "  \"a\" : [",
+            "    2,",
+            "    […(depth limit)]",
+            "    [",
+            "    ]",
+            "  ]",
+            "}",
+        ];
+        assert_eq!(lines, expected);
+        // Going back up skips the same values.
+        let mut cursor = GlobalCursor::new_end(jsons, 80, &folds, options).unwrap();
+        let mut count = 1;
+        while cursor.regress(&folds, 80).is_some() {
+            count += 1;
+        }
+        assert_eq!(count, expected.len());
+    }
+    #[test]
+    fn unit_depth_ruler() {
+        let jsons: Vec<JV> = vec![(&json!({"a": [null, "wraps around"]})).into()];
+        let options = RenderOptions {
+            depth_ruler: true,
+            ..RenderOptions::default()
+        };
+        let rect = Rect::new(0, 0, 16, 10);
+        let folds = HashSet::new();
+        let mut cursor = GlobalCursor::new(jsons.into(), rect.width, &folds, options).unwrap();
+        let lines: Vec<(String, Option<Color>)> = cursor
+            .render_lines(None, &folds, rect)
+            .into_iter()
+            .map(|line| {
+                let ruler = line.0[0].style.fg;
+                (line.0.into_iter().map(|span| span.content).collect(), ruler)
+            })
+            .collect();
+        let expected = vec![
+            ("▎ {".to_owned(), Some(Color::Red)),
+            ("▎   \"a\" : [".to_owned(), Some(Color::Yellow)),
+            ("▎     null,".to_owned(), Some(Color::Green)),
+            ("▎     \"wraps aro".to_owned(), Some(Color::Green)),
+            ("▎       und\"".to_owned(), Some(Color::Green)),
+            ("▎   ]".to_owned(), Some(Color::Yellow)),
+            ("▎ }".to_owned(), Some(Color::Red)),
+        ];
+        assert_eq!(lines, expected);
+    }
+    #[test]
     fn unit_path_roundtrip() {
         check_path_roundtrip(vec![json!([{ "": null }])])
     }
@@ -839,7 +1267,8 @@ mod tests {
             let jsons : Rc<[JV]> = jsons.into();
             let folds = HashSet::new();
             let mut seen = HashSet::new();
-            if let Some(mut cursor) = GlobalCursor::new(jsons.clone(), width, &folds) {
+            let options = RenderOptions::default();
+            if let Some(mut cursor) = GlobalCursor::new(jsons.clone(), width, &folds, options) {
                 check_advance_regress(&cursor, &folds, width);
                 while let Some(()) = cursor.advance(&folds, width) {
                     let key = hashable_cursor_key(&cursor);
@@ -863,13 +1292,24 @@ mod tests {
                 vec![json!([[{"¡¡": "\u{0}\u{0}\u{7f}\u{3fffe}®\u{e000}A0\u{3fffe}𠀀\""}]])],
                 8,
             ),
+            (
+                vec![json!(["a\nb\n", "\n\n\"\t"]), json!("aaaaaaaaaaaa\n")],
+                8,
+            ),
         ];
-        for (values, width) in tests {
+        for ((values, width), raw_strings) in tests
+            .into_iter()
+            .flat_map(|test| vec![(test.clone(), false), (test, true)])
+        {
             let jsons: Vec<JV> = values.iter().map(|v| v.into()).collect();
             let jsons: Rc<[JV]> = jsons.into();
             let folds = HashSet::new();
             let mut seen = HashSet::new();
-            if let Some(mut cursor) = GlobalCursor::new(jsons.clone(), width, &folds) {
+            let options = RenderOptions {
+                raw_strings,
+                ..RenderOptions::default()
+            };
+            if let Some(mut cursor) = GlobalCursor::new(jsons.clone(), width, &folds, options) {
                 check_advance_regress(&cursor, &folds, width);
                 while let Some(()) = cursor.advance(&folds, width) {
                     let key = hashable_cursor_key(&cursor);
@@ -1,5 +1,9 @@
-use crate::jq::jv::{JVBool, JVNull, JVNumber, JVString, JV};
+use crate::{
+    cursor::PathElement,
+    jq::jv::{JVBool, JVNull, JVNumber, JVObject, JVString, JV},
+};
 use similar::{capture_diff, Algorithm, DiffOp};
+use std::collections::BTreeSet;
 
 #[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
 enum DiffElem {
@@ -57,11 +61,83 @@ fn diff(a: JV, b: JV) -> Vec<DiffOp> {
     )
 }
 
+// The parts of a structural diff, each an object keyed by the jq path of what's in it.
+struct StructuralDiff {
+    only_in_a: JVObject,
+    only_in_b: JVObject,
+    changed: JVObject,
+}
+
+fn path_string(path: &[PathElement]) -> String {
+    let elements: String = path.iter().map(PathElement::to_string).collect();
+    format!(".{}", elements)
+}
+
+fn diff_into(a: &JV, b: &JV, path: &mut Vec<PathElement>, out: &mut StructuralDiff) {
+    match (a, b) {
+        (JV::Object(obj_a), JV::Object(obj_b)) => {
+            let keys: BTreeSet<String> = obj_a
+                .iter()
+                .chain(obj_b.iter())
+                .map(|(k, _)| k.to_owned())
+                .collect();
+            for key in keys {
+                let (value_a, value_b) = (obj_a.get(&key), obj_b.get(&key));
+                path.push(PathElement::Key(key));
+                match (value_a, value_b) {
+                    (Some(a), Some(b)) => diff_into(&a, &b, path, out),
+                    (Some(a), None) => out.only_in_a.set(&path_string(path), a),
+                    (None, Some(b)) => out.only_in_b.set(&path_string(path), b),
+                    (None, None) => {}
+                }
+                path.pop();
+            }
+        }
+        // Elements are compared by index, so one inserted early shows as every later one changing.
+        (JV::Array(arr_a), JV::Array(arr_b)) => {
+            let len = std::cmp::max(arr_a.len(), arr_b.len());
+            for i in 0..len {
+                path.push(PathElement::Index(i as usize));
+                match (arr_a.get(i), arr_b.get(i)) {
+                    (Some(a), Some(b)) => diff_into(&a, &b, path, out),
+                    (Some(a), None) => out.only_in_a.set(&path_string(path), a),
+                    (None, Some(b)) => out.only_in_b.set(&path_string(path), b),
+                    (None, None) => {}
+                }
+                path.pop();
+            }
+        }
+        _ if a == b => {}
+        _ => {
+            let mut change = JVObject::new();
+            change.set("a", a.clone());
+            change.set("b", b.clone());
+            out.changed.set(&path_string(path), change.into());
+        }
+    }
+}
+
+// Compares `a` and `b` key by key and index by index, giving what's only in `a`, what's only in
+// `b`, and the leaves (or values of different types) that differ, each keyed by its jq path.
+pub fn structural_diff(a: &JV, b: &JV) -> JV {
+    let mut out = StructuralDiff {
+        only_in_a: JVObject::new(),
+        only_in_b: JVObject::new(),
+        changed: JVObject::new(),
+    };
+    diff_into(a, b, &mut Vec::new(), &mut out);
+    let mut diff = JVObject::new();
+    diff.set("only_in_a", out.only_in_a.into());
+    diff.set("only_in_b", out.only_in_b.into());
+    diff.set("changed", out.changed.into());
+    diff.into()
+}
+
 #[cfg(test)]
 mod tests {
-    use super::{diff, to_diffable};
+    use super::{diff, structural_diff, to_diffable};
     use crate::jq::jv::JV;
-    use serde_json::json;
+    use serde_json::{json, value::Value};
     #[test]
     fn unit_diff() {
         let a: JV = (&json!({
@@ -78,4 +154,26 @@ mod tests {
         dbg!(to_diffable(b.clone()));
         dbg!(diff(a, b));
     }
+    #[test]
+    fn unit_structural_diff() {
+        let a: JV =
+            (&json!({"id": 1, "name": "a", "tags": ["x", "y"], "meta": {"old": true}})).into();
+        let b: JV =
+            (&json!({"id": 2, "name": "a", "tags": ["x"], "meta": {"new": null}, "extra": {}}))
+                .into();
+        let diff: Value = (&structural_diff(&a, &b)).into();
+        assert_eq!(
+            diff,
+            json!({
+                "only_in_a": {".[\"meta\"][\"old\"]": true, ".[\"tags\"][1]": "y"},
+                "only_in_b": {".[\"extra\"]": {}, ".[\"meta\"][\"new\"]": null},
+                "changed": {".[\"id\"]": {"a": 1.0, "b": 2.0}},
+            })
+        );
+        let diff: Value = (&structural_diff(&a, &a)).into();
+        assert_eq!(
+            diff,
+            json!({"only_in_a": {}, "only_in_b": {}, "changed": {}})
+        );
+    }
 }
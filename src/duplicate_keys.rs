@@ -0,0 +1,233 @@
+use crate::cursor::PathElement;
+use std::{
+    cell::RefCell,
+    collections::HashSet,
+    io::{self, Read},
+    rc::Rc,
+};
+
+// Finds keys that appear more than once in the same object. Parsers keep only the last value for
+// a key, so this has to look at the json as it's read, before the duplicates are lost. It only
+// tracks enough of the syntax to know where keys are: it's up to the parser to reject bad json.
+#[derive(Debug, Default)]
+pub struct DuplicateKeys {
+    // One frame per open array or object.
+    frames: Vec<KeyFrame>,
+    // Top level values started so far.
+    values: usize,
+    // Whether we're in a number or literal, so a top level one is only counted once.
+    in_scalar: bool,
+    string: Option<StringState>,
+    // Paths like `$0["a"]["b"]`, each listed once, in the order they're found.
+    found: Vec<String>,
+}
+
+#[derive(Debug)]
+enum KeyFrame {
+    Object {
+        keys: HashSet<String>,
+        // The key whose value we're in, if any.
+        key: Option<String>,
+        expecting_key: bool,
+    },
+    Array {
+        index: usize,
+    },
+}
+
+#[derive(Debug)]
+struct StringState {
+    // The raw bytes of a key, quotes and escapes included, to be decoded once it ends. Other
+    // strings aren't kept.
+    key: Option<Vec<u8>>,
+    escaped: bool,
+}
+
+impl DuplicateKeys {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    pub fn found(&self) -> &[String] {
+        &self.found
+    }
+    pub fn into_found(self) -> Vec<String> {
+        self.found
+    }
+    pub fn feed(&mut self, bytes: &[u8]) {
+        for &b in bytes {
+            self.feed_byte(b);
+        }
+    }
+    fn feed_byte(&mut self, b: u8) {
+        if let Some(string) = &mut self.string {
+            if let Some(key) = &mut string.key {
+                key.push(b);
+            }
+            if string.escaped {
+                string.escaped = false;
+            } else if b == b'\\' {
+                string.escaped = true;
+            } else if b == b'"' {
+                let key = self.string.take().and_then(|string| string.key);
+                if let Some(key) = key {
+                    self.end_key(&key);
+                }
+            }
+            return;
+        }
+        let in_scalar = self.in_scalar;
+        self.in_scalar = false;
+        match b {
+            b'{' => {
+                self.start_value();
+                self.frames.push(KeyFrame::Object {
+                    keys: HashSet::new(),
+                    key: None,
+                    expecting_key: true,
+                });
+            }
+            b'[' => {
+                self.start_value();
+                self.frames.push(KeyFrame::Array { index: 0 });
+            }
+            b'}' | b']' => {
+                self.frames.pop();
+            }
+            b',' => match self.frames.last_mut() {
+                Some(KeyFrame::Object { expecting_key, .. }) => *expecting_key = true,
+                Some(KeyFrame::Array { index }) => *index += 1,
+                None => {}
+            },
+            b'"' => {
+                let is_key = matches!(
+                    self.frames.last(),
+                    Some(KeyFrame::Object {
+                        expecting_key: true,
+                        ..
+                    })
+                );
+                if !is_key {
+                    self.start_value();
+                }
+                self.string = Some(StringState {
+                    key: if is_key { Some(vec![b]) } else { None },
+                    escaped: false,
+                });
+            }
+            b' ' | b'\t' | b'\n' | b'\r' | b':' => {}
+            _ => {
+                if !in_scalar {
+                    self.start_value();
+                }
+                self.in_scalar = true;
+            }
+        }
+    }
+    fn start_value(&mut self) {
+        if self.frames.is_empty() {
+            self.values += 1;
+        }
+    }
+    fn end_key(&mut self, raw: &[u8]) {
+        // Anything that doesn't decode is bad json, which the parser will report.
+        let key: String = match serde_json::from_slice(raw) {
+            Ok(key) => key,
+            Err(_) => return,
+        };
+        let duplicate = match self.frames.last_mut() {
+            Some(KeyFrame::Object {
+                keys,
+                key: current,
+                expecting_key,
+            }) => {
+                *expecting_key = false;
+                *current = Some(key.clone());
+                !keys.insert(key)
+            }
+            _ => return,
+        };
+        if duplicate {
+            let path = self.path();
+            if !self.found.contains(&path) {
+                self.found.push(path);
+            }
+        }
+    }
+    // Where we are, as shown in the bookmark list.
+    fn path(&self) -> String {
+        let elements = self.frames.iter().filter_map(|frame| match frame {
+            KeyFrame::Object { key, .. } => key.clone().map(PathElement::Key),
+            KeyFrame::Array { index } => Some(PathElement::Index(*index)),
+        });
+        let path: String = elements.map(|element| element.to_string()).collect();
+        format!("${}{}", self.values.saturating_sub(1), path)
+    }
+}
+
+// Feeds everything read through it to a DuplicateKeys.
+pub(crate) struct CheckKeys<R> {
+    inner: R,
+    keys: Rc<RefCell<DuplicateKeys>>,
+}
+
+impl<R> CheckKeys<R> {
+    pub(crate) fn new(inner: R) -> (Self, Rc<RefCell<DuplicateKeys>>) {
+        let keys = Rc::new(RefCell::new(DuplicateKeys::new()));
+        let r = CheckKeys {
+            inner,
+            keys: keys.clone(),
+        };
+        (r, keys)
+    }
+}
+
+impl<R: Read> Read for CheckKeys<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.keys.borrow_mut().feed(&buf[..n]);
+        Ok(n)
+    }
+}
+
+// A warning listing where the duplicates are, or None if there aren't any.
+pub fn duplicates_warning(found: &[String]) -> Option<String> {
+    const SHOWN: usize = 20;
+    if found.is_empty() {
+        return None;
+    }
+    let mut lines = vec!["Duplicate keys, of which only the last value is shown, at:".to_owned()];
+    lines.extend(found.iter().take(SHOWN).cloned());
+    if found.len() > SHOWN {
+        lines.push(format!("and {} more", found.len() - SHOWN));
+    }
+    Some(lines.join("\n"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::DuplicateKeys;
+
+    fn duplicates(json: &str) -> Vec<String> {
+        let mut keys = DuplicateKeys::new();
+        // Split the input, to check nothing depends on how it's read.
+        for chunk in json.as_bytes().chunks(3) {
+            keys.feed(chunk);
+        }
+        keys.into_found()
+    }
+    #[test]
+    fn unit_duplicate_keys() {
+        assert!(duplicates(r#"{"a": 1, "b": {"a": 2}, "c": ["a", "a"]}"#).is_empty());
+        assert_eq!(
+            duplicates(r#"{"a": 1, "b": [{}, {"x": "y\"", "x": 2, "x": 3}], "a": 4}"#),
+            vec![r#"$0["b"][1]["x"]"#, r#"$0["a"]"#]
+        );
+        // Later top level values, after scalars and strings.
+        assert_eq!(
+            duplicates(r#"1 "s" 2.5e3 null {"k": [true, {"k": 1, "k": 2}]}"#),
+            vec![r#"$4["k"][1]["k"]"#]
+        );
+        // Keys are compared after unescaping.
+        assert_eq!(duplicates(r#"{"a": 1, "\u0061": 2}"#), vec![r#"$0["a"]"#]);
+    }
+}
@@ -0,0 +1,330 @@
+use crate::{
+    cursor::PathElement,
+    jq::jv::{JVArray, JVNull, JVObject, JV},
+};
+use serde_json::value::Value;
+use std::cmp::Ordering;
+
+// Rebuilds `root` with the value at `path` replaced by `f` of it. Returns None if the path doesn't
+// exist or `f` returns None.
+pub fn modify_at<F: FnOnce(JV) -> Option<JV>>(root: JV, path: &[PathElement], f: F) -> Option<JV> {
+    let (first, rest) = match path.split_first() {
+        None => return f(root),
+        Some(split) => split,
+    };
+    match (root, first) {
+        (JV::Array(mut arr), PathElement::Index(i)) => {
+            let child = arr.get(*i as i32)?;
+            arr.set(*i as i32, modify_at(child, rest, f)?);
+            Some(arr.into())
+        }
+        (JV::Object(mut obj), PathElement::Key(key)) => {
+            let child = obj.get(key)?;
+            obj.set(key, modify_at(child, rest, f)?);
+            Some(obj.into())
+        }
+        _ => None,
+    }
+}
+
+// The value at `path` under `root`, if there is one.
+pub fn get_at(root: &JV, path: &[PathElement]) -> Option<JV> {
+    path.iter()
+        .try_fold(root.clone(), |jv, element| match (jv, element) {
+            (JV::Array(arr), PathElement::Index(i)) => arr.get(*i as i32),
+            (JV::Object(obj), PathElement::Key(key)) => obj.get(key),
+            _ => None,
+        })
+}
+
+// Like modify_at, but for the `top_index`th of a view's top level values.
+pub fn modify_values<F: FnOnce(JV) -> Option<JV>>(
+    values: &[JV],
+    top_index: usize,
+    path: &[PathElement],
+    f: F,
+) -> Option<Vec<JV>> {
+    let mut values = values.to_vec();
+    let top = values.get(top_index)?.clone();
+    values[top_index] = modify_at(top, path, f)?;
+    Some(values)
+}
+
+// Rebuilds `parent` without `child`, also returning the sibling the cursor should move to, if
+// there is one.
+pub fn delete_child(parent: JV, child: &PathElement) -> Option<(JV, Option<PathElement>)> {
+    match (parent, child) {
+        (JV::Array(arr), PathElement::Index(i)) => {
+            let len = arr.len() as usize;
+            if *i >= len {
+                return None;
+            }
+            let mut out = JVArray::new();
+            for (j, v) in arr.iter().enumerate().filter(|(j, _)| j != i) {
+                out.set(if j < *i { j } else { j - 1 } as i32, v);
+            }
+            // The next element slides into the deleted one's place.
+            let neighbor = match len - 1 {
+                0 => None,
+                new_len => Some(PathElement::Index(std::cmp::min(*i, new_len - 1))),
+            };
+            Some((out.into(), neighbor))
+        }
+        (JV::Object(obj), PathElement::Key(key)) => {
+            let keys: Vec<String> = obj.iter().map(|(k, _)| k.to_owned()).collect();
+            let position = keys.iter().position(|k| k == key)?;
+            let mut out = JVObject::new();
+            for (k, v) in obj.iter().filter(|(k, _)| k != key) {
+                out.set(k, v);
+            }
+            let neighbor = keys
+                .get(position + 1)
+                .or_else(|| keys.get(position.checked_sub(1)?))
+                .map(|k| PathElement::Key(k.clone()));
+            Some((out.into(), neighbor))
+        }
+        _ => None,
+    }
+}
+
+// Rebuilds `parent` with a copy of `child` right after it, also returning where the copy is.
+// Object keys are copied to `<key>_copy`, with a number added if that's taken.
+pub fn duplicate_child(parent: JV, child: &PathElement) -> Option<(JV, PathElement)> {
+    match (parent, child) {
+        (JV::Array(arr), PathElement::Index(i)) => {
+            let copy = arr.get(*i as i32)?;
+            let mut out = JVArray::new();
+            for (j, v) in arr.iter().enumerate() {
+                let j = if j <= *i { j } else { j + 1 };
+                out.set(j as i32, v);
+            }
+            out.set(*i as i32 + 1, copy);
+            Some((out.into(), PathElement::Index(i + 1)))
+        }
+        (JV::Object(mut obj), PathElement::Key(key)) => {
+            let copy = obj.get(key)?;
+            let new_key = std::iter::once(format!("{}_copy", key))
+                .chain((2..).map(|n| format!("{}_copy{}", key, n)))
+                .find(|k| obj.get(k).is_none())
+                .expect("some key must be free");
+            obj.set(&new_key, copy);
+            Some((obj.into(), PathElement::Key(new_key)))
+        }
+        _ => None,
+    }
+}
+
+// Parses a sort key like `.a.b[0]`. The leading `.` is optional, and an empty key means the
+// elements themselves.
+pub fn parse_key_path(text: &str) -> Result<Vec<PathElement>, String> {
+    let text = text.trim();
+    let text = text.strip_prefix('.').unwrap_or(text);
+    let mut path = Vec::new();
+    for part in text.split('.').filter(|part| !part.is_empty()) {
+        let (key, indices) = match part.find('[') {
+            Some(start) => part.split_at(start),
+            None => (part, ""),
+        };
+        if !key.is_empty() {
+            path.push(PathElement::Key(key.to_owned()));
+        }
+        let mut indices = indices;
+        while !indices.is_empty() {
+            let close = indices
+                .find(']')
+                .filter(|_| indices.starts_with('['))
+                .ok_or_else(|| format!("Invalid key path: {}", text))?;
+            let index = indices[1..close]
+                .parse()
+                .map_err(|_| format!("Invalid index in key path: {}", &indices[1..close]))?;
+            path.push(PathElement::Index(index));
+            indices = &indices[close + 1..];
+        }
+    }
+    Ok(path)
+}
+
+fn type_rank(jv: &JV) -> u8 {
+    match jv {
+        JV::Null(_) => 0,
+        JV::Bool(_) => 1,
+        JV::Number(_) => 2,
+        JV::String(_) => 3,
+        JV::Array(_) => 4,
+        JV::Object(_) => 5,
+    }
+}
+
+// Orders like jq: null < booleans < numbers < strings < arrays < objects. Arrays and objects
+// compare equal to others of their kind, so they keep their relative order.
+fn compare_sort_keys(a: &JV, b: &JV) -> Ordering {
+    match (a, b) {
+        (JV::Bool(a), JV::Bool(b)) => a.cmp(b),
+        (JV::Number(a), JV::Number(b)) => a.cmp(b),
+        (JV::String(a), JV::String(b)) => a.cmp(b),
+        _ => type_rank(a).cmp(&type_rank(b)),
+    }
+}
+
+// Stably sorts an array by the value at `key` in each element, treating missing keys as null.
+// Also returns the old index of each element in the sorted array.
+pub fn sort_array(arr: JV, key: &[PathElement], descending: bool) -> Option<(JV, Vec<usize>)> {
+    let arr = match arr {
+        JV::Array(arr) => arr,
+        _ => return None,
+    };
+    let null: JV = JVNull::new().into();
+    let keyed: Vec<(JV, JV)> = arr
+        .iter()
+        .map(|v| (get_at(&v, key).unwrap_or_else(|| null.clone()), v))
+        .collect();
+    let mut order: Vec<usize> = (0..keyed.len()).collect();
+    order.sort_by(|&i, &j| {
+        let ordering = compare_sort_keys(&keyed[i].0, &keyed[j].0);
+        if descending {
+            ordering.reverse()
+        } else {
+            ordering
+        }
+    });
+    let mut out = JVArray::new();
+    for (new_index, &old_index) in order.iter().enumerate() {
+        out.set(new_index as i32, keyed[old_index].1.clone());
+    }
+    Some((out.into(), order))
+}
+
+pub fn is_scalar(jv: &JV) -> bool {
+    !matches!(jv, JV::Array(_) | JV::Object(_))
+}
+
+// Parses the text of an edited leaf, which must be a json scalar.
+pub fn parse_scalar(text: &str) -> Result<JV, String> {
+    let value: Value =
+        serde_json::from_str(text).map_err(|err| format!("Invalid json: {}", err))?;
+    let jv: JV = (&value).into();
+    if is_scalar(&jv) {
+        Ok(jv)
+    } else {
+        Err("Only strings, numbers, booleans and null can be edited in place".to_owned())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        delete_child, duplicate_child, modify_at, parse_key_path, parse_scalar, sort_array,
+    };
+    use crate::{cursor::PathElement, jq::jv::JV};
+    use serde_json::{json, value::Value};
+    #[test]
+    fn unit_modify_at() {
+        let jv: JV = (&json!({"a": [1, {"b": 2}]})).into();
+        let path = vec![
+            PathElement::Key("a".to_owned()),
+            PathElement::Index(1),
+            PathElement::Key("b".to_owned()),
+        ];
+        let edited = modify_at(jv.clone(), &path, |_| Some((&json!("x")).into())).unwrap();
+        let edited: Value = (&edited).into();
+        assert_eq!(edited, json!({"a": [1.0, {"b": "x"}]}));
+        // The original is untouched.
+        let original: Value = (&jv).into();
+        assert_eq!(original, json!({"a": [1.0, {"b": 2.0}]}));
+        assert!(modify_at(jv, &[PathElement::Index(0)], Some).is_none());
+    }
+    #[test]
+    fn unit_delete_child() {
+        let delete = |value: Value, child: PathElement| {
+            let (parent, neighbor) = delete_child((&value).into(), &child).unwrap();
+            (Value::from(&parent), neighbor)
+        };
+        assert_eq!(
+            delete(json!([1, 2, 3]), PathElement::Index(1)),
+            (json!([1.0, 3.0]), Some(PathElement::Index(1)))
+        );
+        assert_eq!(
+            delete(json!([1, 2]), PathElement::Index(1)),
+            (json!([1.0]), Some(PathElement::Index(0)))
+        );
+        assert_eq!(delete(json!([1]), PathElement::Index(0)), (json!([]), None));
+        let (parent, neighbor) = delete(json!({"a": 1}), PathElement::Key("a".to_owned()));
+        assert_eq!((parent, neighbor), (json!({}), None));
+        let (parent, neighbor) = delete(json!({"a": 1, "b": 2}), PathElement::Key("a".to_owned()));
+        assert_eq!(parent, json!({"b": 2.0}));
+        assert_eq!(neighbor, Some(PathElement::Key("b".to_owned())));
+        assert!(delete_child((&json!([1])).into(), &PathElement::Index(1)).is_none());
+    }
+    #[test]
+    fn unit_duplicate_child() {
+        let duplicate = |value: Value, child: PathElement| {
+            let (parent, copy) = duplicate_child((&value).into(), &child).unwrap();
+            (Value::from(&parent), copy)
+        };
+        assert_eq!(
+            duplicate(json!([1, [2], 3]), PathElement::Index(1)),
+            (json!([1.0, [2.0], [2.0], 3.0]), PathElement::Index(2))
+        );
+        assert_eq!(
+            duplicate(
+                json!({"a": 1, "a_copy": 2}),
+                PathElement::Key("a".to_owned())
+            ),
+            (
+                json!({"a": 1.0, "a_copy": 2.0, "a_copy2": 1.0}),
+                PathElement::Key("a_copy2".to_owned())
+            )
+        );
+    }
+    #[test]
+    fn unit_parse_key_path() {
+        assert_eq!(parse_key_path("").unwrap(), vec![]);
+        assert_eq!(
+            parse_key_path(".a.b[1][2]").unwrap(),
+            vec![
+                PathElement::Key("a".to_owned()),
+                PathElement::Key("b".to_owned()),
+                PathElement::Index(1),
+                PathElement::Index(2),
+            ]
+        );
+        assert_eq!(
+            parse_key_path("a").unwrap(),
+            vec![PathElement::Key("a".to_owned())]
+        );
+        assert!(parse_key_path(".a[x]").is_err());
+        assert!(parse_key_path(".a[1").is_err());
+    }
+    #[test]
+    fn unit_sort_array() {
+        let sort = |value: Value, key: &str, descending| {
+            let key = parse_key_path(key).unwrap();
+            let (arr, order) = sort_array((&value).into(), &key, descending).unwrap();
+            (Value::from(&arr), order)
+        };
+        let value = json!([{"a": "x"}, {"a": 2}, {}, {"a": 1}]);
+        assert_eq!(
+            sort(value.clone(), ".a", false),
+            (
+                json!([{}, {"a": 1.0}, {"a": 2.0}, {"a": "x"}]),
+                vec![2, 3, 1, 0]
+            )
+        );
+        assert_eq!(
+            sort(value, ".a", true),
+            (
+                json!([{"a": "x"}, {"a": 2.0}, {"a": 1.0}, {}]),
+                vec![0, 1, 3, 2]
+            )
+        );
+        assert_eq!(sort(json!([2, 1]), "", false).0, json!([1.0, 2.0]));
+        assert!(sort_array((&json!({})).into(), &[], false).is_none());
+    }
+    #[test]
+    fn unit_parse_scalar() {
+        let parsed: Value = (&parse_scalar(" \"hi\" ").unwrap()).into();
+        assert_eq!(parsed, json!("hi"));
+        assert!(parse_scalar("hi").is_err());
+        assert!(parse_scalar("[1]").is_err());
+    }
+}
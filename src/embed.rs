@@ -0,0 +1,261 @@
+use crate::{
+    app::{App, AppRenderMode, Focus},
+    layout::JexLayout,
+    lines::GlyphMode,
+    view_tree::{FoldPreset, Scroll, View},
+};
+use crossterm::event::{KeyCode, KeyEvent};
+use std::io;
+use tui::{
+    layout::Rect,
+    widgets::{Block, Borders},
+    Frame,
+};
+
+// jex's viewer, for showing json inside another tui app. It only handles the keys that need no
+// prompt, file or history: moving between views, scrolling, folding, searching again and the
+// display toggles. There's no logging, panic hook or saved state, which are left to whoever
+// owns the terminal.
+pub struct Viewer {
+    pub app: App,
+}
+
+impl Viewer {
+    // `size` is the area the viewer will be drawn in.
+    pub fn new<R: io::Read>(r: R, name: String, size: Rect) -> io::Result<Self> {
+        let app = App::new(r, name, JexLayout::new(size, false))?;
+        Ok(Viewer { app })
+    }
+    // Returns whether the key did anything, so the embedding app can handle the ones that didn't.
+    pub fn handle_key(&mut self, key: KeyEvent, size: Rect) -> bool {
+        if handle_app_key(&mut self.app, key.code) {
+            return true;
+        }
+        let layout = self.app.layout(size);
+        let view_rect = match self.app.focus {
+            Focus::Left => layout.left,
+            Focus::Right => layout.right,
+        };
+        handle_view_key(&mut self.app, key.code, view_rect, None)
+    }
+    pub fn resize(&mut self, size: Rect) {
+        let layout = self.app.layout(size);
+        self.app.resize(layout);
+    }
+    // Moves a running query along. Call it every events::TICK_INTERVAL while self.app.is_busy().
+    pub fn tick(&mut self, size: Rect) {
+        let layout = self.app.layout(size);
+        self.app.tick(layout);
+    }
+    // Draws the viewer within `area`, which should be the size last passed in.
+    pub fn render<B: tui::backend::Backend>(&self, area: Rect) -> impl FnMut(&mut Frame<B>) + '_ {
+        self.app.render_in(AppRenderMode::Normal, Some(area))
+    }
+}
+
+// Handles a key that moves between views or changes what the panes show. Returns false for keys
+// it doesn't know.
+pub fn handle_app_key(app: &mut App, code: KeyCode) -> bool {
+    match code {
+        KeyCode::Tab => app.focus = app.focus.swap(),
+        KeyCode::Char('j') => {
+            match app.focus {
+                Focus::Left => app.left_index.advance(&app.views),
+                Focus::Right => app.right_index.advance(&app.views),
+            };
+        }
+        KeyCode::Char('k') => {
+            match app.focus {
+                Focus::Left => app.left_index.regress(&app.views),
+                Focus::Right => app.right_index.regress(&app.views),
+            };
+        }
+        KeyCode::Char(c @ 'y') | KeyCode::Char(c @ 'Y') => app.toggle_view_tree_fold(c == 'Y'),
+        KeyCode::Char('L') => {
+            app.scroll_lock = !app.scroll_lock;
+            app.set_status(if app.scroll_lock {
+                "Scroll lock on: both panes move together"
+            } else {
+                "Scroll lock off"
+            });
+        }
+        KeyCode::Char('f') => app.toggle_isolate_matches(),
+        _ => return false,
+    }
+    true
+}
+
+// The letter keys handle_view_key knows.
+const VIEW_KEYS: &str = "zCRIWb,MvHEgnN";
+
+// Handles a key that scrolls or changes how the focused view is shown, in the view drawn in
+// `view_rect`. Returns false for keys it doesn't know, including ones that need a prompt.
+pub fn handle_view_key(
+    app: &mut App,
+    code: KeyCode,
+    view_rect: Rect,
+    page_size: Option<usize>,
+) -> bool {
+    // Other keys leave the view alone, since its size may be stale by then.
+    let handled = match code {
+        KeyCode::Down | KeyCode::Up | KeyCode::Home | KeyCode::End => true,
+        KeyCode::PageDown | KeyCode::PageUp => true,
+        KeyCode::Char(c) => VIEW_KEYS.contains(c),
+        _ => false,
+    };
+    if !handled {
+        return false;
+    }
+    let mut status = None;
+    let view = match &mut app.focused_view_mut().take_frame().view {
+        View::Json(Some(view)) => view,
+        View::Json(None) | View::Error(_) => return false,
+    };
+    view.resize_to(Block::default().borders(Borders::ALL).inner(view_rect));
+    match code {
+        KeyCode::Down | KeyCode::Up | KeyCode::Home | KeyCode::End => {
+            let scroll = match code {
+                KeyCode::Down => Scroll::Down,
+                KeyCode::Up => Scroll::Up,
+                KeyCode::Home => Scroll::Top,
+                _ => Scroll::Bottom,
+            };
+            view.scroll_by(scroll);
+            app.follow_scroll(scroll);
+        }
+        KeyCode::PageDown => {
+            let scroll = Scroll::PageDown(view.page_lines(page_size));
+            if view.scroll_by(scroll) {
+                app.follow_scroll(scroll);
+            } else {
+                app.set_status("Already at the bottom");
+            }
+            return true;
+        }
+        KeyCode::PageUp => {
+            let scroll = Scroll::PageUp(view.page_lines(page_size));
+            if view.scroll_by(scroll) {
+                app.follow_scroll(scroll);
+            } else {
+                app.set_status("Already at the top");
+            }
+            return true;
+        }
+        KeyCode::Char('z') => {
+            if !view.toggle_fold() {
+                status = Some("Only arrays and objects can be folded");
+            }
+        }
+        KeyCode::Char('C') => {
+            status = Some(match view.cycle_fold_preset() {
+                FoldPreset::Manual => "Your folds",
+                FoldPreset::Compact => "Compact: folded below the top level",
+                FoldPreset::Expanded => "Expanded: nothing folded",
+            });
+        }
+        KeyCode::Char('R') => view.toggle_raw_strings(),
+        KeyCode::Char('I') => view.toggle_index_gutter(),
+        KeyCode::Char('W') => view.toggle_wrap_lines(),
+        KeyCode::Char('b') => {
+            view.toggle_depth_ruler();
+        }
+        KeyCode::Char(',') => view.toggle_format_numbers(),
+        KeyCode::Char('M') => {
+            status = Some(if view.toggle_minimal_punctuation() {
+                "Hiding brackets and commas"
+            } else {
+                "Showing brackets and commas"
+            });
+        }
+        KeyCode::Char('v') => {
+            status = Some(if view.toggle_reveal_whitespace() {
+                "Revealing whitespace in strings"
+            } else {
+                "Hiding whitespace in strings"
+            });
+        }
+        KeyCode::Char('H') => {
+            status = Some(if view.toggle_hide_nulls() {
+                "Hiding keys whose values are null"
+            } else {
+                "Showing keys whose values are null"
+            });
+        }
+        KeyCode::Char('E') => {
+            status = Some(if view.toggle_hide_empty() {
+                "Hiding keys whose values are empty arrays or objects"
+            } else {
+                "Showing keys whose values are empty arrays or objects"
+            });
+        }
+        KeyCode::Char('g') => {
+            status = Some(match view.cycle_scalar_glyphs() {
+                GlyphMode::Off => "Showing true, false and null as words",
+                GlyphMode::Beside => "Showing symbols beside true, false and null",
+                GlyphMode::Instead => "Showing true, false and null as symbols",
+            });
+        }
+        KeyCode::Char('n') => app.search(false),
+        KeyCode::Char('N') => app.search(true),
+        _ => return false,
+    }
+    if let Some(status) = status {
+        app.set_status(status);
+    }
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Viewer;
+    use crate::{cursor::PathElement, view_tree::View};
+    use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+    use tui::{backend::TestBackend, layout::Rect, Terminal};
+    const SIZE: Rect = Rect {
+        x: 0,
+        y: 0,
+        width: 100,
+        height: 50,
+    };
+    fn press(viewer: &mut Viewer, code: KeyCode) -> bool {
+        viewer.handle_key(KeyEvent::new(code, KeyModifiers::NONE), SIZE)
+    }
+    #[test]
+    fn unit_viewer_keys() {
+        let json = br#"[1, [2, 3]]"#;
+        let mut viewer = Viewer::new(&json[..], "test.json".to_owned(), SIZE).unwrap();
+        assert!(press(&mut viewer, KeyCode::Down));
+        assert!(press(&mut viewer, KeyCode::Down));
+        match &viewer.app.focused_view().frame().view {
+            View::Json(Some(view)) => {
+                assert_eq!(view.cursor.to_json_path(), vec![PathElement::Index(1)])
+            }
+            View::Json(None) | View::Error(_) => panic!("Expected a json view"),
+        }
+        assert!(press(&mut viewer, KeyCode::Char('z')));
+        // Queries need a prompt, which is up to the embedding app.
+        assert!(!press(&mut viewer, KeyCode::Char('q')));
+        let mut terminal = Terminal::new(TestBackend::new(SIZE.width, SIZE.height)).unwrap();
+        terminal.draw(viewer.render(SIZE)).unwrap();
+    }
+    #[test]
+    fn unit_viewer_renders_in_area() {
+        let area = Rect {
+            x: 10,
+            y: 5,
+            width: 60,
+            height: 20,
+        };
+        let json = br#"{"a": 1}"#;
+        let viewer = Viewer::new(&json[..], "test.json".to_owned(), area).unwrap();
+        let mut terminal = Terminal::new(TestBackend::new(SIZE.width, SIZE.height)).unwrap();
+        terminal.draw(viewer.render(area)).unwrap();
+        let buffer = terminal.backend().buffer();
+        assert_eq!(buffer.get(area.x, area.y).symbol, "┌");
+        assert_eq!(buffer.get(area.right() - 1, area.y).symbol, "┐");
+        // Nothing is drawn outside the area.
+        for (x, y) in &[(0, 0), (area.x - 1, area.y), (area.x, area.y - 1), (99, 49)] {
+            assert_eq!(buffer.get(*x, *y).symbol, " ");
+        }
+    }
+}
@@ -0,0 +1,213 @@
+use crossterm::event::{self, Event};
+use std::{
+    io,
+    sync::mpsc::{self, Receiver, Sender},
+    time::{Duration, Instant},
+};
+
+// How often to animate while something's in progress.
+pub const TICK_INTERVAL: Duration = Duration::from_millis(100);
+// How long to wait for terminal input before checking for injected events. Terminal input has to
+// be read on the main thread, or it would race rustyline for it.
+const POLL_INTERVAL: Duration = Duration::from_millis(25);
+
+#[derive(Debug)]
+pub enum AppEvent {
+    Input(Event),
+    // Sent while something's in progress, to animate it.
+    Tick,
+    // Sent by worker threads.
+    Progress(TaskProgress),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TaskProgress {
+    // Shown next to the spinner, like "Loading".
+    pub task: String,
+    pub state: TaskState,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TaskState {
+    // With how far along it is, from 0 to 1, if that's known.
+    Running(Option<f32>),
+    Done,
+}
+
+// Merges terminal input with events sent from other threads.
+pub struct EventLoop {
+    sender: Sender<AppEvent>,
+    receiver: Receiver<AppEvent>,
+    last_tick: Instant,
+}
+
+impl EventLoop {
+    pub fn new() -> Self {
+        let (sender, receiver) = mpsc::channel();
+        EventLoop {
+            sender,
+            receiver,
+            last_tick: Instant::now(),
+        }
+    }
+    // For worker threads to report progress with.
+    pub fn sender(&self) -> Sender<AppEvent> {
+        self.sender.clone()
+    }
+    // Waits for the next event. With `ticking`, there's a tick at least every TICK_INTERVAL.
+    pub fn next(&mut self, ticking: bool) -> io::Result<AppEvent> {
+        loop {
+            if let Ok(injected) = self.receiver.try_recv() {
+                return Ok(injected);
+            }
+            let mut timeout = POLL_INTERVAL;
+            if ticking {
+                let since_tick = self.last_tick.elapsed();
+                if since_tick >= TICK_INTERVAL {
+                    self.last_tick = Instant::now();
+                    return Ok(AppEvent::Tick);
+                }
+                timeout = timeout.min(TICK_INTERVAL - since_tick);
+            }
+            if event::poll(timeout).map_err(crossterm_error)? {
+                return event::read().map(AppEvent::Input).map_err(crossterm_error);
+            }
+        }
+    }
+}
+
+impl Default for EventLoop {
+    fn default() -> Self {
+        EventLoop::new()
+    }
+}
+
+// Reports how much of `total` bytes has been read as progress on `task`, and that the task is
+// done once it's dropped, so reading it on a worker thread shows in the status line.
+pub struct ProgressRead<R> {
+    inner: R,
+    task: String,
+    total: u64,
+    read: u64,
+    // Only whole percents are sent, so big files don't flood the channel.
+    percent: Option<u64>,
+    sender: Sender<AppEvent>,
+}
+
+impl<R> ProgressRead<R> {
+    pub fn new(inner: R, task: &str, total: u64, sender: Sender<AppEvent>) -> Self {
+        ProgressRead {
+            inner,
+            task: task.to_owned(),
+            total,
+            read: 0,
+            percent: None,
+            sender,
+        }
+    }
+    fn send(&self, state: TaskState) {
+        let progress = TaskProgress {
+            task: self.task.clone(),
+            state,
+        };
+        // If the receiver is gone there's no one to tell.
+        let _ = self.sender.send(AppEvent::Progress(progress));
+    }
+}
+
+impl<R: io::Read> io::Read for ProgressRead<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.read += n as u64;
+        let percent = (self.read * 100)
+            .checked_div(self.total)
+            .map_or(100, |percent| percent.min(100));
+        if self.percent != Some(percent) {
+            self.percent = Some(percent);
+            self.send(TaskState::Running(Some(percent as f32 / 100.0)));
+        }
+        Ok(n)
+    }
+}
+
+impl<R> Drop for ProgressRead<R> {
+    fn drop(&mut self) {
+        self.send(TaskState::Done);
+    }
+}
+
+fn crossterm_error(err: crossterm::ErrorKind) -> io::Error {
+    match err {
+        crossterm::ErrorKind::IoError(err) => err,
+        err => io::Error::other(err.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{AppEvent, EventLoop, ProgressRead, TaskProgress, TaskState, TICK_INTERVAL};
+    use std::{io::Read, sync::mpsc, thread, time::Instant};
+
+    #[test]
+    fn unit_injected_events() {
+        let mut events = EventLoop::new();
+        let sender = events.sender();
+        let progress = TaskProgress {
+            task: "Working".to_owned(),
+            state: TaskState::Running(Some(0.5)),
+        };
+        let sent = progress.clone();
+        thread::spawn(move || sender.send(AppEvent::Progress(sent)).unwrap())
+            .join()
+            .unwrap();
+        match events.next(false).unwrap() {
+            AppEvent::Progress(received) => assert_eq!(received, progress),
+            event => panic!("Expected progress, got {:?}", event),
+        }
+    }
+    #[test]
+    fn unit_progress_read() {
+        let (sender, receiver) = mpsc::channel();
+        let data = b"0123456789";
+        let mut r = ProgressRead::new(&data[..], "Loading", data.len() as u64, sender);
+        let mut buf = [0; 4];
+        let mut contents = Vec::new();
+        loop {
+            let n = r.read(&mut buf).unwrap();
+            if n == 0 {
+                break;
+            }
+            contents.extend_from_slice(&buf[..n]);
+        }
+        assert_eq!(contents, data);
+        drop(r);
+        let states: Vec<TaskState> = receiver
+            .try_iter()
+            .map(|event| match event {
+                AppEvent::Progress(progress) => {
+                    assert_eq!(progress.task, "Loading");
+                    progress.state
+                }
+                event => panic!("Expected progress, got {:?}", event),
+            })
+            .collect();
+        // Reading the end again doesn't repeat 100%.
+        assert_eq!(
+            states,
+            vec![
+                TaskState::Running(Some(0.4)),
+                TaskState::Running(Some(0.8)),
+                TaskState::Running(Some(1.0)),
+                TaskState::Done,
+            ]
+        );
+    }
+    #[test]
+    fn unit_ticks() {
+        let mut events = EventLoop::new();
+        // Due ticks come before waiting for input.
+        events.last_tick = Instant::now() - TICK_INTERVAL;
+        assert!(matches!(events.next(true).unwrap(), AppEvent::Tick));
+        assert!(events.last_tick.elapsed() < TICK_INTERVAL);
+    }
+}
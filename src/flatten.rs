@@ -0,0 +1,65 @@
+use crate::jq::jv::{JVObject, JV};
+
+// Escapes `separator` and backslashes in a key, so flattened keys can be split unambiguously.
+fn escape_key(key: &str, separator: char) -> String {
+    let mut escaped = String::with_capacity(key.len());
+    for c in key.chars() {
+        if c == separator || c == '\\' {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+fn flatten_into(jv: &JV, prefix: Option<&str>, separator: char, out: &mut JVObject) {
+    let join = |key: &str| match prefix {
+        Some(prefix) => format!("{}{}{}", prefix, separator, key),
+        None => key.to_owned(),
+    };
+    match jv {
+        JV::Object(obj) if !obj.is_empty() => {
+            for (k, v) in obj.iter() {
+                flatten_into(&v, Some(&join(&escape_key(k, separator))), separator, out);
+            }
+        }
+        JV::Array(arr) if !arr.is_empty() => {
+            for (i, v) in arr.iter().enumerate() {
+                flatten_into(&v, Some(&join(&i.to_string())), separator, out);
+            }
+        }
+        // Scalars and empty containers are leaves.
+        _ => out.set(prefix.unwrap_or(""), jv.clone()),
+    }
+}
+
+// Turns nested objects and arrays into a single object keyed by the path to each leaf, so
+// `{"a": {"b": 1}, "c": [2]}` becomes `{"a.b": 1, "c.0": 2}` with a `.` separator.
+pub fn flatten(jv: &JV, separator: char) -> JV {
+    let mut out = JVObject::new();
+    flatten_into(jv, None, separator, &mut out);
+    out.into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::flatten;
+    use crate::jq::jv::JV;
+    use serde_json::{json, value::Value};
+    #[test]
+    fn unit_flatten() {
+        let jv: JV = (&json!({"a": {"b": 1}, "c": [2, 3], "d.e": {"f\\g": null}, "h": []})).into();
+        let flat: Value = (&flatten(&jv, '.')).into();
+        let expected = json!({
+            "a.b": 1.0,
+            "c.0": 2.0,
+            "c.1": 3.0,
+            "d\\.e.f\\\\g": null,
+            "h": [],
+        });
+        assert_eq!(flat, expected);
+        let jv: JV = (&json!("scalar")).into();
+        let flat: Value = (&flatten(&jv, '/')).into();
+        assert_eq!(flat, json!({"": "scalar"}));
+    }
+}
@@ -0,0 +1,450 @@
+use crate::{
+    compression::{self, Compression},
+    duplicate_keys::{CheckKeys, DuplicateKeys},
+    jq::jv::JV,
+};
+use memmap2::Mmap;
+use serde_json::{value::Value, Deserializer};
+use std::{
+    cell::Cell,
+    fs,
+    io::{self, Read},
+    path::Path,
+    process::{Command, Stdio},
+    rc::Rc,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InputFormat {
+    Json,
+    Yaml,
+    Toml,
+}
+
+impl InputFormat {
+    // Anything we don't recognize is treated as json, which is what jex has always done. The
+    // extension of a compressed file is the one before `.gz` or `.zst`.
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Self {
+        let extension_of = |path: &Path| {
+            path.extension()
+                .and_then(|ext| ext.to_str())
+                .map(|ext| ext.to_ascii_lowercase())
+        };
+        let path = path.as_ref();
+        let mut extension = extension_of(path);
+        if extension.as_deref().is_some_and(Compression::is_extension) {
+            extension = path
+                .file_stem()
+                .and_then(|stem| extension_of(Path::new(stem)));
+        }
+        match extension.as_deref() {
+            Some("yaml") | Some("yml") => InputFormat::Yaml,
+            Some("toml") => InputFormat::Toml,
+            _ => InputFormat::Json,
+        }
+    }
+    // Json files can hold any number of values, back to back or separated by whitespace, and each
+    // is a top level value of its own.
+    pub fn read_values<R: io::Read>(self, r: R) -> io::Result<Vec<JV>> {
+        self.read_checked(r).map(|(values, _)| values)
+    }
+    // Like read_values, but also returns the paths of json keys that appear more than once in the
+    // same object. YAML and TOML parsers reject those themselves.
+    pub fn read_checked<R: io::Read>(self, r: R) -> io::Result<(Vec<JV>, Vec<String>)> {
+        // serde_json reads a byte at a time, so buffer after checking to check in big chunks.
+        let mut r = io::BufReader::new(Utf8Check::new(SkipBom::new(r)));
+        match self {
+            InputFormat::Json => {
+                let (r, bytes_read) = CountingRead::new(r);
+                let (r, keys) = CheckKeys::new(r);
+                let values = Deserializer::from_reader(r)
+                    .into_iter::<JV>()
+                    .collect::<Result<Vec<JV>, _>>()
+                    .map_err(|err| {
+                        io::Error::new(
+                            io::ErrorKind::InvalidData,
+                            json_error(&err, bytes_read.get()),
+                        )
+                    })?;
+                let duplicates = keys.borrow().found().to_vec();
+                Ok((values, duplicates))
+            }
+            // YAML and TOML are converted through serde_json's Value: it's slower than going
+            // straight to JV, but these files are typically small configs.
+            InputFormat::Yaml => {
+                let value: Value = serde_yaml::from_reader(r).map_err(invalid_data)?;
+                Ok((vec![(&value).into()], Vec::new()))
+            }
+            InputFormat::Toml => {
+                let mut s = String::new();
+                r.read_to_string(&mut s)?;
+                let value: Value = toml::from_str(&s).map_err(invalid_data)?;
+                Ok((vec![(&value).into()], Vec::new()))
+            }
+        }
+    }
+}
+
+// Opens a file to load, decompressing it if it's gzip or zstd. With `lossy_utf8`, invalid UTF-8
+// is replaced with U+FFFD instead of failing to load, which means reading the whole file up front.
+pub fn open_input<P: AsRef<Path>>(
+    path: P,
+    lossy_utf8: bool,
+) -> io::Result<Box<dyn io::Read + Send>> {
+    let f = compression::decompress(io::BufReader::new(fs::File::open(path)?))?;
+    if !lossy_utf8 {
+        return Ok(Box::new(f));
+    }
+    decode_lossy(f).map(|r| Box::new(r) as Box<dyn io::Read + Send>)
+}
+
+// Whether the file at `path` can be used as it is wherever json is expected, like by jq or by
+// saving over it: it exists, and isn't yaml, toml or compressed.
+pub fn is_plain_json<P: AsRef<Path>>(path: P) -> bool {
+    let path = path.as_ref();
+    path.is_file()
+        && InputFormat::from_path(path) == InputFormat::Json
+        && matches!(Compression::of_file(path), Ok(None))
+}
+
+// Runs `command` through the shell and opens what it prints, like open_input does a file. If the
+// command fails, the error includes what it wrote to stderr.
+pub fn open_command(command: &str, lossy_utf8: bool) -> io::Result<Box<dyn io::Read + Send>> {
+    let output = Command::new("sh")
+        .arg("-c")
+        .arg(command)
+        .stdin(Stdio::null())
+        .output()?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(io::Error::other(format!(
+            "`{}` failed ({}):\n{}",
+            command,
+            output.status,
+            stderr.trim_end()
+        )));
+    }
+    let r = compression::decompress(io::Cursor::new(output.stdout))?;
+    if !lossy_utf8 {
+        return Ok(r);
+    }
+    decode_lossy(r).map(|r| Box::new(r) as Box<dyn io::Read + Send>)
+}
+
+// Parses a json file straight out of a memory map of it, rather than copying it through buffers,
+// which keeps peak memory down for huge files. The values don't borrow from the map (jq copies
+// everything in), so it's unmapped as soon as they're parsed. Like read_checked, it also returns
+// the paths of duplicate keys.
+pub fn read_mapped(path: &str) -> io::Result<(Vec<JV>, Vec<String>)> {
+    let f = fs::File::open(path)?;
+    // If the file changes while it's mapped, the bytes can change under us, but only while
+    // parsing: nothing holds onto them afterwards.
+    let map = unsafe { Mmap::map(&f)? };
+    let bytes = &map[..];
+    let bytes = bytes.strip_prefix(BOM).unwrap_or(bytes);
+    if let Err(err) = std::str::from_utf8(bytes) {
+        return Err(invalid_utf8(err.valid_up_to()));
+    }
+    let values = Deserializer::from_slice(bytes)
+        .into_iter::<JV>()
+        .collect::<Result<Vec<JV>, _>>()
+        .map_err(|err| {
+            // From a slice, the column counts up to and including the problem, like CountingRead.
+            let line_start: usize = bytes
+                .split(|b| *b == b'\n')
+                .take(err.line().saturating_sub(1))
+                .map(|line| line.len() + 1)
+                .sum();
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                json_error(&err, line_start + err.column()),
+            )
+        })?;
+    let mut keys = DuplicateKeys::new();
+    keys.feed(bytes);
+    Ok((values, keys.into_found()))
+}
+
+pub fn decode_lossy<R: Read>(mut r: R) -> io::Result<io::Cursor<Vec<u8>>> {
+    let mut bytes = Vec::new();
+    r.read_to_end(&mut bytes)?;
+    let decoded = String::from_utf8_lossy(&bytes).into_owned();
+    Ok(io::Cursor::new(decoded.into_bytes()))
+}
+
+const BOM: &[u8] = b"\xEF\xBB\xBF";
+
+// Counts the bytes read through it, so a parse error can say where it was. serde_json reads a byte
+// at a time, so the count is exactly how far it got.
+pub(crate) struct CountingRead<R> {
+    inner: R,
+    count: Rc<Cell<usize>>,
+}
+
+impl<R> CountingRead<R> {
+    pub(crate) fn new(inner: R) -> (Self, Rc<Cell<usize>>) {
+        let count = Rc::new(Cell::new(0));
+        let r = CountingRead {
+            inner,
+            count: count.clone(),
+        };
+        (r, count)
+    }
+}
+
+impl<R: Read> Read for CountingRead<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.count.set(self.count.get() + n);
+        Ok(n)
+    }
+}
+
+// Says where parsing stopped, given how many bytes serde_json had read by then: for a bad byte,
+// that includes the byte itself. The position in serde_json's message is a line and column.
+pub(crate) fn json_error(err: &serde_json::Error, bytes_read: usize) -> String {
+    // Like invalid UTF-8, which says where it is itself.
+    if err.is_io() {
+        return err.to_string();
+    }
+    let offset = if err.is_eof() {
+        bytes_read
+    } else {
+        bytes_read.saturating_sub(1)
+    };
+    format!("Invalid json at byte {}: {}", offset, err)
+}
+
+// Drops a UTF-8 byte order mark from the start, which some Windows tools write but json parsers
+// reject.
+pub(crate) struct SkipBom<R> {
+    inner: R,
+    checked: bool,
+    // What was read while checking, if it wasn't a byte order mark.
+    start: Vec<u8>,
+}
+
+impl<R> SkipBom<R> {
+    pub(crate) fn new(inner: R) -> Self {
+        SkipBom {
+            inner,
+            checked: false,
+            start: Vec::new(),
+        }
+    }
+}
+
+impl<R: Read> Read for SkipBom<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if !self.checked {
+            let mut start = [0; 3];
+            let mut n = 0;
+            while n < start.len() {
+                match self.inner.read(&mut start[n..])? {
+                    0 => break,
+                    read => n += read,
+                }
+            }
+            self.checked = true;
+            if &start[..n] != BOM {
+                self.start = start[..n].to_vec();
+            }
+        }
+        if self.start.is_empty() {
+            return self.inner.read(buf);
+        }
+        let n = self.start.len().min(buf.len());
+        buf[..n].copy_from_slice(&self.start[..n]);
+        self.start.drain(..n);
+        Ok(n)
+    }
+}
+
+// Passes bytes through, failing with the byte offset of the first invalid UTF-8 sequence. Parsers
+// would reject it too, but without saying where it is.
+pub(crate) struct Utf8Check<R> {
+    inner: R,
+    // Bytes before `pending`.
+    offset: usize,
+    // The start of a character split across reads.
+    pending: Vec<u8>,
+}
+
+impl<R> Utf8Check<R> {
+    pub(crate) fn new(inner: R) -> Self {
+        Utf8Check {
+            inner,
+            offset: 0,
+            pending: Vec::new(),
+        }
+    }
+}
+
+impl<R: Read> Read for Utf8Check<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        if n == 0 && !self.pending.is_empty() {
+            return Err(invalid_utf8(self.offset));
+        }
+        self.pending.extend_from_slice(&buf[..n]);
+        let valid = match std::str::from_utf8(&self.pending) {
+            Ok(_) => self.pending.len(),
+            Err(err) if err.error_len().is_none() => err.valid_up_to(),
+            Err(err) => return Err(invalid_utf8(self.offset + err.valid_up_to())),
+        };
+        self.offset += valid;
+        self.pending.drain(..valid);
+        Ok(n)
+    }
+}
+
+fn invalid_utf8(offset: usize) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!(
+            "Invalid UTF-8 at byte {}. Pass --lossy-utf8 to load it with replacement characters.",
+            offset
+        ),
+    )
+}
+
+fn invalid_data<E: std::error::Error + Send + Sync + 'static>(err: E) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, err)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{decode_lossy, is_plain_json, open_command, read_mapped, InputFormat, SkipBom};
+    use crate::jq::jv::JV;
+    use serde_json::json;
+    use std::io::Read;
+
+    #[test]
+    fn unit_is_plain_json() {
+        let dir = std::env::temp_dir();
+        let plain = dir.join(format!("jex_plain_{}.json", std::process::id()));
+        let gzipped = dir.join(format!("jex_gzipped_{}.json", std::process::id()));
+        let yaml = dir.join(format!("jex_plain_{}.yaml", std::process::id()));
+        std::fs::write(&plain, "[1]").unwrap();
+        std::fs::write(&gzipped, b"\x1F\x8B\x08").unwrap();
+        std::fs::write(&yaml, "[1]").unwrap();
+        assert!(is_plain_json(&plain));
+        assert!(!is_plain_json(&gzipped));
+        assert!(!is_plain_json(&yaml));
+        assert!(!is_plain_json(dir.join("jex_no_such_file.json")));
+        for path in [plain, gzipped, yaml].iter() {
+            std::fs::remove_file(path).unwrap();
+        }
+    }
+    #[test]
+    fn unit_format_from_path() {
+        assert_eq!(InputFormat::from_path("a.json"), InputFormat::Json);
+        assert_eq!(InputFormat::from_path("a.yml"), InputFormat::Yaml);
+        assert_eq!(InputFormat::from_path("dir/a.YAML"), InputFormat::Yaml);
+        assert_eq!(InputFormat::from_path("Cargo.toml"), InputFormat::Toml);
+        assert_eq!(InputFormat::from_path("no_extension"), InputFormat::Json);
+        assert_eq!(InputFormat::from_path("a.yaml.gz"), InputFormat::Yaml);
+        assert_eq!(InputFormat::from_path("logs.json.zst"), InputFormat::Json);
+        assert_eq!(InputFormat::from_path("a.gz"), InputFormat::Json);
+    }
+    #[test]
+    fn unit_skip_bom() {
+        let values = InputFormat::Json
+            .read_values(&b"\xEF\xBB\xBF{\"a\": 1} 2"[..])
+            .unwrap();
+        let expected: Vec<JV> = vec![(&json!({"a": 1.0})).into(), (&json!(2.0)).into()];
+        assert_eq!(values, expected);
+        // Anything else at the start is left alone, even if it's shorter than a byte order mark.
+        for text in &["", "1", "12", "123", "\"\u{FEFF}\""] {
+            let mut read = String::new();
+            SkipBom::new(text.as_bytes())
+                .read_to_string(&mut read)
+                .unwrap();
+            assert_eq!(&read, text);
+        }
+    }
+    #[test]
+    fn unit_read_yaml() {
+        let yaml = "a: 1\nb:\n  - x\n  - y\n";
+        let values = InputFormat::Yaml.read_values(yaml.as_bytes()).unwrap();
+        let expected: JV = (&json!({"a": 1.0, "b": ["x", "y"]})).into();
+        assert_eq!(values, vec![expected]);
+    }
+    #[test]
+    fn unit_read_toml() {
+        let toml = "a = 1\n[b]\nc = \"d\"\n";
+        let values = InputFormat::Toml.read_values(toml.as_bytes()).unwrap();
+        let expected: JV = (&json!({"a": 1.0, "b": {"c": "d"}})).into();
+        assert_eq!(values, vec![expected]);
+    }
+    #[test]
+    fn unit_read_mapped() {
+        let path = std::env::temp_dir().join(format!("jex_mapped_{}.json", std::process::id()));
+        std::fs::write(&path, br#"{"a": [1, "b"]} null {"c": 1, "c": 2}"#).unwrap();
+        let (values, duplicates) = read_mapped(path.to_str().unwrap()).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        let expected: Vec<JV> = vec![
+            (&json!({"a": [1, "b"]})).into(),
+            (&json!(null)).into(),
+            (&json!({"c": 2})).into(),
+        ];
+        assert_eq!(values, expected);
+        assert_eq!(duplicates, vec![r#"$2["c"]"#]);
+    }
+    #[test]
+    fn unit_multiple_values() {
+        let (values, duplicates) = InputFormat::Json
+            .read_checked(&b"{\"a\": 1}{\"a\": 2}\n[3] 4"[..])
+            .unwrap();
+        // Values are separate objects, so their keys don't clash.
+        assert!(duplicates.is_empty());
+        let expected: Vec<JV> = vec![
+            (&json!({"a": 1.0})).into(),
+            (&json!({"a": 2.0})).into(),
+            (&json!([3.0])).into(),
+            (&json!(4.0)).into(),
+        ];
+        assert_eq!(values, expected);
+    }
+    #[test]
+    fn unit_trailing_junk() {
+        let json = b"{\"a\": 1}\n  junk";
+        let err = InputFormat::Json.read_values(&json[..]).unwrap_err();
+        assert!(err.to_string().starts_with("Invalid json at byte 11:"));
+        let path = std::env::temp_dir().join(format!("jex_junk_{}.json", std::process::id()));
+        std::fs::write(&path, json).unwrap();
+        let err = read_mapped(path.to_str().unwrap()).unwrap_err();
+        std::fs::remove_file(&path).unwrap();
+        assert!(err.to_string().starts_with("Invalid json at byte 11:"));
+        // Running out partway through a value is at the end.
+        let err = InputFormat::Json.read_values(&b"[1, 2"[..]).unwrap_err();
+        assert!(err.to_string().starts_with("Invalid json at byte 5:"));
+    }
+    #[test]
+    fn unit_invalid_utf8() {
+        let mut bytes = br#"{"a": "ok", "b": "caf"#.to_vec();
+        let offset = bytes.len();
+        bytes.extend_from_slice(b"\xe9\"}");
+        let err = InputFormat::Json.read_values(&bytes[..]).unwrap_err();
+        assert!(err.to_string().contains(&format!("byte {}", offset)));
+        // Multi-byte characters split across reads are fine.
+        let r = Read::chain(&b"\"caf\xc3"[..], &b"\xa9\""[..]);
+        assert!(InputFormat::Json.read_values(r).is_ok());
+        let values = InputFormat::Json
+            .read_values(decode_lossy(&bytes[..]).unwrap())
+            .unwrap();
+        let expected: JV = (&json!({"a": "ok", "b": "caf\u{fffd}"})).into();
+        assert_eq!(values, vec![expected]);
+    }
+    #[test]
+    fn unit_open_command() {
+        let r = open_command("echo '{\"a\": [1]}'; echo 2", false).unwrap();
+        let values = InputFormat::Json.read_values(r).unwrap();
+        let expected: Vec<JV> = vec![(&json!({"a": [1.0]})).into(), (&json!(2.0)).into()];
+        assert_eq!(values, expected);
+        let err = open_command("echo out; echo oops >&2; exit 3", false)
+            .err()
+            .unwrap();
+        assert!(err.to_string().ends_with(":\noops"), "{}", err);
+    }
+}
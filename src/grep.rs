@@ -0,0 +1,78 @@
+use crate::{
+    cursor::scalar_to_string,
+    jq::jv::{JVArray, JVObject, JV},
+};
+use regex::Regex;
+
+// Prunes `jv` to the parts a search for `re` would stop at, along with the objects and arrays
+// around them. A matching key keeps its whole value. Arrays keep their matching elements in order,
+// so their indices can change. Returns None if nothing matches.
+pub fn grep(jv: &JV, re: &Regex) -> Option<JV> {
+    match jv {
+        JV::Array(arr) => {
+            let mut out = JVArray::new();
+            for child in arr.iter().filter_map(|child| grep(&child, re)) {
+                out.set(out.len(), child);
+            }
+            if out.is_empty() {
+                None
+            } else {
+                Some(out.into())
+            }
+        }
+        JV::Object(obj) => {
+            let mut out = JVObject::new();
+            for (key, child) in obj.iter() {
+                let kept = if re.is_match(key) {
+                    Some(child)
+                } else {
+                    grep(&child, re)
+                };
+                if let Some(kept) = kept {
+                    out.set(key, kept);
+                }
+            }
+            if out.is_empty() {
+                None
+            } else {
+                Some(out.into())
+            }
+        }
+        scalar => {
+            let text = scalar_to_string(scalar).expect("Arrays and objects are handled above");
+            if re.is_match(&text) {
+                Some(scalar.clone())
+            } else {
+                None
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::grep;
+    use crate::jq::jv::JV;
+    use regex::Regex;
+    use serde_json::{json, Value};
+    #[test]
+    fn unit_grep() {
+        let re = Regex::new("cat").unwrap();
+        let jv: JV = (&json!({
+            "a": ["dog", "cat", {"x": "catalog", "y": 1}],
+            "b": {"c": false},
+            "category": {"anything": [null]},
+        }))
+            .into();
+        let grepped: Value = (&grep(&jv, &re).unwrap()).into();
+        let expected = json!({
+            "a": ["cat", {"x": "catalog"}],
+            "category": {"anything": [null]},
+        });
+        assert_eq!(grepped, expected);
+        let jv: JV = (&json!([1, "dog"])).into();
+        assert!(grep(&jv, &re).is_none());
+        let jv: JV = (&json!(1.5)).into();
+        assert!(grep(&jv, &Regex::new(r"^1\.5$").unwrap()).is_some());
+    }
+}
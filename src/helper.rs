@@ -1,5 +1,5 @@
 use rustyline::{
-    completion::{Completer, FilenameCompleter},
+    completion::{Completer, FilenameCompleter, Pair},
     highlight::Highlighter,
     hint::Hinter,
     line_buffer::LineBuffer,
@@ -7,31 +7,197 @@ use rustyline::{
     Context, Helper as HelperTrait, Result,
 };
 
+const JQ_BUILTINS: &[&str] = &[
+    "add",
+    "all",
+    "any",
+    "ascii_downcase",
+    "ascii_upcase",
+    "contains",
+    "del",
+    "empty",
+    "endswith",
+    "error",
+    "flatten",
+    "from_entries",
+    "fromjson",
+    "group_by",
+    "has",
+    "in",
+    "index",
+    "indices",
+    "inside",
+    "join",
+    "keys",
+    "keys_unsorted",
+    "length",
+    "map",
+    "map_values",
+    "max",
+    "max_by",
+    "min",
+    "min_by",
+    "not",
+    "path",
+    "paths",
+    "range",
+    "recurse",
+    "reverse",
+    "select",
+    "sort",
+    "sort_by",
+    "split",
+    "startswith",
+    "test",
+    "to_entries",
+    "tojson",
+    "tonumber",
+    "tostring",
+    "type",
+    "unique",
+    "unique_by",
+    "values",
+    "with_entries",
+];
+
+enum HelperCompleter {
+    Filename(FilenameCompleter),
+    // Keys are refreshed from the query's input before each prompt.
+    Jq { keys: Vec<String> },
+}
+
 pub struct Helper {
-    completer: FilenameCompleter,
+    completer: HelperCompleter,
 }
 impl Helper {
     pub fn new() -> Self {
         Helper {
-            completer: FilenameCompleter::new(),
+            completer: HelperCompleter::Filename(FilenameCompleter::new()),
+        }
+    }
+    pub fn new_jq() -> Self {
+        Helper {
+            completer: HelperCompleter::Jq { keys: Vec::new() },
+        }
+    }
+    pub fn set_keys(&mut self, new_keys: Vec<String>) {
+        if let HelperCompleter::Jq { keys } = &mut self.completer {
+            *keys = new_keys;
         }
     }
 }
+
+fn is_identifier_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '_'
+}
+
+fn is_identifier(s: &str) -> bool {
+    !s.is_empty()
+        && !s.starts_with(|c: char| c.is_ascii_digit())
+        && s.chars().all(is_identifier_char)
+}
+
+fn complete_jq(keys: &[String], line: &str, pos: usize) -> (usize, Vec<Pair>) {
+    let start = line[..pos]
+        .char_indices()
+        .rfind(|&(_, c)| !is_identifier_char(c))
+        .map_or(0, |(i, c)| i + c.len_utf8());
+    let word = &line[start..pos];
+    let candidates = if line[..start].ends_with('.') {
+        keys.iter()
+            .filter(|key| key.starts_with(word))
+            .map(|key| Pair {
+                display: key.clone(),
+                // Other keys are quoted as json strings, which jq's string syntax accepts.
+                replacement: if is_identifier(key) {
+                    key.clone()
+                } else {
+                    serde_json::to_string(key).expect("strings should serialize")
+                },
+            })
+            .collect()
+    } else if word.is_empty() {
+        Vec::new()
+    } else {
+        JQ_BUILTINS
+            .iter()
+            .filter(|builtin| builtin.starts_with(word))
+            .map(|&builtin| Pair {
+                display: builtin.to_owned(),
+                replacement: builtin.to_owned(),
+            })
+            .collect()
+    };
+    (start, candidates)
+}
+
 impl Completer for Helper {
-    type Candidate = <FilenameCompleter as Completer>::Candidate;
+    type Candidate = Pair;
     fn complete(
         &self,
         line: &str,
         pos: usize,
         ctx: &Context<'_>,
     ) -> Result<(usize, Vec<Self::Candidate>)> {
-        self.completer.complete(line, pos, ctx)
+        match &self.completer {
+            HelperCompleter::Filename(completer) => completer.complete(line, pos, ctx),
+            HelperCompleter::Jq { keys } => Ok(complete_jq(keys, line, pos)),
+        }
     }
     fn update(&self, line: &mut LineBuffer, start: usize, elected: &str) {
-        self.completer.update(line, start, elected)
+        match &self.completer {
+            HelperCompleter::Filename(completer) => completer.update(line, start, elected),
+            HelperCompleter::Jq { .. } => {
+                let end = line.pos();
+                line.replace(start..end, elected)
+            }
+        }
     }
 }
 impl Highlighter for Helper {}
 impl Hinter for Helper {}
 impl Validator for Helper {}
 impl HelperTrait for Helper {}
+
+#[cfg(test)]
+mod tests {
+    use super::complete_jq;
+    fn replacements(keys: &[&str], line: &str) -> (usize, Vec<String>) {
+        let keys: Vec<String> = keys.iter().map(|&k| k.to_owned()).collect();
+        let (start, candidates) = complete_jq(&keys, line, line.len());
+        (
+            start,
+            candidates
+                .into_iter()
+                .map(|pair| pair.replacement)
+                .collect(),
+        )
+    }
+    #[test]
+    fn unit_complete_jq() {
+        assert_eq!(
+            replacements(&[], ".[] | sel"),
+            (6, vec!["select".to_owned()])
+        );
+        assert_eq!(
+            replacements(&["name", "nested key", "id"], ".n"),
+            (1, vec!["name".to_owned(), "\"nested key\"".to_owned()])
+        );
+        assert_eq!(
+            replacements(&["name", "id"], ".a | ."),
+            (6, vec!["name".to_owned(), "id".to_owned()])
+        );
+        assert_eq!(replacements(&["name"], ""), (0, vec![]));
+        // Keys that aren't identifiers are quoted as json, which jq accepts.
+        assert_eq!(
+            replacements(&["é", "\u{1f}"], "."),
+            (1, vec!["\"é\"".to_owned(), "\"\\u001f\"".to_owned()])
+        );
+        // Non-ASCII characters before the word don't split it mid-character.
+        assert_eq!(replacements(&["é"], ".é"), (3, vec![]));
+        assert_eq!(
+            replacements(&[], "\"café\" | sel"),
+            (10, vec!["select".to_owned()])
+        );
+    }
+}
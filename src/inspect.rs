@@ -0,0 +1,262 @@
+use crate::jq::jv::JV;
+
+pub fn type_name(jv: &JV) -> &'static str {
+    match jv {
+        JV::Null(_) => "null",
+        JV::Bool(_) => "boolean",
+        JV::Number(_) => "number",
+        JV::String(_) => "string",
+        JV::Array(_) => "array",
+        JV::Object(_) => "object",
+    }
+}
+
+// Integers past this can't all be told apart as doubles, which is how jq keeps numbers, so longer
+// ones, like 64 bit ids, may have lost digits when they were loaded.
+pub const MAX_SAFE_INTEGER: f64 = 9_007_199_254_740_991.0;
+
+pub fn beyond_safe_integer(x: f64) -> bool {
+    x.abs() > MAX_SAFE_INTEGER
+}
+
+// Scalars have depth 0, and each level of nesting adds 1.
+pub fn max_depth(jv: &JV) -> usize {
+    max_depth_up_to(jv, usize::MAX)
+}
+
+// Whether `jv` nests arrays and objects more than `limit` deep. Recursive operations check this
+// first, since a deep enough value would overflow the stack.
+pub fn deeper_than(jv: &JV, limit: usize) -> bool {
+    max_depth_up_to(jv, limit) > limit
+}
+
+// Walks with a stack of its own rather than recursing, stopping once it's past `limit`.
+fn max_depth_up_to(jv: &JV, limit: usize) -> usize {
+    let mut deepest = 0;
+    let mut stack = vec![(jv.clone(), 0)];
+    while let Some((jv, depth)) = stack.pop() {
+        match jv {
+            JV::Array(arr) => stack.extend(arr.iter().map(|child| (child, depth + 1))),
+            JV::Object(obj) => stack.extend(obj.values().map(|child| (child, depth + 1))),
+            _ => continue,
+        }
+        deepest = deepest.max(depth + 1);
+        if deepest > limit {
+            break;
+        }
+    }
+    deepest
+}
+
+// The length of `jv` serialized as compact json, as jq would print it.
+pub fn serialized_size(jv: &JV) -> usize {
+    let mut size = 0;
+    let mut stack = vec![jv.clone()];
+    while let Some(jv) = stack.pop() {
+        size += match &jv {
+            JV::Null(_) => "null".len(),
+            JV::Bool(b) => b.value().to_string().len(),
+            JV::Number(x) => jq_number(x.value()).len(),
+            JV::String(s) => string_size(s.value()),
+            // Brackets, plus a comma between each pair of elements.
+            JV::Array(arr) => {
+                stack.extend(arr.iter());
+                2 + (arr.len() as usize).saturating_sub(1)
+            }
+            // Braces, commas, and a colon after each key.
+            JV::Object(obj) => {
+                let mut keys_size = 0;
+                for (k, v) in obj.iter() {
+                    keys_size += string_size(k) + 1;
+                    stack.push(v);
+                }
+                2 + (obj.len() as usize).saturating_sub(1) + keys_size
+            }
+        };
+    }
+    size
+}
+
+// A number as jq 1.6 prints it: the shortest digits that read back as the same number, with an
+// exponent of at least two digits if it's below 0.001 or has more than 15 trailing zeros. NaN
+// is printed as null, and infinities as the largest finite numbers.
+fn jq_number(x: f64) -> String {
+    if x.is_nan() {
+        return "null".to_owned();
+    }
+    let x = x.clamp(-f64::MAX, f64::MAX);
+    let sign = if x.is_sign_negative() { "-" } else { "" };
+    // Rust's exponent form also has the shortest digits, like `1.5e-7`.
+    let scientific = format!("{:e}", x.abs());
+    let (mantissa, exponent) = scientific
+        .split_once('e')
+        .expect("exponent form should have an exponent");
+    let digits: String = mantissa.chars().filter(|&c| c != '.').collect();
+    let count = digits.len() as i32;
+    // Where the decimal point goes, counting from the start of the digits.
+    let point = exponent
+        .parse::<i32>()
+        .expect("exponents should be integers")
+        + 1;
+    if point <= -4 || point > count + 15 {
+        let (first, rest) = digits.split_at(1);
+        let exponent = point - 1;
+        format!(
+            "{}{}{}{}e{}{:02}",
+            sign,
+            first,
+            if rest.is_empty() { "" } else { "." },
+            rest,
+            if exponent < 0 { '-' } else { '+' },
+            exponent.abs()
+        )
+    } else if point <= 0 {
+        format!("{}0.{}{}", sign, "0".repeat(-point as usize), digits)
+    } else if point >= count {
+        format!("{}{}{}", sign, digits, "0".repeat((point - count) as usize))
+    } else {
+        let (whole, fraction) = digits.split_at(point as usize);
+        format!("{}{}.{}", sign, whole, fraction)
+    }
+}
+
+fn string_size(s: &str) -> usize {
+    serde_json::to_string(s)
+        .expect("strings should serialize")
+        .len()
+}
+
+// Like `1.5 MB`, in powers of 1024, or exact under a kilobyte.
+pub fn human_size(bytes: usize) -> String {
+    const UNITS: [&str; 4] = ["KB", "MB", "GB", "TB"];
+    if bytes < 1024 {
+        return format!("{} B", bytes);
+    }
+    let mut size = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while size >= 1024.0 && unit + 1 < UNITS.len() {
+        size /= 1024.0;
+        unit += 1;
+    }
+    format!("{:.1} {}", size, UNITS[unit])
+}
+
+pub fn describe(jv: &JV) -> String {
+    let size = serialized_size(jv);
+    let size = if size < 1024 {
+        human_size(size)
+    } else {
+        format!("{} ({} bytes)", human_size(size), size)
+    };
+    let mut lines = vec![
+        format!("Type: {}", type_name(jv)),
+        format!("Size: {}", size),
+    ];
+    match jv {
+        JV::Null(_) => {}
+        JV::Bool(b) => lines.push(format!("Value: {}", b.value())),
+        JV::Number(x) => {
+            lines.push(format!("Value: {}", x.value()));
+            if beyond_safe_integer(x.value()) {
+                lines
+                    .push("Beyond the safe integer range, so digits may have been lost".to_owned());
+            }
+        }
+        JV::String(s) => {
+            lines.push(format!("Bytes: {}", s.value().len()));
+            lines.push(format!("Characters: {}", s.value().chars().count()));
+        }
+        JV::Array(arr) => {
+            lines.push(format!("Elements: {}", arr.len()));
+            lines.push(format!("Max depth: {}", max_depth(jv)));
+        }
+        JV::Object(obj) => {
+            lines.push(format!("Keys: {}", obj.len()));
+            lines.push(format!("Max depth: {}", max_depth(jv)));
+        }
+    }
+    lines.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        beyond_safe_integer, deeper_than, describe, human_size, jq_number, max_depth,
+        serialized_size, MAX_SAFE_INTEGER,
+    };
+    use crate::jq::jv::{JVArray, JV};
+    use serde_json::json;
+    #[test]
+    fn unit_describe() {
+        let jv: JV = (&json!({"a": [1, {"b": []}], "c": "d"})).into();
+        assert_eq!(max_depth(&jv), 4);
+        assert_eq!(
+            describe(&jv),
+            "Type: object\nSize: 26 B\nKeys: 2\nMax depth: 4"
+        );
+        let jv: JV = (&json!("héllo")).into();
+        assert_eq!(
+            describe(&jv),
+            "Type: string\nSize: 8 B\nBytes: 6\nCharacters: 5"
+        );
+        let jv: JV = (&json!(1.5)).into();
+        assert_eq!(describe(&jv), "Type: number\nSize: 3 B\nValue: 1.5");
+    }
+    #[test]
+    fn unit_beyond_safe_integer() {
+        assert!(!beyond_safe_integer(MAX_SAFE_INTEGER));
+        assert!(!beyond_safe_integer(-MAX_SAFE_INTEGER));
+        let jv = JV::parse_native("12345678901234567890").unwrap();
+        assert!(describe(&jv).ends_with("digits may have been lost"));
+        let jv = JV::parse_native("-9007199254740993").unwrap();
+        assert!(describe(&jv).ends_with("digits may have been lost"));
+    }
+    #[test]
+    fn unit_sizes() {
+        let jv: JV = (&json!({"a": [1, 2]})).into();
+        assert_eq!(serialized_size(&jv), r#"{"a":[1,2]}"#.len());
+        let jv = JV::parse_native("[1e100, 0.00001]").unwrap();
+        assert_eq!(serialized_size(&jv), "[1e+100,1e-05]".len());
+        assert_eq!(human_size(1023), "1023 B");
+        assert_eq!(human_size(1536), "1.5 KB");
+        assert_eq!(human_size(5 * 1024 * 1024), "5.0 MB");
+    }
+    #[test]
+    fn unit_jq_number() {
+        let cases = [
+            (0.0, "0"),
+            (-0.0, "-0"),
+            (1.0, "1"),
+            (-1.5, "-1.5"),
+            (0.1, "0.1"),
+            (0.0001, "0.0001"),
+            (0.00001, "1e-05"),
+            (-1.5e-7, "-1.5e-07"),
+            (1e15, "1000000000000000"),
+            (1e16, "1e+16"),
+            (123456789012345678.0, "123456789012345680"),
+            (1e100, "1e+100"),
+            (f64::INFINITY, "1.7976931348623157e+308"),
+            (f64::NAN, "null"),
+        ];
+        for &(x, printed) in cases.iter() {
+            assert_eq!(jq_number(x), printed);
+        }
+    }
+    #[test]
+    fn unit_deep_values() {
+        // Deep enough to overflow a test thread's stack if these recursed.
+        let mut jv = JV::from(JVArray::new());
+        for _ in 1..100_000 {
+            let mut arr = JVArray::new();
+            arr.set(0, jv);
+            jv = arr.into();
+        }
+        assert_eq!(max_depth(&jv), 100_000);
+        assert_eq!(serialized_size(&jv), 200_000);
+        assert!(deeper_than(&jv, 1000));
+        assert!(!deeper_than(&jv, 100_000));
+        // Freeing it recurses in jq, so it's left for the process to clean up.
+        std::mem::forget(jv);
+    }
+}
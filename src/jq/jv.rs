@@ -403,6 +403,84 @@ impl JV {
     pub fn parse_native(s: &str) -> Result<Self, String> {
         JVRaw::parse_native(s).try_into()
     }
+    pub fn null() -> Self {
+        JVNull::new().into()
+    }
+    pub fn bool(b: bool) -> Self {
+        JVBool::new(b).into()
+    }
+    pub fn number(x: f64) -> Self {
+        JVNumber::new(x).into()
+    }
+    pub fn string(s: &str) -> Self {
+        JVString::new(s).into()
+    }
+    pub fn empty_array() -> Self {
+        JVArray::new().into()
+    }
+    pub fn empty_object() -> Self {
+        JVObject::new().into()
+    }
+    // The array with `value` at index `i`, padded with nulls if that's past the end, or None if
+    // this isn't an array or `i` is past what jq can index. Values are copied on write, so other
+    // handles to the array don't change.
+    pub fn array_set(self, i: usize, value: JV) -> Option<JV> {
+        let i = i32::try_from(i).ok()?;
+        match self {
+            JV::Array(mut arr) => {
+                arr.set(i, value);
+                Some(arr.into())
+            }
+            _ => None,
+        }
+    }
+    // The object with `key` set to `value`, or None if this isn't an object.
+    pub fn object_set(self, key: &str, value: JV) -> Option<JV> {
+        match self {
+            JV::Object(mut obj) => {
+                obj.set(key, value);
+                Some(obj.into())
+            }
+            _ => None,
+        }
+    }
+    // An object's keys and values in jq's order, or nothing for anything else.
+    pub fn object_entries(&self) -> impl Iterator<Item = (String, JV)> + '_ {
+        let entries = match self {
+            JV::Object(obj) => Some(obj.iter()),
+            _ => None,
+        };
+        entries
+            .into_iter()
+            .flatten()
+            .map(|(key, value)| (key.to_owned(), value))
+    }
+    // An array's elements, or nothing for anything else.
+    pub fn array_elements(&self) -> impl Iterator<Item = JV> + '_ {
+        let elements = match self {
+            JV::Array(arr) => Some(arr.iter()),
+            _ => None,
+        };
+        elements.into_iter().flatten()
+    }
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            JV::String(s) => Some(s.value()),
+            _ => None,
+        }
+    }
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            JV::Number(x) => Some(x.value()),
+            _ => None,
+        }
+    }
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            JV::Bool(b) => Some(b.value()),
+            _ => None,
+        }
+    }
 }
 
 impl PartialEq for JVNull {
@@ -541,12 +619,47 @@ mod tests {
     use proptest::proptest;
     use serde_json::{json, value::Value};
     use std::convert::TryInto;
+    // Builds the JV for `value` from the constructors, without going through serde.
+    fn construct(value: &Value) -> JV {
+        match value {
+            Value::Null => JV::null(),
+            Value::Bool(b) => JV::bool(*b),
+            Value::Number(x) => JV::number(x.as_f64().unwrap()),
+            Value::String(s) => JV::string(s),
+            Value::Array(elements) => elements
+                .iter()
+                .enumerate()
+                .fold(JV::empty_array(), |arr, (i, element)| {
+                    arr.array_set(i, construct(element)).unwrap()
+                }),
+            Value::Object(entries) => entries
+                .iter()
+                .fold(JV::empty_object(), |obj, (key, value)| {
+                    obj.object_set(key, construct(value)).unwrap()
+                }),
+        }
+    }
     fn test_jv_roundtrip(value: Value) {
         let jv: JV = (&value).into();
         let roundtrip: Value = (&jv).try_into().unwrap();
         assert_eq!(value, roundtrip);
     }
     #[test]
+    fn unit_traversal() {
+        let jv: JV = (&json!({"a": [1, "b", true], "c": null})).into();
+        let entries: Vec<(String, JV)> = jv.object_entries().collect();
+        let keys: Vec<&str> = entries.iter().map(|(key, _)| key.as_str()).collect();
+        assert_eq!(keys, vec!["a", "c"]);
+        let elements: Vec<JV> = entries[0].1.array_elements().collect();
+        assert_eq!(elements[0].as_f64(), Some(1.0));
+        assert_eq!(elements[1].as_str(), Some("b"));
+        assert_eq!(elements[2].as_bool(), Some(true));
+        assert_eq!(elements[2].as_str(), None);
+        // Other kinds of values have no entries or elements.
+        assert_eq!(jv.array_elements().count(), 0);
+        assert_eq!(entries[1].1.object_entries().count(), 0);
+    }
+    #[test]
     fn null_jv_roundtrip() {
         test_jv_roundtrip(json!(null));
     }
@@ -576,6 +689,25 @@ mod tests {
             test_jv_roundtrip(value);
         }
     }
+    proptest! {
+        #[test]
+        fn prop_jv_construct(value in arb_json()) {
+            let constructed = construct(&value);
+            assert_eq!(constructed, JV::from(&value));
+            let roundtrip: Value = (&constructed).into();
+            assert_eq!(roundtrip, value);
+        }
+    }
+    #[test]
+    fn unit_set_copies() {
+        let arr = JV::empty_array().array_set(0, JV::number(1.0)).unwrap();
+        let padded = arr.clone().array_set(2, JV::string("x")).unwrap();
+        assert_eq!(Value::from(&arr), json!([1.0]));
+        assert_eq!(Value::from(&padded), json!([1.0, null, "x"]));
+        assert_eq!(JV::null().array_set(0, JV::null()), None);
+        assert_eq!(arr.clone().array_set(usize::MAX, JV::null()), None);
+        assert_eq!(arr.object_set("a", JV::null()), None);
+    }
     proptest! {
         #[test]
         fn prop_jv_deserialize(value in arb_json()) {
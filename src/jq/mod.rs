@@ -1,3 +1,7 @@
 pub mod jv;
 mod jv_raw;
 pub mod query;
+
+// The jq that jq-sys's bundled build compiles in. libjq 1.6 has no function returning its version,
+// so this is bumped along with jq-sys.
+pub const JQ_VERSION: &str = "1.6";
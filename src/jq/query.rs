@@ -2,9 +2,20 @@ use super::{
     jv::JV,
     jv_raw::{JVKind, JVRaw},
 };
-use jq_sys::{jq_compile, jq_init, jq_next, jq_set_error_cb, jq_start, jq_state, jq_teardown};
-use std::{convert::TryInto, ffi::CString, os::raw::c_void};
+use jq_sys::{
+    jq_compile_args, jq_init, jq_next, jq_set_attr, jq_set_error_cb, jq_start, jq_state,
+    jq_teardown,
+};
+use std::{
+    convert::TryInto,
+    ffi::CString,
+    os::raw::c_void,
+    rc::Rc,
+    time::{Duration, Instant},
+};
 
+// Runs `prog` on each of `content` in turn. Values are handed to jq as they are, never going
+// through serde, which used to be most of the time a query took (see the notes in main.rs).
 pub fn run_jq_query<'a, I: IntoIterator<Item = &'a JV>>(
     content: I,
     prog: &mut JQ,
@@ -18,6 +29,87 @@ pub fn run_jq_query<'a, I: IntoIterator<Item = &'a JV>>(
     Ok(results)
 }
 
+// A query that runs a little at a time, so its first results can be shown while it's still
+// going. Results come out in the same order as from run_jq_query.
+#[derive(Debug)]
+pub struct QueryRun {
+    prog: JQ,
+    inputs: Rc<[JV]>,
+    next_input: usize,
+    // Whether the program is partway through the input before next_input.
+    started: bool,
+    results: Vec<JV>,
+    // The most results to compute until it's raised, or None for all of them.
+    limit: Option<usize>,
+}
+
+impl QueryRun {
+    pub fn new(prog: JQ, inputs: Rc<[JV]>) -> Self {
+        QueryRun {
+            prog,
+            inputs,
+            next_input: 0,
+            started: false,
+            results: Vec::new(),
+            limit: None,
+        }
+    }
+    pub fn with_limit(mut self, limit: Option<usize>) -> Self {
+        self.limit = limit;
+        self
+    }
+    // Runs until there are at least `min_results` results and `budget` has passed, or to the end
+    // without a budget. Stops at the first error, like run_jq_query, and at the limit.
+    pub fn run(&mut self, budget: Option<Duration>, min_results: usize) -> Result<(), String> {
+        let start = Instant::now();
+        while !self.is_done() && !self.is_capped() {
+            if !self.started {
+                let input = self.inputs[self.next_input].clone();
+                self.prog.start(input.into());
+                self.next_input += 1;
+                self.started = true;
+            }
+            match self.prog.next_result() {
+                Some(res) => self.results.push(res.try_into()?),
+                None => self.started = false,
+            }
+            let out_of_time = budget.is_some_and(|budget| start.elapsed() >= budget);
+            if out_of_time && self.results.len() >= min_results {
+                break;
+            }
+        }
+        Ok(())
+    }
+    pub fn is_done(&self) -> bool {
+        !self.started && self.next_input == self.inputs.len()
+    }
+    // Whether it's stopped at the limit with input left, so there may be more results.
+    pub fn is_capped(&self) -> bool {
+        !self.is_done() && self.limit.is_some_and(|limit| self.results.len() >= limit)
+    }
+    pub fn limit(&self) -> Option<usize> {
+        self.limit
+    }
+    pub fn set_limit(&mut self, limit: Option<usize>) {
+        self.limit = limit;
+    }
+    pub fn inputs(&self) -> &Rc<[JV]> {
+        &self.inputs
+    }
+    pub fn results(&self) -> &[JV] {
+        &self.results
+    }
+}
+
+// Settings applied to every program before it's compiled.
+#[derive(Debug, Clone, Default)]
+pub struct JQOptions {
+    // Directories searched by `import` and `include`, like jq's -L.
+    pub library_paths: Vec<String>,
+    // Variables bound for the program, like jq's --arg and --argjson.
+    pub named_args: Vec<(String, JV)>,
+}
+
 #[derive(Debug)]
 pub struct JQ {
     ptr: *mut jq_state,
@@ -44,9 +136,30 @@ impl JQ {
         self.errors.as_mut().drain(..)
     }
     pub fn compile(s: &str) -> Result<Self, Vec<String>> {
+        JQ::compile_with(s, &JQOptions::default())
+    }
+    pub fn compile_with(s: &str, options: &JQOptions) -> Result<Self, Vec<String>> {
         let mut prog = JQ::new();
+        if !options.library_paths.is_empty() {
+            let mut paths = JVRaw::empty_array();
+            for (i, path) in options.library_paths.iter().enumerate() {
+                paths.array_set(i as i32, JVRaw::string(path));
+            }
+            let attr = JVRaw::string("JQ_LIBRARY_PATH");
+            unsafe {
+                jq_set_attr(
+                    prog.ptr,
+                    attr.unwrap_without_drop(),
+                    paths.unwrap_without_drop(),
+                )
+            };
+        }
+        let mut args = JVRaw::empty_object();
+        for (name, value) in options.named_args.iter() {
+            args.object_set(name, value.clone().into());
+        }
         let cstr = CString::new(s).expect("Nul byte in jq program");
-        let ok = unsafe { jq_compile(prog.ptr, cstr.as_ptr()) };
+        let ok = unsafe { jq_compile_args(prog.ptr, cstr.as_ptr(), args.unwrap_without_drop()) };
         if ok > 0 {
             Ok(prog)
         } else {
@@ -58,9 +171,23 @@ impl JQ {
         }
     }
     fn execute(&mut self, input: JVRaw) -> impl Iterator<Item = JVRaw> + '_ {
-        unsafe { jq_start(self.ptr, input.unwrap_without_drop(), 0) };
+        self.start(input);
         JQResults { jq: self }
     }
+    fn start(&mut self, input: JVRaw) {
+        unsafe { jq_start(self.ptr, input.unwrap_without_drop(), 0) };
+    }
+    // The next result for the input given to start, or None when there are no more. Errors are
+    // results too, as invalid values with a message.
+    fn next_result(&mut self) -> Option<JVRaw> {
+        let res = JVRaw {
+            ptr: unsafe { jq_next(self.ptr) },
+        };
+        match res.get_kind() {
+            JVKind::Invalid if !res.invalid_has_msg() => None,
+            _ => Some(res),
+        }
+    }
 }
 
 unsafe extern "C" fn jq_error_callback(data_pointer: *mut c_void, data: jq_sys::jv) {
@@ -77,19 +204,7 @@ struct JQResults<'a> {
 impl<'a> Iterator for JQResults<'a> {
     type Item = JVRaw;
     fn next(&mut self) -> Option<Self::Item> {
-        let res = JVRaw {
-            ptr: unsafe { jq_next(self.jq.ptr) },
-        };
-        match res.get_kind() {
-            JVKind::Invalid => {
-                if res.invalid_has_msg() {
-                    Some(res)
-                } else {
-                    None
-                }
-            }
-            _ => Some(res),
-        }
+        self.jq.next_result()
     }
 }
 
@@ -104,11 +219,11 @@ impl<'a> Drop for JQResults<'a> {
 
 #[cfg(test)]
 mod tests {
-    use super::{run_jq_query, JQ};
+    use super::{run_jq_query, JQOptions, QueryRun, JQ};
     use crate::{jq::jv::JV, testing::arb_json};
     use proptest::proptest;
     use serde_json::{json, value::Value};
-    use std::{cell::RefCell, convert::TryInto};
+    use std::{cell::RefCell, convert::TryInto, time::Duration};
     fn sample_json() -> JV {
         let val = json!({
             "hello": "world",
@@ -163,6 +278,51 @@ mod tests {
         assert_eq!(prog.unwrap_err(), expected);
     }
     #[test]
+    fn unit_jq_library_path() {
+        let dir = std::env::temp_dir().join("jex_unit_jq_library_path");
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("jex_test.jq"), "def double: . * 2;").unwrap();
+        let options = JQOptions {
+            library_paths: vec![dir.to_str().unwrap().to_owned()],
+            ..JQOptions::default()
+        };
+        let mut prog =
+            JQ::compile_with("import \"jex_test\" as t; .array[3] | t::double", &options).unwrap();
+        let res = run_jq_query(&[sample_json()], &mut prog).unwrap();
+        assert_eq!(res, vec![(&json!(2.0)).into()]);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+    #[test]
+    fn unit_jq_named_args() {
+        let options = JQOptions {
+            named_args: vec![
+                ("key".to_owned(), (&json!("hello")).into()),
+                ("default".to_owned(), (&json!([1.0])).into()),
+            ],
+            ..JQOptions::default()
+        };
+        let mut prog = JQ::compile_with(".[$key], .missing // $default", &options).unwrap();
+        let res = run_jq_query(&[sample_json()], &mut prog).unwrap();
+        assert_eq!(res, vec![(&json!("world")).into(), (&json!([1.0])).into()]);
+    }
+    #[test]
+    fn unit_query_run() {
+        let inputs: Vec<JV> = vec![(&json!([1, 2, 3])).into(), (&json!([4])).into()];
+        let mut run = QueryRun::new(JQ::compile(".[]").unwrap(), inputs.into());
+        run.run(Some(Duration::from_secs(0)), 2).unwrap();
+        assert_eq!(run.results().len(), 2);
+        assert!(!run.is_done());
+        run.run(None, 0).unwrap();
+        assert!(run.is_done());
+        let expected: Vec<JV> = (1..=4).map(|i| (&json!(i as f64)).into()).collect();
+        assert_eq!(run.results(), &expected[..]);
+        let mut run = QueryRun::new(JQ::compile(".[1]").unwrap(), vec![sample_json()].into());
+        assert_eq!(
+            run.run(None, 0).unwrap_err(),
+            "Cannot index object with number"
+        );
+    }
+    #[test]
     fn unit_jq_runtime_error() {
         let mut prog = JQ::compile(".[1]").unwrap();
         let res = run_jq_query(&[sample_json()], &mut prog);
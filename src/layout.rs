@@ -4,6 +4,7 @@ pub struct JexLayout {
     pub tree: Option<Rect>,
     pub left: Rect,
     pub right: Rect,
+    pub breadcrumb: Rect,
     pub query: Rect,
 }
 
@@ -11,7 +12,14 @@ impl JexLayout {
     pub fn new(size: Rect, show_tree: bool) -> JexLayout {
         let vchunks = Layout::default()
             .direction(Direction::Vertical)
-            .constraints([Constraint::Min(0), Constraint::Length(1)].as_ref())
+            .constraints(
+                [
+                    Constraint::Min(0),
+                    Constraint::Length(1),
+                    Constraint::Length(1),
+                ]
+                .as_ref(),
+            )
             .split(size);
         if show_tree {
             let tree_split = Layout::default()
@@ -26,7 +34,8 @@ impl JexLayout {
                 tree: Some(tree_split[0]),
                 left: views[0],
                 right: views[1],
-                query: vchunks[1],
+                breadcrumb: vchunks[1],
+                query: vchunks[2],
             }
         } else {
             let views = Layout::default()
@@ -37,7 +46,8 @@ impl JexLayout {
                 tree: None,
                 left: views[0],
                 right: views[1],
-                query: vchunks[1],
+                breadcrumb: vchunks[1],
+                query: vchunks[2],
             }
         }
     }
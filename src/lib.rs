@@ -1,10 +1,27 @@
 pub mod app;
+pub mod compression;
+pub mod config;
 pub mod cursor;
 mod diff;
+pub mod duplicate_keys;
+pub mod edit;
+pub mod embed;
+pub mod events;
+pub mod flatten;
+pub mod formats;
+pub mod grep;
 pub mod helper;
+pub mod inspect;
 pub mod jq;
 pub mod layout;
 pub mod lines;
+pub mod outline;
+pub mod palette;
+pub mod path_glob;
+pub mod query_library;
+pub mod schema;
+pub mod session;
+pub mod streaming;
 #[cfg(test)]
 mod testing;
 pub mod view_tree;
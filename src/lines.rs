@@ -1,5 +1,8 @@
-use crate::jq::jv::JVString;
-use std::{cell::RefCell, matches, ops::Range, rc::Rc};
+use crate::{
+    inspect,
+    jq::jv::{JVString, JV},
+};
+use std::{cell::RefCell, matches, ops::Range, rc::Rc, str::FromStr};
 use tui::{
     style::{Color, Modifier, Style},
     text::{Span, Spans},
@@ -7,6 +10,391 @@ use tui::{
 use unicode_general_category::{get_general_category, GeneralCategory};
 use unicode_width::UnicodeWidthChar;
 
+// Display settings that change how leaves are turned into lines.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RenderOptions {
+    // Like jq's -r: top level strings, and strings directly inside a top level array, are shown
+    // without quotes or escapes, and their newlines start new lines.
+    pub raw_strings: bool,
+    // When a query's results are shown raw without asking.
+    pub auto_raw: AutoRaw,
+    // Columns of indentation per level of nesting.
+    pub indent_width: u16,
+    // Whether to show the index of each array element in a gutter left of the json.
+    pub index_gutter: bool,
+    // Whether lines too long for the view continue on the next row, or are cut off.
+    pub wrap_lines: bool,
+    // Whether numbers are shown with thousands separators and rounded to `float_precision`
+    // decimal places. The values themselves are untouched.
+    pub format_numbers: bool,
+    pub float_precision: Option<usize>,
+    // How many characters of the first child to show on a folded line. 0 shows none.
+    pub fold_preview_width: usize,
+    // Whether object keys whose values are null, or empty arrays and objects, are left out. The
+    // values are still there, and saved.
+    pub hide_nulls: bool,
+    pub hide_empty: bool,
+    // Whether spaces, tabs, newlines and other invisible characters in string values are shown as
+    // dim glyphs, like `·`, `→` and `¶`, instead of as they'd be written in json.
+    pub reveal_whitespace: bool,
+    // Strings longer than this many characters are cut short with a `…`, since drawing huge ones
+    // makes scrolling past them slow. The values themselves are untouched.
+    pub max_value_chars: Option<usize>,
+    // Whether brackets and commas are left off, leaving indentation to show the structure. Their
+    // lines are still there, blank, so the cursor moves just the same.
+    pub minimal_punctuation: bool,
+    // Types whose values are shown as a placeholder, to see the shape of a document without its
+    // data.
+    pub abstracted: AbstractTypes,
+    // Whether each line starts with a bar colored by how deeply it's nested.
+    pub depth_ruler: bool,
+    pub depth_palette: DepthPalette,
+    // Whether booleans and null are shown with symbols, which are easier to pick out of a dense
+    // array than the words.
+    pub scalar_glyphs: ScalarGlyphs,
+    // Arrays and objects nested deeper than this are shown on one line with a marker, and can't be
+    // opened, so machine-generated or hostile documents can't make lines or traversals unbounded.
+    pub max_depth: usize,
+}
+
+// Deeper than any document written by hand, and than jq will parse.
+pub const DEFAULT_MAX_DEPTH: usize = 1000;
+
+// Symbols for `true`, `false` and `null`, and whether they're shown.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ScalarGlyphs {
+    pub mode: GlyphMode,
+    pub true_glyph: char,
+    pub false_glyph: char,
+    pub null_glyph: char,
+}
+
+impl Default for ScalarGlyphs {
+    fn default() -> Self {
+        ScalarGlyphs {
+            mode: GlyphMode::Off,
+            true_glyph: '✓',
+            false_glyph: '✗',
+            null_glyph: '∅',
+        }
+    }
+}
+
+impl ScalarGlyphs {
+    // From three characters, for true, false and null, like `✓✗∅`.
+    pub fn with_glyphs(self, glyphs: &str) -> Result<Self, String> {
+        match glyphs.chars().collect::<Vec<_>>()[..] {
+            [true_glyph, false_glyph, null_glyph] => Ok(ScalarGlyphs {
+                true_glyph,
+                false_glyph,
+                null_glyph,
+                ..self
+            }),
+            _ => Err(format!(
+                "glyphs should be three characters, for true, false and null, not {:?}",
+                glyphs
+            )),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum GlyphMode {
+    #[default]
+    Off,
+    // Before the word, like `✓ true`.
+    Beside,
+    Instead,
+}
+
+impl GlyphMode {
+    pub fn next(self) -> Self {
+        match self {
+            GlyphMode::Off => GlyphMode::Beside,
+            GlyphMode::Beside => GlyphMode::Instead,
+            GlyphMode::Instead => GlyphMode::Off,
+        }
+    }
+}
+
+impl FromStr for GlyphMode {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "off" => Ok(GlyphMode::Off),
+            "beside" => Ok(GlyphMode::Beside),
+            "instead" => Ok(GlyphMode::Instead),
+            _ => Err(format!(
+                "Expected off, beside or instead for scalar_glyphs, not {:?}",
+                s
+            )),
+        }
+    }
+}
+
+// Which query results are shown raw as soon as they're computed, as if `R` had been pressed, for
+// the common case of extracting a string.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum AutoRaw {
+    Off,
+    // When there's just one result, and it's a string.
+    #[default]
+    SingleString,
+    // When every result is a string.
+    AllStrings,
+}
+
+impl AutoRaw {
+    pub fn applies(self, results: &[JV]) -> bool {
+        let all_strings = !results.is_empty() && results.iter().all(|v| matches!(v, JV::String(_)));
+        match self {
+            AutoRaw::Off => false,
+            AutoRaw::SingleString => all_strings && results.len() == 1,
+            AutoRaw::AllStrings => all_strings,
+        }
+    }
+}
+
+impl FromStr for AutoRaw {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "off" => Ok(AutoRaw::Off),
+            "single" => Ok(AutoRaw::SingleString),
+            "all" => Ok(AutoRaw::AllStrings),
+            _ => Err(format!("Expected off, single or all, got {:?}", s)),
+        }
+    }
+}
+
+// Which types of scalar are shown as a placeholder instead of their value: `"…"` for strings, `#`
+// for numbers and `true/false` for booleans. Nulls are their own placeholder.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct AbstractTypes {
+    pub strings: bool,
+    pub numbers: bool,
+    pub booleans: bool,
+}
+
+impl AbstractTypes {
+    pub const ALL: Self = AbstractTypes {
+        strings: true,
+        numbers: true,
+        booleans: true,
+    };
+    // From the letter naming them: `s`, `n`, `b`, or `a` for all of them.
+    pub fn from_char(c: char) -> Option<Self> {
+        let none = AbstractTypes::default();
+        match c {
+            's' => Some(AbstractTypes {
+                strings: true,
+                ..none
+            }),
+            'n' => Some(AbstractTypes {
+                numbers: true,
+                ..none
+            }),
+            'b' => Some(AbstractTypes {
+                booleans: true,
+                ..none
+            }),
+            'a' => Some(AbstractTypes::ALL),
+            _ => None,
+        }
+    }
+    // Turns `types` off if they're all on already, and otherwise on.
+    pub fn toggled(self, types: Self) -> Self {
+        let all_on = (!types.strings || self.strings)
+            && (!types.numbers || self.numbers)
+            && (!types.booleans || self.booleans);
+        let set = |on: bool, toggled: bool| if toggled { !all_on } else { on };
+        AbstractTypes {
+            strings: set(self.strings, types.strings),
+            numbers: set(self.numbers, types.numbers),
+            booleans: set(self.booleans, types.booleans),
+        }
+    }
+    // Like "strings and numbers", or None if there aren't any.
+    pub fn describe(self) -> Option<String> {
+        let names: Vec<&str> = [
+            (self.strings, "strings"),
+            (self.numbers, "numbers"),
+            (self.booleans, "booleans"),
+        ]
+        .iter()
+        .filter(|(on, _)| *on)
+        .map(|(_, name)| *name)
+        .collect();
+        match names.as_slice() {
+            [] => None,
+            [name] => Some(name.to_string()),
+            [init @ .., last] => Some(format!("{} and {}", init.join(", "), last)),
+        }
+    }
+}
+
+pub const INDEX_GUTTER_WIDTH: u16 = 8;
+// The bar, and a space before the json.
+pub const DEPTH_RULER_WIDTH: u16 = 2;
+const MAX_DEPTH_COLORS: usize = 12;
+
+// The colors of the depth ruler, from the top level down, starting over past the last.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DepthPalette {
+    colors: [Color; MAX_DEPTH_COLORS],
+    len: usize,
+}
+
+// Colors are only ever compared as names and numbers.
+impl Eq for DepthPalette {}
+
+impl DepthPalette {
+    // Only the first MAX_DEPTH_COLORS are used. None if there are no colors.
+    pub fn new(colors: &[Color]) -> Option<Self> {
+        if colors.is_empty() {
+            return None;
+        }
+        let len = colors.len().min(MAX_DEPTH_COLORS);
+        let mut palette = [Color::Reset; MAX_DEPTH_COLORS];
+        palette[..len].copy_from_slice(&colors[..len]);
+        Some(DepthPalette {
+            colors: palette,
+            len,
+        })
+    }
+    pub fn color(&self, depth: usize) -> Color {
+        self.colors[depth % self.len]
+    }
+}
+
+impl Default for DepthPalette {
+    fn default() -> Self {
+        DepthPalette::new(&[
+            Color::Red,
+            Color::Yellow,
+            Color::Green,
+            Color::Cyan,
+            Color::Blue,
+            Color::Magenta,
+        ])
+        .expect("The default palette has colors")
+    }
+}
+
+// A color for the depth ruler: a name like `red` or `lightblue`, a 256 color index like `208`,
+// or `#rrggbb`.
+pub fn parse_color(s: &str) -> Result<Color, String> {
+    let color = match s.to_ascii_lowercase().replace(['_', '-', ' '], "").as_str() {
+        "black" => Color::Black,
+        "red" => Color::Red,
+        "green" => Color::Green,
+        "yellow" => Color::Yellow,
+        "blue" => Color::Blue,
+        "magenta" => Color::Magenta,
+        "cyan" => Color::Cyan,
+        "gray" | "grey" => Color::Gray,
+        "darkgray" | "darkgrey" => Color::DarkGray,
+        "lightred" => Color::LightRed,
+        "lightgreen" => Color::LightGreen,
+        "lightyellow" => Color::LightYellow,
+        "lightblue" => Color::LightBlue,
+        "lightmagenta" => Color::LightMagenta,
+        "lightcyan" => Color::LightCyan,
+        "white" => Color::White,
+        hex if hex.starts_with('#') && hex.len() == 7 => {
+            let channel = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16);
+            match (channel(1), channel(3), channel(5)) {
+                (Ok(r), Ok(g), Ok(b)) => Color::Rgb(r, g, b),
+                _ => return Err(format!("Invalid color {:?}", s)),
+            }
+        }
+        index => match index.parse::<u8>() {
+            Ok(index) => Color::Indexed(index),
+            Err(_) => return Err(format!("Invalid color {:?}", s)),
+        },
+    };
+    Ok(color)
+}
+
+impl RenderOptions {
+    // Whether an object member with this value is left out.
+    pub fn hides_member(&self, value: &JV) -> bool {
+        match value {
+            JV::Null(_) => self.hide_nulls,
+            JV::Array(arr) => self.hide_empty && arr.is_empty(),
+            JV::Object(obj) => self.hide_empty && obj.is_empty(),
+            _ => false,
+        }
+    }
+    // The start of `s` that's shown, if it's too long to show whole.
+    pub fn truncated<'a>(&self, s: &'a str) -> Option<&'a str> {
+        let max = self.max_value_chars?;
+        s.char_indices().nth(max).map(|(i, _)| &s[..i])
+    }
+    // The width left for json lines in a view `width` columns wide.
+    pub fn text_width(&self, width: u16) -> u16 {
+        let mut gutter_width = 0;
+        if self.index_gutter {
+            gutter_width += INDEX_GUTTER_WIDTH;
+        }
+        if self.depth_ruler {
+            gutter_width += DEPTH_RULER_WIDTH;
+        }
+        if gutter_width == 0 {
+            return width;
+        }
+        std::cmp::max(width.saturating_sub(gutter_width), 7)
+    }
+    pub fn wrapping(&self, leaf: &Leaf) -> Wrapping {
+        if !self.wrap_lines {
+            return Wrapping::Truncate;
+        }
+        // Raw strings read as text, so their rows line up; anything else is indented one more
+        // level so it doesn't look like a sibling.
+        let continuation_indent = match leaf.content {
+            LeafContent::RawString(_) => leaf.indent,
+            _ => leaf.indent + self.indent_width,
+        };
+        Wrapping::Wrap {
+            continuation_indent,
+        }
+    }
+}
+
+// How a line wider than the view is shown.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Wrapping {
+    // Continues on as many rows as needed, each indented by `continuation_indent`.
+    Wrap { continuation_indent: u16 },
+    // Cut off at the edge of the view with a `…`. Raw newlines still start new rows.
+    Truncate,
+}
+
+impl Default for RenderOptions {
+    fn default() -> Self {
+        RenderOptions {
+            raw_strings: false,
+            auto_raw: AutoRaw::default(),
+            indent_width: 2,
+            index_gutter: false,
+            wrap_lines: true,
+            format_numbers: false,
+            float_precision: None,
+            fold_preview_width: 24,
+            hide_nulls: false,
+            hide_empty: false,
+            reveal_whitespace: false,
+            max_value_chars: None,
+            minimal_punctuation: false,
+            abstracted: AbstractTypes::default(),
+            depth_ruler: false,
+            depth_palette: DepthPalette::default(),
+            scalar_glyphs: ScalarGlyphs::default(),
+            max_depth: DEFAULT_MAX_DEPTH,
+        }
+    }
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub struct Leaf {
     pub content: LeafContent,
@@ -20,13 +408,29 @@ pub enum LeafContent {
     Null,
     Bool(bool),
     Number(f64),
+    // The number, and how it's shown.
+    FormattedNumber(f64, String),
     String(JVString),
-    FoldedArray(usize),
+    RawString(JVString),
+    RevealedString(JVString),
+    // The start of a string too long to show whole.
+    TruncatedString(String),
+    // The number of children, and a preview of the first.
+    FoldedArray(usize, Option<String>),
     ArrayStart,
     ArrayEnd,
-    FoldedObject(usize),
+    FoldedObject(usize, Option<String>),
     ObjectStart,
     ObjectEnd,
+    // A value whose type is abstracted, shown as the type's placeholder.
+    Placeholder(&'static str),
+    // A boolean, or null if None, shown with a symbol.
+    Glyph(Option<bool>, ScalarGlyphs),
+    // An array or object past `max_depth`, shown as its brackets around a marker.
+    DepthLimited(&'static str),
+    // A bracket's line with `minimal_punctuation`: `[]` or `{}` for an empty array or object, and
+    // otherwise nothing.
+    Bracketless(&'static str),
 }
 
 use std::fmt::Debug;
@@ -57,6 +461,29 @@ impl Leaf {
                     out.push(LineFragment::new_unstyled(",", false));
                 }
             }
+            LeafContent::RevealedString(string) => {
+                out.push(LineFragment::new("\"", false, StyleType::Highlightable));
+                push_revealed(&mut out, string.value());
+                out.push(LineFragment::new("\"", false, StyleType::Highlightable));
+                if self.comma {
+                    out.push(LineFragment::new_unstyled(",", false));
+                }
+            }
+            LeafContent::TruncatedString(prefix) => {
+                out.push(LineFragment::new("\"", false, StyleType::Highlightable));
+                out.push(LineFragment::new(prefix, true, StyleType::Highlightable));
+                out.push(LineFragment::new("…", true, StyleType::Background));
+                out.push(LineFragment::new("\"", false, StyleType::Highlightable));
+                if self.comma {
+                    out.push(LineFragment::new_unstyled(",", false));
+                }
+            }
+            LeafContent::RawString(string) => {
+                out.push(LineFragment::new_raw(string, StyleType::Highlightable));
+                if self.comma {
+                    out.push(LineFragment::new_unstyled(",", false));
+                }
+            }
             LeafContent::Bool(b) => {
                 out.push(LineFragment::new(
                     b.to_string(),
@@ -68,25 +495,19 @@ impl Leaf {
                 }
             }
             LeafContent::Number(x) => {
-                out.push(LineFragment::new(
-                    x.to_string(),
-                    false,
-                    StyleType::Highlightable,
-                ));
+                out.push(LineFragment::new(x.to_string(), false, number_style(x)));
                 if self.comma {
                     out.push(LineFragment::new_unstyled(",", false));
                 }
             }
-            LeafContent::FoldedArray(children) => {
-                out.push(LineFragment::new("[...]", false, StyleType::Highlightable));
+            LeafContent::FormattedNumber(x, shown) => {
+                out.push(LineFragment::new(shown, false, number_style(x)));
                 if self.comma {
                     out.push(LineFragment::new_unstyled(",", false));
                 }
-                out.push(LineFragment::new(
-                    format!(" ({} children)", children),
-                    false,
-                    StyleType::Background,
-                ));
+            }
+            LeafContent::FoldedArray(children, preview) => {
+                push_folded(&mut out, ("[", "]"), children, preview, self.comma);
             }
             LeafContent::ArrayStart => {
                 out.push(LineFragment::new("[", false, StyleType::Highlightable));
@@ -97,31 +518,219 @@ impl Leaf {
                     out.push(LineFragment::new_unstyled(",", false));
                 }
             }
-            LeafContent::FoldedObject(children) => {
-                out.push(LineFragment::new("{...}", false, StyleType::Highlightable));
+            LeafContent::FoldedObject(children, preview) => {
+                push_folded(&mut out, ("{", "}"), children, preview, self.comma);
+            }
+            LeafContent::ObjectStart => {
+                out.push(LineFragment::new("{", false, StyleType::Highlightable));
+            }
+            LeafContent::ObjectEnd => {
+                out.push(LineFragment::new("}", false, StyleType::Highlightable));
                 if self.comma {
                     out.push(LineFragment::new_unstyled(",", false));
                 }
+            }
+            LeafContent::Glyph(value, glyphs) => {
+                let (glyph, word) = match value {
+                    Some(true) => (glyphs.true_glyph, "true"),
+                    Some(false) => (glyphs.false_glyph, "false"),
+                    None => (glyphs.null_glyph, "null"),
+                };
                 out.push(LineFragment::new(
-                    format!(" ({} children)", children),
+                    glyph.to_string(),
                     false,
-                    StyleType::Background,
+                    StyleType::Glyph(value),
                 ));
+                if glyphs.mode == GlyphMode::Beside {
+                    out.push(LineFragment::new_unstyled(" ", false));
+                    out.push(LineFragment::new(word, false, StyleType::Highlightable));
+                }
+                if self.comma {
+                    out.push(LineFragment::new_unstyled(",", false));
+                }
             }
-            LeafContent::ObjectStart => {
-                out.push(LineFragment::new("{", false, StyleType::Highlightable));
-            }
-            LeafContent::ObjectEnd => {
-                out.push(LineFragment::new("}", false, StyleType::Highlightable));
+            LeafContent::Placeholder(placeholder) | LeafContent::DepthLimited(placeholder) => {
+                out.push(LineFragment::new(
+                    placeholder,
+                    false,
+                    StyleType::Highlightable,
+                ));
                 if self.comma {
                     out.push(LineFragment::new_unstyled(",", false));
                 }
             }
+            LeafContent::Bracketless(brackets) => {
+                // Something has to be highlighted to show the cursor.
+                let shown = if brackets.is_empty() { " " } else { brackets };
+                out.push(LineFragment::new(shown, false, StyleType::Highlightable));
+            }
         };
         LineFragments::new(out)
     }
 }
 
+// Numbers are kept as doubles, so integers past the safe range may not have their original digits.
+fn number_style(x: f64) -> StyleType {
+    if inspect::beyond_safe_integer(x) {
+        StyleType::Imprecise
+    } else {
+        StyleType::Highlightable
+    }
+}
+
+// Groups the integer digits of `x` in threes, like `1,234,567.5`, after rounding it to
+// `float_precision` decimal places if it has any.
+pub fn format_number(x: f64, float_precision: Option<usize>) -> String {
+    let s = match float_precision {
+        Some(precision) if x.fract() != 0.0 => {
+            let rounded = format!("{:.*}", precision, x);
+            if rounded.contains('.') {
+                rounded
+                    .trim_end_matches('0')
+                    .trim_end_matches('.')
+                    .to_owned()
+            } else {
+                rounded
+            }
+        }
+        _ => x.to_string(),
+    };
+    let (sign, unsigned) = match s.strip_prefix('-') {
+        Some(unsigned) => ("-", unsigned),
+        None => ("", s.as_str()),
+    };
+    let (integer, fraction) = unsigned.split_at(unsigned.find('.').unwrap_or(unsigned.len()));
+    let mut out = sign.to_owned();
+    for (i, c) in integer.chars().enumerate() {
+        if i > 0 && (integer.len() - i) % 3 == 0 {
+            out.push(',');
+        }
+        out.push(c);
+    }
+    out.push_str(fraction);
+    out
+}
+
+// Renders a folded value like `[ "alice", … ]`, or `[...]` without a preview.
+fn push_folded(
+    out: &mut Vec<LineFragment>,
+    (open, close): (&'static str, &'static str),
+    children: usize,
+    preview: Option<String>,
+    comma: bool,
+) {
+    match preview {
+        Some(preview) => {
+            out.push(LineFragment::new(open, false, StyleType::Highlightable));
+            out.push(LineFragment::new(" ", false, StyleType::Highlightable));
+            out.push(LineFragment::new_raw(preview, StyleType::Highlightable));
+            let rest = if children > 1 { ", … " } else { " " };
+            out.push(LineFragment::new(rest, false, StyleType::Highlightable));
+            out.push(LineFragment::new(close, false, StyleType::Highlightable));
+        }
+        None => {
+            let folded = if open == "[" { "[...]" } else { "{...}" };
+            out.push(LineFragment::new(folded, false, StyleType::Highlightable));
+        }
+    }
+    if comma {
+        out.push(LineFragment::new_unstyled(",", false));
+    }
+    out.push(LineFragment::new(
+        format!(" ({} children)", children),
+        false,
+        StyleType::Background,
+    ));
+}
+
+// A one line summary of a folded value's first child, like `"id": 1`, cut off after
+// `max_width` characters. Nested arrays and objects are shown as `[…]` and `{…}`.
+pub fn fold_preview(key: Option<&str>, first: &JV, max_width: usize) -> String {
+    // Only serialize as much of a string as could be shown.
+    let quoted = |s: &str| {
+        let prefix: String = s.chars().take(max_width).collect();
+        serde_json::to_string(&prefix).expect("Strings always serialize")
+    };
+    let value = match first {
+        JV::Array(arr) if arr.is_empty() => "[]".to_owned(),
+        JV::Array(_) => "[…]".to_owned(),
+        JV::Object(obj) if obj.is_empty() => "{}".to_owned(),
+        JV::Object(_) => "{…}".to_owned(),
+        JV::String(s) => quoted(s.value()),
+        JV::Number(x) => x.value().to_string(),
+        JV::Bool(b) => b.value().to_string(),
+        JV::Null(_) => "null".to_owned(),
+    };
+    let preview = match key {
+        Some(key) => format!("{}: {}", quoted(key), value),
+        None => value,
+    };
+    if preview.chars().count() <= max_width {
+        preview
+    } else {
+        let mut truncated: String = preview.chars().take(max_width.saturating_sub(1)).collect();
+        truncated.push('…');
+        truncated
+    }
+}
+
+// Splits `s` into runs of characters shown as usual and runs of revealed ones, so the revealed
+// ones can be styled differently.
+fn push_revealed(out: &mut Vec<LineFragment>, s: &str) {
+    let mut run_start = 0;
+    let mut run_revealed = false;
+    for (i, c) in s.char_indices() {
+        if is_revealed(c) != run_revealed {
+            push_revealed_run(out, &s[run_start..i], run_revealed);
+            run_start = i;
+            run_revealed = !run_revealed;
+        }
+    }
+    push_revealed_run(out, &s[run_start..], run_revealed);
+}
+
+fn push_revealed_run(out: &mut Vec<LineFragment>, run: &str, revealed: bool) {
+    if revealed {
+        out.push(LineFragment {
+            string: run.to_owned().into(),
+            escaping: Escaping::Reveal,
+            style: StyleType::Revealed,
+        });
+    } else {
+        out.push(LineFragment::new(
+            run.to_owned(),
+            true,
+            StyleType::Highlightable,
+        ));
+    }
+}
+
+fn is_revealed(c: char) -> bool {
+    c == ' ' || (is_escaped(c) && !matches!(c, '\"' | '\\'))
+}
+
+// Characters without a glyph of their own are written as their escapes.
+pub fn revealed_str(s: &str) -> String {
+    let mut out = String::new();
+    for c in s.chars() {
+        match c {
+            ' ' => out.push('·'),
+            '\t' => out.push('→'),
+            '\n' => out.push('¶'),
+            '\r' => out.push('␍'),
+            _ => write_escaped_char(c, &mut out),
+        }
+    }
+    out
+}
+
+fn revealed_display_width(c: char) -> u8 {
+    match c {
+        ' ' | '\t' | '\n' | '\r' => 1,
+        _ => display_width(c),
+    }
+}
+
 fn is_unicode_escaped(c: char) -> bool {
     match get_general_category(c) {
         GeneralCategory::Control
@@ -180,6 +789,34 @@ fn is_escaped(c: char) -> bool {
     }
 }
 
+// Raw strings keep quotes and backslashes as is, and break lines on newlines instead of escaping
+// them, but other control characters would corrupt the terminal so they're still escaped.
+fn is_raw_escaped(c: char) -> bool {
+    !matches!(c, '\"' | '\\' | '\n') && is_escaped(c)
+}
+
+pub fn raw_str(s: &str) -> String {
+    let mut out = String::new();
+    for c in s.chars() {
+        if c == '\n' {
+            continue;
+        } else if is_raw_escaped(c) {
+            write_escaped_char(c, &mut out);
+        } else {
+            out.push(c);
+        }
+    }
+    out
+}
+
+fn raw_display_width(c: char) -> u8 {
+    match c {
+        '\n' => 0,
+        '\"' | '\\' => 1,
+        _ => display_width(c),
+    }
+}
+
 fn display_width(c: char) -> u8 {
     match c {
         '\"' | '\\' | '\u{08}' | '\u{0C}' | '\n' | '\r' | '\t' => 2,
@@ -211,6 +848,12 @@ impl UnstyledSpans {
             .collect();
         v.into()
     }
+    pub fn to_plain(&self) -> String {
+        self.content
+            .iter()
+            .map(|unstyled| unstyled.text.as_str())
+            .collect()
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -224,6 +867,12 @@ pub enum StyleType {
     Unhighlightable,
     Highlightable,
     Background,
+    // Invisible characters made visible, in string values.
+    Revealed,
+    // Numbers that may have lost digits when they were loaded.
+    Imprecise,
+    // The symbol for a boolean, or null if None.
+    Glyph(Option<bool>),
 }
 
 impl StyleType {
@@ -232,6 +881,24 @@ impl StyleType {
             StyleType::Highlightable if is_cursor => Style::default().bg(Color::Blue),
             StyleType::Unhighlightable | StyleType::Highlightable => Style::default(),
             StyleType::Background => Style::default().add_modifier(Modifier::DIM),
+            StyleType::Revealed if is_cursor => {
+                Style::default().fg(Color::DarkGray).bg(Color::Blue)
+            }
+            StyleType::Revealed => Style::default().fg(Color::DarkGray),
+            StyleType::Imprecise if is_cursor => Style::default().fg(Color::Yellow).bg(Color::Blue),
+            StyleType::Imprecise => Style::default().fg(Color::Yellow),
+            StyleType::Glyph(value) => {
+                let style = Style::default().fg(match value {
+                    Some(true) => Color::Green,
+                    Some(false) => Color::Red,
+                    None => Color::DarkGray,
+                });
+                if is_cursor {
+                    style.bg(Color::Blue)
+                } else {
+                    style
+                }
+            }
         }
     }
 }
@@ -284,10 +951,19 @@ impl From<JVString> for StringLike {
     }
 }
 
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Escaping {
+    None,
+    Json,
+    Raw,
+    // With reveal_whitespace.
+    Reveal,
+}
+
 #[derive(Clone, Debug)]
 pub struct LineFragment {
     string: StringLike,
-    is_escaped: bool,
+    escaping: Escaping,
     style: StyleType,
 }
 
@@ -295,38 +971,58 @@ impl LineFragment {
     fn new<S: Into<StringLike>>(s: S, is_escaped: bool, style: StyleType) -> Self {
         LineFragment {
             string: s.into(),
-            is_escaped,
+            escaping: if is_escaped {
+                Escaping::Json
+            } else {
+                Escaping::None
+            },
             style,
         }
     }
     fn new_unstyled<S: Into<StringLike>>(s: S, is_escaped: bool) -> Self {
+        LineFragment::new(s, is_escaped, StyleType::Unhighlightable)
+    }
+    fn new_raw<S: Into<StringLike>>(s: S, style: StyleType) -> Self {
         LineFragment {
             string: s.into(),
-            is_escaped,
-            style: StyleType::Unhighlightable,
+            escaping: Escaping::Raw,
+            style,
         }
     }
     fn take_width(&self, from: usize, target_width: u16) -> (Range<usize>, u16) {
-        if self.is_escaped {
-            let mut width = 0u16;
-            for (i, c) in self.string.as_str()[from..].char_indices() {
-                let new_width = width + display_width(c) as u16;
-                if new_width > target_width {
-                    return (from..from + i, width);
-                }
-                width = new_width;
+        let char_width = match self.escaping {
+            Escaping::None => {
+                let width = std::cmp::min(self.string.len() - from, target_width as usize);
+                return (from..from + width, width as u16);
+            }
+            Escaping::Json => display_width,
+            Escaping::Raw => raw_display_width,
+            Escaping::Reveal => revealed_display_width,
+        };
+        let mut width = 0u16;
+        for (i, c) in self.string.as_str()[from..].char_indices() {
+            let new_width = width + char_width(c) as u16;
+            if new_width > target_width {
+                return (from..from + i, width);
+            }
+            width = new_width;
+            if c == '\n' && self.escaping == Escaping::Raw {
+                // The newline belongs to this line, and nothing else does.
+                return (from..from + i + 1, width);
             }
-            (from..self.string.len(), width)
-        } else {
-            let width = std::cmp::min(self.string.len() - from, target_width as usize);
-            (from..from + width, width as u16)
         }
+        (from..self.string.len(), width)
+    }
+    // Whether a line that stops at `byte_index` in this fragment must end there.
+    fn breaks_line_at(&self, byte_index: usize) -> bool {
+        self.escaping == Escaping::Raw && self.string.as_str()[..byte_index].ends_with('\n')
     }
     fn span(&self, range: Range<usize>) -> UnstyledSpan {
-        let text = if self.is_escaped {
-            escaped_str(&self.string.as_str()[range])
-        } else {
-            self.string.as_str()[range].to_string()
+        let text = match self.escaping {
+            Escaping::None => self.string.as_str()[range].to_string(),
+            Escaping::Json => escaped_str(&self.string.as_str()[range]),
+            Escaping::Raw => raw_str(&self.string.as_str()[range]),
+            Escaping::Reveal => revealed_str(&self.string.as_str()[range]),
         };
         UnstyledSpan {
             text,
@@ -367,6 +1063,10 @@ impl LineFragments {
                 // Didn't consume the whole fragment
                 break;
             }
+            if fragment.breaks_line_at(fragment_range.end) {
+                // Hit a raw newline
+                break;
+            }
             if width == target_width {
                 // Out of width
                 break;
@@ -440,6 +1140,17 @@ impl LineFragments {
         ix.byte_index -= delta;
         ix
     }
+    // The next place a line has to end at or after `from`: a raw newline, or the end.
+    fn skip_to_line_break(&self, from: LineFragmentsIndex) -> LineFragmentsIndex {
+        let mut current = from;
+        while current != self.end_index()
+            && !self.0[current.fragment_index].breaks_line_at(current.byte_index)
+        {
+            let (range, _) = self.take_width(current, u16::MAX);
+            current = range.end;
+        }
+        current
+    }
     fn end_index(&self) -> LineFragmentsIndex {
         LineFragmentsIndex {
             fragment_index: self.0.len() - 1,
@@ -451,7 +1162,8 @@ impl LineFragments {
 #[derive(Debug, Clone)]
 pub struct LineCursor {
     width: u16,
-    line_widths: Rc<RefCell<Vec<u16>>>, //bytes
+    wrapping: Wrapping,
+    line_widths: Rc<RefCell<Vec<usize>>>, //bytes
     position: LineCursorPosition,
     content: LineFragments,
 }
@@ -470,8 +1182,28 @@ impl LineCursor {
                 let line_widths = self.line_widths.borrow();
                 let end = self
                     .content
-                    .add_byte_offset(start, line_widths[current_line] as usize);
-                let content = self.content.spans(start..end);
+                    .add_byte_offset(start, line_widths[current_line]);
+                let mut content = Vec::new();
+                let indent = self.indent(current_line);
+                if indent > 0 {
+                    content.push(UnstyledSpan {
+                        text: " ".repeat(indent as usize),
+                        style_type: StyleType::Unhighlightable,
+                    });
+                }
+                let (shown, _) = self.content.take_width(start, self.width - indent);
+                let end_offset = self.content.to_global_byte_offset(end);
+                if self.content.to_global_byte_offset(shown.end) == end_offset {
+                    content.extend(self.content.spans(start..end));
+                } else {
+                    // Only happens when truncating. Leave room for the ellipsis.
+                    let (shown, _) = self.content.take_width(start, self.width - 1);
+                    content.extend(self.content.spans(shown));
+                    content.push(UnstyledSpan {
+                        text: "…".to_owned(),
+                        style_type: StyleType::Background,
+                    });
+                }
                 Some(UnstyledSpans { content })
             }
         }
@@ -486,40 +1218,52 @@ impl LineCursor {
                 let line_widths = self.line_widths.borrow();
                 let end = self
                     .content
-                    .add_byte_offset(start, line_widths[current_line] as usize);
+                    .add_byte_offset(start, line_widths[current_line]);
                 Some(self.content.end_index() == end)
             }
         }
     }
+    // Columns of indentation before the content of row `line`.
+    fn indent(&self, line: usize) -> u16 {
+        match self.wrapping {
+            // Always leave room for some content.
+            Wrapping::Wrap {
+                continuation_indent,
+            } if line > 0 => std::cmp::min(continuation_indent, self.width.saturating_sub(7)),
+            Wrapping::Wrap { .. } | Wrapping::Truncate => 0,
+        }
+    }
+    // Where row `line`, starting at `start`, ends.
+    fn line_end(&self, start: LineFragmentsIndex, line: usize) -> LineFragmentsIndex {
+        let (range, _) = self
+            .content
+            .take_width(start, self.width - self.indent(line));
+        match self.wrapping {
+            Wrapping::Wrap { .. } => range.end,
+            Wrapping::Truncate => self.content.skip_to_line_break(range.end),
+        }
+    }
     fn push_next_line_width(&mut self) {
         let mut line_widths = self.line_widths.borrow_mut();
-        match self.position {
-            LineCursorPosition::Start => {
-                if line_widths.is_empty() {
-                    let (range, _) = self.content.take_width(
-                        LineFragmentsIndex {
-                            fragment_index: 0,
-                            byte_index: 0,
-                        },
-                        self.width,
-                    );
-                    line_widths.push(self.content.to_global_byte_offset(range.end) as u16);
-                }
-            }
-            LineCursorPosition::End => {}
+        let (start, current_line) = match self.position {
+            LineCursorPosition::Start => (
+                LineFragmentsIndex {
+                    fragment_index: 0,
+                    byte_index: 0,
+                },
+                0,
+            ),
+            LineCursorPosition::End => return,
             LineCursorPosition::Valid {
                 current_line,
                 start,
-            } => {
-                if current_line == line_widths.len() {
-                    let (range, _) = self.content.take_width(start, self.width);
-                    line_widths.push(
-                        (self.content.to_global_byte_offset(range.end)
-                            - self.content.to_global_byte_offset(start))
-                            as u16,
-                    );
-                }
-            }
+            } => (start, current_line),
+        };
+        if current_line == line_widths.len() {
+            let end = self.line_end(start, current_line);
+            line_widths.push(
+                self.content.to_global_byte_offset(end) - self.content.to_global_byte_offset(start),
+            );
         }
     }
     pub fn move_next(&mut self) {
@@ -540,7 +1284,7 @@ impl LineCursor {
             } => {
                 *start = self
                     .content
-                    .add_byte_offset(*start, self.line_widths.borrow()[*current_line] as usize);
+                    .add_byte_offset(*start, self.line_widths.borrow()[*current_line]);
                 *current_line += 1;
                 if *start == self.content.end_index() {
                     self.position = LineCursorPosition::End;
@@ -557,7 +1301,7 @@ impl LineCursor {
                 let current_line = line_widths.len() - 1;
                 let start = self
                     .content
-                    .sub_byte_offset(self.content.end_index(), line_widths[current_line] as usize);
+                    .sub_byte_offset(self.content.end_index(), line_widths[current_line]);
                 self.position = LineCursorPosition::Valid {
                     current_line,
                     start,
@@ -573,7 +1317,7 @@ impl LineCursor {
                 *current_line -= 1;
                 *start = self
                     .content
-                    .sub_byte_offset(*start, line_widths[*current_line] as usize);
+                    .sub_byte_offset(*start, line_widths[*current_line]);
             }
         }
     }
@@ -584,20 +1328,39 @@ impl LineCursor {
         }
     }
     pub fn new_at_start(content: LineFragments, width: u16) -> Self {
+        Self::new_at_start_with(
+            content,
+            width,
+            Wrapping::Wrap {
+                continuation_indent: 0,
+            },
+        )
+    }
+    pub fn new_at_end(content: LineFragments, width: u16) -> Self {
+        Self::new_at_end_with(
+            content,
+            width,
+            Wrapping::Wrap {
+                continuation_indent: 0,
+            },
+        )
+    }
+    pub fn new_at_start_with(content: LineFragments, width: u16, wrapping: Wrapping) -> Self {
         assert!(width > 6);
         let mut out = LineCursor {
             line_widths: Rc::new(RefCell::new(Vec::new())),
             position: LineCursorPosition::Start,
             content,
             width,
+            wrapping,
         };
         out.move_next();
         out
     }
-    pub fn new_at_end(content: LineFragments, width: u16) -> Self {
+    pub fn new_at_end_with(content: LineFragments, width: u16, wrapping: Wrapping) -> Self {
         assert!(width > 6);
         // We start from the start and scan forward to populate line_widths
-        let mut out = Self::new_at_start(content, width);
+        let mut out = Self::new_at_start_with(content, width, wrapping);
         while out.position != LineCursorPosition::End {
             out.move_next();
         }
@@ -610,15 +1373,15 @@ impl LineCursor {
         }
         match self.position {
             LineCursorPosition::Start => {
-                *self = LineCursor::new_at_start(self.content.clone(), width);
+                *self = LineCursor::new_at_start_with(self.content.clone(), width, self.wrapping);
                 self.move_prev();
             }
             LineCursorPosition::End => {
-                *self = LineCursor::new_at_end(self.content.clone(), width);
+                *self = LineCursor::new_at_end_with(self.content.clone(), width, self.wrapping);
                 self.move_next();
             }
             LineCursorPosition::Valid { start: target, .. } => {
-                *self = LineCursor::new_at_start(self.content.clone(), width);
+                *self = LineCursor::new_at_start_with(self.content.clone(), width, self.wrapping);
                 loop {
                     match self.position {
                         LineCursorPosition::Start => {
@@ -641,8 +1404,14 @@ impl LineCursor {
 
 #[cfg(test)]
 mod tests {
-    use super::{display_width, escaped_str, LineCursor, LineFragment, LineFragments};
+    use super::{
+        display_width, escaped_str, fold_preview, format_number, is_revealed,
+        revealed_display_width, revealed_str, GlyphMode, Leaf, LeafContent, LineCursor,
+        LineFragment, LineFragments, ScalarGlyphs, StyleType, Wrapping,
+    };
+    use crate::jq::jv::JV;
     use proptest::prelude::*;
+    use serde_json::json;
     use unicode_width::UnicodeWidthStr;
     proptest! {
         #[test]
@@ -710,6 +1479,25 @@ mod tests {
         }
     }
     #[test]
+    fn unit_format_number() {
+        assert_eq!(format_number(1234567.0, None), "1,234,567");
+        assert_eq!(format_number(-123.0, None), "-123");
+        assert_eq!(format_number(-1234.5678, Some(2)), "-1,234.57");
+        assert_eq!(format_number(0.1, Some(3)), "0.1");
+        assert_eq!(format_number(999.9999, Some(2)), "1,000");
+        assert_eq!(format_number(1000.25, None), "1,000.25");
+    }
+    #[test]
+    fn unit_fold_preview() {
+        let jv: JV = (&json!({"a": 1})).into();
+        assert_eq!(fold_preview(None, &jv, 10), "{…}");
+        let jv: JV = (&json!("alice")).into();
+        assert_eq!(fold_preview(None, &jv, 10), "\"alice\"");
+        assert_eq!(fold_preview(Some("name"), &jv, 10), "\"name\": \"…");
+        let jv: JV = (&json!(1.5)).into();
+        assert_eq!(fold_preview(Some("id"), &jv, 10), "\"id\": 1.5");
+    }
+    #[test]
     fn unit_to_string() {
         let tests = vec![
             ("Hello world!", r#"Hello world!"#),
@@ -723,6 +1511,111 @@ mod tests {
             assert_eq!(actual, expected, "Test failure for {:?}", string);
         }
     }
+    #[test]
+    fn unit_raw_lines() {
+        let line_fragments = LineFragments::new(vec![
+            LineFragment::new_unstyled("  ", false),
+            LineFragment::new_raw("say \"hi\"\n\tthere\n", StyleType::Highlightable),
+            LineFragment::new_unstyled(",", false),
+        ]);
+        let mut cursor = LineCursor::new_at_start(line_fragments, 10);
+        let mut lines = Vec::new();
+        while let Some(line) = cursor.current() {
+            lines.push(
+                line.content
+                    .iter()
+                    .map(|span| span.text.as_str())
+                    .collect::<String>(),
+            );
+            cursor.move_next();
+        }
+        assert_eq!(lines, vec!["  say \"hi\"", "\\tthere", ","]);
+    }
+    #[test]
+    fn unit_revealed_string() {
+        let string = "a b\t\"c\"\n\u{200b} ";
+        let json: JV = (&json!(string)).into();
+        let string = match json {
+            JV::String(s) => s,
+            _ => unreachable!(),
+        };
+        let leaf = Leaf {
+            content: LeafContent::RevealedString(string),
+            key: None,
+            indent: 0,
+            comma: true,
+        };
+        let cursor = LineCursor::new_at_start(leaf.render(), 100);
+        let line = cursor.current().unwrap();
+        assert_eq!(line.to_plain(), "\"a·b→\\\"c\\\"¶\\u200b·\",");
+        let revealed: Vec<&str> = line
+            .content
+            .iter()
+            .filter(|span| span.style_type == StyleType::Revealed)
+            .map(|span| span.text.as_str())
+            .collect();
+        assert_eq!(revealed, vec!["·", "→", "¶\\u200b·"]);
+    }
+    #[test]
+    fn unit_scalar_glyphs() {
+        let render = |value, mode| {
+            let glyphs = ScalarGlyphs {
+                mode,
+                ..ScalarGlyphs::default()
+            };
+            let leaf = Leaf {
+                content: LeafContent::Glyph(value, glyphs),
+                key: None,
+                indent: 0,
+                comma: true,
+            };
+            let cursor = LineCursor::new_at_start(leaf.render(), 100);
+            cursor.current().unwrap().to_plain()
+        };
+        assert_eq!(render(Some(true), GlyphMode::Beside), "✓ true,");
+        assert_eq!(render(Some(false), GlyphMode::Instead), "✗,");
+        assert_eq!(render(None, GlyphMode::Instead), "∅,");
+    }
+    proptest! {
+        #[test]
+        fn prop_revealed_width(string in any::<String>()) {
+            // Only runs of revealed characters are ever passed to revealed_str.
+            let string: String = string.chars().filter(|&c| is_revealed(c)).collect();
+            let revealed = revealed_str(&string);
+            let width: usize = string.chars().map(|c| revealed_display_width(c) as usize).sum();
+            assert_eq!(revealed.width(), width, "original: {:?}, revealed: {}", &string, &revealed);
+        }
+    }
+    fn read_rows(mut cursor: LineCursor) -> Vec<String> {
+        let mut rows = Vec::new();
+        while let Some(line) = cursor.current() {
+            rows.push(line.content.iter().map(|span| span.text.as_str()).collect());
+            cursor.move_next();
+        }
+        rows
+    }
+    #[test]
+    fn unit_wrapping() {
+        let line_fragments = LineFragments::new(vec![
+            LineFragment::new_unstyled("  ", false),
+            LineFragment::new_raw("abcdefghijkl\nxy\n", StyleType::Highlightable),
+            LineFragment::new_unstyled(",", false),
+        ]);
+        let cursor = LineCursor::new_at_start_with(line_fragments, 8, Wrapping::Truncate);
+        assert_eq!(read_rows(cursor), vec!["  abcde…", "xy", ","]);
+        let line_fragments =
+            LineFragments::new(vec![LineFragment::new_unstyled("abcdefghijklmnop", false)]);
+        let wrapping = Wrapping::Wrap {
+            continuation_indent: 4,
+        };
+        // Rows are always left at least 7 columns.
+        let cursor = LineCursor::new_at_start_with(line_fragments.clone(), 8, wrapping);
+        assert_eq!(read_rows(cursor), vec!["abcdefgh", " ijklmno", " p"]);
+        let cursor = LineCursor::new_at_start_with(line_fragments.clone(), 11, wrapping);
+        assert_eq!(read_rows(cursor), vec!["abcdefghijk", "    lmnop"]);
+        let cursor = LineCursor::new_at_end_with(line_fragments, 11, wrapping);
+        assert_eq!(read_rows(cursor), vec!["    lmnop"]);
+    }
     fn strings_to_fragments(strings: Vec<String>) -> LineFragments {
         let content = strings
             .into_iter()
@@ -1,43 +1,61 @@
 use argh::FromArgs;
 use crossterm::{
     event,
-    event::KeyCode,
+    event::{KeyCode, KeyEvent, KeyModifiers},
     execute,
     terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
 };
 use directories;
 use jex::{
-    app::{App, AppRenderMode, Focus},
-    cursor::GlobalCursor,
+    app::{
+        self, App, AppRenderMode, Focus, DEFAULT_HISTORY_LIMIT, READ_ONLY_MESSAGE,
+        ROOT_HAS_NO_QUERY,
+    },
+    config::Config,
+    embed,
+    events::{AppEvent, EventLoop, ProgressRead},
+    flatten,
+    formats::{self, InputFormat},
     helper::Helper,
+    jq::{
+        jv::{JVString, JV},
+        query::{JQOptions, JQ},
+        JQ_VERSION,
+    },
     layout::JexLayout,
-    view_tree::View,
+    lines::{AbstractTypes, AutoRaw, RenderOptions, DEFAULT_MAX_DEPTH},
+    palette::{self, Palette, PaletteAction},
+    query_library::QueryLibrary,
+    schema,
+    session::{self, FileStates, Session},
+    view_tree::{View, ViewForestIndex},
 };
 use log::{debug, warn};
-use regex::Regex;
 use simplelog::WriteLogger;
 use std::{
+    cell::{Cell, RefCell},
     default::Default,
     error::Error,
-    fs,
+    fmt, fs,
     fs::{create_dir_all, File},
     io,
     io::Write,
     panic,
     path::PathBuf,
 };
-use tui::{
-    backend::CrosstermBackend,
-    layout::Rect,
-    widgets::{Block, Borders},
-    Frame, Terminal,
-};
+use tui::{backend::CrosstermBackend, layout::Rect, Frame, Terminal};
 use unicode_width::UnicodeWidthStr;
 
 #[cfg(feature = "dev-tools")]
 use cpuprofiler::PROFILER;
 #[cfg(feature = "dev-tools")]
+use jex::app::{millis, time_query};
+#[cfg(feature = "dev-tools")]
 use prettytable::{cell, ptable, row, table, Table};
+#[cfg(feature = "dev-tools")]
+use std::time::Instant;
+#[cfg(feature = "dev-tools")]
+use tui::backend::TestBackend;
 
 #[derive(FromArgs, PartialEq, Debug)]
 /// Json viewer and editor
@@ -52,8 +70,96 @@ struct Args {
     #[argh(option)]
     #[argh(description = "logging output file")]
     log_path: Option<String>,
+    #[argh(switch)]
+    #[argh(description = "don't save the session on exit")]
+    no_session: bool,
+    #[argh(switch)]
+    #[argh(description = "turn off everything that runs other programs, like --exec and piping")]
+    safe: bool,
+    #[argh(switch)]
+    #[argh(description = "turn off edits and saving over files, until P is pressed")]
+    read_only: bool,
+    #[argh(switch)]
+    #[argh(description = "save edits over the json file they came from, once editing pauses")]
+    autosave: bool,
+    #[argh(switch)]
+    #[argh(description = "start with the tree panel open")]
+    tree: bool,
+    #[argh(option)]
+    #[argh(description = "start with the cursor at this path, like .data.items[0]")]
+    at: Option<String>,
+    #[argh(option, short = 'L')]
+    #[argh(description = "directory to search for jq modules (repeatable)")]
+    library_path: Vec<String>,
+    #[argh(option)]
+    #[argh(description = "bind $name to a string for queries, given as name=value (repeatable)")]
+    arg: Vec<String>,
+    #[argh(option)]
+    #[argh(
+        description = "bind $name to a json value for queries, given as name=json (repeatable)"
+    )]
+    argjson: Vec<String>,
+    #[argh(option)]
+    #[argh(description = "columns of indentation per level of nesting (default 2)")]
+    #[argh(default = "2")]
+    indent_width: u16,
+    #[argh(option)]
+    #[argh(
+        description = "how deeply arrays and objects nest before they're shown on one line with a marker (default 1000)"
+    )]
+    #[argh(default = "DEFAULT_MAX_DEPTH")]
+    max_depth: usize,
+    #[argh(option)]
+    #[argh(description = "how many edits to remember for undo, per view (default 100)")]
+    #[argh(default = "DEFAULT_HISTORY_LIMIT")]
+    history_limit: usize,
+    #[argh(switch)]
+    #[argh(description = "show numbers with thousands separators")]
+    format_numbers: bool,
+    #[argh(option)]
+    #[argh(
+        description = "which query results to show raw, like jq -r: single (one string, the default), all (all strings) or off"
+    )]
+    #[argh(default = "AutoRaw::SingleString")]
+    auto_raw: AutoRaw,
+    #[argh(option)]
+    #[argh(description = "decimal places to round numbers to when they're formatted")]
+    float_precision: Option<usize>,
+    #[argh(option)]
+    #[argh(
+        description = "characters of the first child to show on folded lines, or 0 for none (default 24)"
+    )]
+    #[argh(default = "24")]
+    fold_preview: usize,
+    #[argh(switch)]
+    #[argh(description = "replace invalid UTF-8 in files with U+FFFD instead of failing to load")]
+    lossy_utf8: bool,
+    #[argh(switch)]
+    #[argh(description = "load the file through a memory map, using less memory (experimental)")]
+    mmap: bool,
+    #[argh(switch)]
+    #[argh(description = "print the file as jex would show it, without opening the viewer")]
+    print: bool,
+    #[argh(switch)]
+    #[argh(description = "on quitting, print the focused view so it stays in the scrollback")]
+    print_on_exit: bool,
+    #[argh(option)]
+    #[argh(description = "query to run on the file in the right pane when it opens")]
+    query: Option<String>,
+    #[argh(option)]
+    #[argh(description = "json schema file to validate the input against")]
+    schema: Option<String>,
+    #[argh(option)]
+    #[argh(description = "most results to compute for a query at first, fetching more on request")]
+    max_results: Option<usize>,
+    #[argh(option)]
+    #[argh(description = "shell command to open the output of, instead of a file")]
+    exec: Option<String>,
+    #[argh(switch)]
+    #[argh(description = "print the versions of jex and jq and the enabled features, and exit")]
+    version: bool,
     #[argh(positional)]
-    json_path: String,
+    json_path: Option<String>,
 }
 
 #[derive(FromArgs, PartialEq, Debug)]
@@ -71,7 +177,11 @@ struct NormalMode {}
 #[derive(FromArgs, PartialEq, Debug)]
 #[argh(subcommand, name = "bench")]
 /// Benchmark loading a json file
-struct BenchMode {}
+struct BenchMode {
+    #[argh(option)]
+    #[argh(description = "query to time on the loaded file")]
+    query: Option<String>,
+}
 
 // Large file perf (181 mb):
 // * Old: 13.68 sec
@@ -83,13 +193,16 @@ struct BenchMode {}
 //     * JV -> Serde: 3.37 sec
 // * New: 6.32 sec
 //   * Initial parsing (JV deserialize): 6.26
-//   * Query execution: ~0
+//   * Query execution: ~0 (run_jq_query works on JVs directly; serde Values are only used where
+//   they have to be, like sending parsed values between threads in streaming.rs)
 //
 // What can we do to improve load times? The current situation looks bleak.
 // * If (big if) JV iterated through maps in insertion order, you could imagine rendinering the
 // scene before the file is fully loaded. We can't load instantly, but we can definitely load one
 // page of json instantly. Probably worth reading the JV object implementation: hopefully it's not
 // too complicated.
+//   * Top level values, and elements of a top level array, are now shown as they're parsed (see
+//   streaming.rs), but a single huge object still has to load completely.
 // * We might be able to deserialize in parallel.
 // * Use private JV functions to bypass typechecking when we already know the type.
 // * Only use JVRaws duing deserialization.
@@ -108,15 +221,11 @@ struct BenchMode {}
 // TODO
 // * Edit tree:
 //   * Children can be modified if they have no children
-//   * Allow copying descendents onto another root, so you if you want to modify a tree's root you
-// can do so by making a new root and then copying over the descendents
-// * Lightweight error messages (no search results, can't fold a leaf, can't edit a non-leaf)
-//   * Probably requires timers, which requires us to be able to inject stuff into the event
-//   stream. Async? That would also let us show a loading message.
+//   * Copy a tree's descendents onto another root along with its values (c only copies the
+// values)
 // * Diffs
 //   * UI
 //     * Need to make left and right pane independent
-//     * Query is with respect to parent, which may not be visible
 //     * Root nodes have no query
 //     * Once this is implemented, can turn on diffing
 //   * Backend
@@ -141,23 +250,272 @@ struct BenchMode {}
 // * UnstyledSpans
 // * Spans
 
+// What jex exits with when it can't start, so scripts can tell a file that didn't load from a
+// query that didn't compile. Anything else, like a bad argument, counts as a load error.
+const EXIT_LOAD_ERROR: i32 = 1;
+const EXIT_QUERY_ERROR: i32 = 2;
+
+// A query given on the command line that jq rejected.
+#[derive(Debug)]
+struct QueryError(String);
+
+impl fmt::Display for QueryError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Invalid query: {}", self.0)
+    }
+}
+
+impl Error for QueryError {}
+
+fn exit_code(err: &(dyn Error + 'static)) -> i32 {
+    if err.is::<QueryError>() {
+        EXIT_QUERY_ERROR
+    } else {
+        EXIT_LOAD_ERROR
+    }
+}
+
+fn main() {
+    if let Err(err) = try_main() {
+        eprintln!("Error: {}", err);
+        std::process::exit(exit_code(&*err));
+    }
+}
+
 #[cfg(feature = "dev-tools")]
-fn main() -> Result<(), Box<dyn Error>> {
+fn try_main() -> Result<(), Box<dyn Error>> {
     use coredump;
     coredump::register_panic_handler();
     let args: Args = argh::from_env();
+    if args.version {
+        println!("{}", version_info());
+        return Ok(());
+    }
     init_logging(&args);
     match args.mode {
-        Mode::Normal(_) => run(args.json_path),
-        Mode::Bench(_) => bench(args.json_path),
+        Mode::Normal(_) => {
+            let jq_options = jq_options(&args)?;
+            let render_options = render_options(&args);
+            let input = Input::from_args(&args)?;
+            if args.print {
+                return print(input, render_options, args.lossy_utf8);
+            }
+            run(
+                input,
+                jq_options,
+                render_options,
+                args.history_limit,
+                args.schema,
+                !args.no_session,
+                LoadOptions {
+                    lossy_utf8: args.lossy_utf8,
+                    mmap: args.mmap,
+                    query: args.query,
+                    max_results: args.max_results,
+                    safe_mode: args.safe,
+                    print_on_exit: args.print_on_exit,
+                    read_only: args.read_only,
+                    autosave: args.autosave,
+                    show_tree: args.tree,
+                    at: args.at,
+                },
+            )
+        }
+        Mode::Bench(BenchMode { ref query }) => {
+            let (query, jq_options) = (query.clone(), jq_options(&args)?);
+            bench(
+                args.json_path.ok_or("No json path given")?,
+                query,
+                jq_options,
+            )
+        }
     }
 }
 
 #[cfg(not(feature = "dev-tools"))]
-fn main() -> Result<(), Box<dyn Error>> {
+fn try_main() -> Result<(), Box<dyn Error>> {
     let args: Args = argh::from_env();
+    if args.version {
+        println!("{}", version_info());
+        return Ok(());
+    }
     init_logging(&args);
-    run(args.json_path)
+    let jq_options = jq_options(&args)?;
+    let render_options = render_options(&args);
+    let input = Input::from_args(&args)?;
+    if args.print {
+        return print(input, render_options, args.lossy_utf8);
+    }
+    run(
+        input,
+        jq_options,
+        render_options,
+        args.history_limit,
+        args.schema,
+        !args.no_session,
+        LoadOptions {
+            lossy_utf8: args.lossy_utf8,
+            mmap: args.mmap,
+            query: args.query,
+            max_results: args.max_results,
+            safe_mode: args.safe,
+            print_on_exit: args.print_on_exit,
+            read_only: args.read_only,
+            autosave: args.autosave,
+            show_tree: args.tree,
+            at: args.at,
+        },
+    )
+}
+
+// For bug reports: queries can behave differently across jq versions.
+fn version_info() -> String {
+    let features: Vec<&str> = [("dev-tools", cfg!(feature = "dev-tools"))]
+        .iter()
+        .filter(|(_, enabled)| *enabled)
+        .map(|(feature, _)| *feature)
+        .collect();
+    format!(
+        "jex {}\njq {}\nfeatures: {}",
+        env!("CARGO_PKG_VERSION"),
+        JQ_VERSION,
+        if features.is_empty() {
+            "none".to_owned()
+        } else {
+            features.join(", ")
+        }
+    )
+}
+
+// Waits for the next key press, for commands that take a letter after them like `m`.
+fn read_char() -> Option<char> {
+    loop {
+        match event::read().expect("Error getting next event") {
+            event::Event::Key(KeyEvent {
+                code: KeyCode::Char(c),
+                ..
+            }) => return Some(c),
+            event::Event::Key(_) => return None,
+            _ => {}
+        }
+    }
+}
+
+// Writes the whole file to stdout as it would be rendered, at the terminal's width.
+fn print(
+    input: Option<Input>,
+    render_options: RenderOptions,
+    lossy_utf8: bool,
+) -> Result<(), Box<dyn Error>> {
+    let input = input.ok_or("No json path given")?;
+    let (width, height) = crossterm::terminal::size().unwrap_or((80, 24));
+    let layout = JexLayout::new(
+        Rect {
+            x: 0,
+            y: 0,
+            width,
+            height,
+        },
+        false,
+    );
+    let mut app = match input {
+        Input::File(json_path) => {
+            let r = formats::open_input(&json_path, lossy_utf8)?;
+            App::new(r, json_path, layout)?
+        }
+        Input::Command(command) => {
+            let r = formats::open_command(&command, lossy_utf8)?;
+            App::new_with_format(r, InputFormat::Json, command, layout)?
+        }
+    };
+    app.set_render_options(render_options);
+    let mut stdout = io::stdout();
+    for line in app.focused_plain_text(true).unwrap_or_default() {
+        writeln!(stdout, "{}", line)?;
+    }
+    Ok(())
+}
+
+// What was given on the command line to open.
+enum Input {
+    File(String),
+    // A shell command, whose output is opened.
+    Command(String),
+}
+
+impl Input {
+    fn from_args(args: &Args) -> Result<Option<Self>, Box<dyn Error>> {
+        match (&args.json_path, &args.exec) {
+            (Some(_), Some(_)) => Err("Give either a json path or --exec, not both".into()),
+            (None, Some(_)) if args.safe => Err(app::disabled_in_safe_mode("--exec").into()),
+            (Some(json_path), None) => Ok(Some(Input::File(json_path.clone()))),
+            (None, Some(command)) => Ok(Some(Input::Command(command.clone()))),
+            (None, None) => Ok(None),
+        }
+    }
+}
+
+// How to read the file given on the command line, and what to show of it.
+struct LoadOptions {
+    lossy_utf8: bool,
+    mmap: bool,
+    query: Option<String>,
+    max_results: Option<usize>,
+    safe_mode: bool,
+    print_on_exit: bool,
+    read_only: bool,
+    autosave: bool,
+    show_tree: bool,
+    at: Option<String>,
+}
+
+fn render_options(args: &Args) -> RenderOptions {
+    RenderOptions {
+        auto_raw: args.auto_raw,
+        indent_width: args.indent_width,
+        format_numbers: args.format_numbers,
+        float_precision: args.float_precision,
+        fold_preview_width: args.fold_preview,
+        max_depth: args.max_depth,
+        ..RenderOptions::default()
+    }
+}
+
+fn split_named_arg(arg: &str) -> Result<(&str, &str), String> {
+    let mut split = arg.splitn(2, '=');
+    match (split.next(), split.next()) {
+        (Some(name), Some(value)) if !name.is_empty() => Ok((name, value)),
+        _ => Err(format!("Expected name=value, got {:?}", arg)),
+    }
+}
+
+fn jq_options(args: &Args) -> Result<JQOptions, Box<dyn Error>> {
+    // jq resolves relative search paths against the location of the main program, which jex
+    // doesn't have, so resolve them against the working directory up front.
+    let library_paths = args
+        .library_path
+        .iter()
+        .map(|path| {
+            fs::canonicalize(path)
+                .map(|path| path.to_string_lossy().into_owned())
+                .unwrap_or_else(|_| path.clone())
+        })
+        .collect();
+    let mut named_args = Vec::new();
+    for arg in args.arg.iter() {
+        let (name, value) = split_named_arg(arg)?;
+        named_args.push((name.to_owned(), JVString::new(value).into()));
+    }
+    for arg in args.argjson.iter() {
+        let (name, value) = split_named_arg(arg)?;
+        let value: JV = serde_json::from_str(value)
+            .map_err(|err| format!("Invalid json for --argjson {}: {}", name, err))?;
+        named_args.push((name.to_owned(), value));
+    }
+    Ok(JQOptions {
+        library_paths,
+        named_args,
+    })
 }
 
 fn init_logging(args: &Args) {
@@ -197,6 +555,40 @@ fn force_draw<B: tui::backend::Backend, F: FnMut(&mut Frame<B>)>(
     terminal.backend_mut().draw(updates.into_iter())
 }
 
+// Redraws everything after a rustyline prompt or editor, which draw over jex and swallow any
+// resize while they're open. The panes are fitted to the terminal as it is now, since crossterm
+// never sees those resizes.
+// Returns the layout for the terminal's size now, since it may have changed during the prompt.
+fn redraw_after_prompt<B: tui::backend::Backend>(
+    app: &mut App,
+    terminal: &mut Terminal<B>,
+) -> Result<JexLayout, io::Error> {
+    terminal.autoresize()?;
+    let layout = app.layout(terminal.get_frame().size());
+    app.resize(layout);
+    force_draw(terminal, app.render(AppRenderMode::Normal))?;
+    Ok(layout)
+}
+
+thread_local! {
+    // Set while handling an event, so the panic hook leaves the terminal alone and saves the
+    // message for `catch_panic` to show.
+    static CATCHING_PANICS: Cell<bool> = const { Cell::new(false) };
+    static CAUGHT_PANIC: RefCell<Option<String>> = const { RefCell::new(None) };
+}
+
+// Runs `f`, returning the message of any panic instead of unwinding out of the editor.
+fn catch_panic<T>(f: impl FnOnce() -> T) -> Result<T, String> {
+    CATCHING_PANICS.with(|catching| catching.set(true));
+    let result = panic::catch_unwind(panic::AssertUnwindSafe(f));
+    CATCHING_PANICS.with(|catching| catching.set(false));
+    result.map_err(|_| {
+        CAUGHT_PANIC
+            .with(|caught| caught.borrow_mut().take())
+            .unwrap_or_else(|| "Unknown panic".to_owned())
+    })
+}
+
 struct DeferRestoreTerminal {}
 
 impl Drop for DeferRestoreTerminal {
@@ -208,23 +600,67 @@ impl Drop for DeferRestoreTerminal {
 
 struct RustylineWrapper {
     history_path: PathBuf,
+    // Whether other prompts use this history file too. If so, it's reread before each prompt and
+    // written after, so each sees what was typed into the others.
+    shared: bool,
     editor: rustyline::Editor<Helper>,
 }
 
 impl RustylineWrapper {
-    fn new(history_path: PathBuf) -> Result<Self, Box<dyn Error>> {
-        let config = rustyline::Config::builder().auto_add_history(true).build();
+    // Past `max_entries`, the oldest entries are dropped, including when it's loaded.
+    fn new(
+        history_path: PathBuf,
+        max_entries: Option<usize>,
+        shared: bool,
+    ) -> Result<Self, Box<dyn Error>> {
+        let mut builder = rustyline::Config::builder().auto_add_history(true);
+        if let Some(max_entries) = max_entries {
+            builder = builder.max_history_size(max_entries);
+        }
+        let config = builder.build();
         let mut editor = rustyline::Editor::with_config(config);
         let _ = editor.history_mut().load(&history_path);
         editor.bind_sequence(rustyline::KeyPress::Esc, rustyline::Cmd::Interrupt);
         Ok(RustylineWrapper {
             history_path,
+            shared,
             editor,
         })
     }
-}
-impl Drop for RustylineWrapper {
-    fn drop(&mut self) {
+    fn readline(&mut self, prompt: &str) -> rustyline::Result<String> {
+        self.readline_with_initial(prompt, ("", ""))
+    }
+    fn readline_with_initial(
+        &mut self,
+        prompt: &str,
+        initial: (&str, &str),
+    ) -> rustyline::Result<String> {
+        if self.shared {
+            self.editor.history_mut().clear();
+            let _ = self.editor.history_mut().load(&self.history_path);
+        }
+        let line = self.editor.readline_with_initial(prompt, initial);
+        if self.shared {
+            self.save_history();
+        }
+        line
+    }
+    // The entries so far, oldest first.
+    fn history_entries(&mut self) -> Vec<String> {
+        if self.shared {
+            self.editor.history_mut().clear();
+            let _ = self.editor.history_mut().load(&self.history_path);
+        }
+        self.editor.history().iter().cloned().collect()
+    }
+    // For prompts jex reads itself, which rustyline doesn't see.
+    fn add_history(&mut self, line: &str) {
+        self.editor.history_mut().add(line);
+        if self.shared {
+            self.save_history();
+        }
+    }
+    fn save_history(&self) {
         let res = create_dir_all(self.history_path.parent().unwrap());
         if let Err(err) = res {
             warn!("Error creating directory: {:?}", err);
@@ -235,263 +671,994 @@ impl Drop for RustylineWrapper {
         }
     }
 }
+impl Drop for RustylineWrapper {
+    fn drop(&mut self) {
+        // A shared history was saved after every prompt, and might have been added to since by
+        // another prompt.
+        if !self.shared {
+            self.save_history();
+        }
+    }
+}
+
+// Multi-line queries don't fit in the query bar, and wouldn't survive a round trip through the
+// line-based rustyline history, so they're edited in $VISUAL/$EDITOR instead.
+fn edit_in_external_editor(query: &str) -> Result<String, Box<dyn Error>> {
+    // Removed when it's dropped, however this returns.
+    let mut file = tempfile::Builder::new()
+        .prefix("jex_query_")
+        .suffix(".jq")
+        .tempfile()?;
+    file.write_all(query.as_bytes())?;
+    file.flush()?;
+    let editor = std::env::var("VISUAL")
+        .or_else(|_| std::env::var("EDITOR"))
+        .unwrap_or_else(|_| "vi".to_owned());
+    disable_raw_mode()?;
+    execute!(io::stdout(), LeaveAlternateScreen)?;
+    let status = std::process::Command::new("sh")
+        .arg("-c")
+        .arg(format!("{} \"$1\"", editor))
+        .arg("sh")
+        .arg(file.path())
+        .status();
+    execute!(io::stdout(), EnterAlternateScreen)?;
+    enable_raw_mode()?;
+    let status = status?;
+    if !status.success() {
+        return Err(format!("{} exited with {}", editor, status).into());
+    }
+    // Read by path, since editors that save by replacing the file leave our handle on the old one.
+    let new_query = fs::read_to_string(file.path())?;
+    Ok(new_query.trim_end().to_owned())
+}
+
+// Asks on the plain terminal, before we enter raw mode, whether to restore the last session.
+fn confirm_restore_session() -> io::Result<bool> {
+    print!("No file given. Restore the last session? [y/N] ");
+    io::stdout().flush()?;
+    let mut answer = String::new();
+    io::stdin().read_line(&mut answer)?;
+    Ok(matches!(answer.trim(), "y" | "Y" | "yes"))
+}
+
+// Asks whether to save views with unsaved edits before quitting, discard them, or keep going.
+// Returns whether to keep going.
+fn confirm_quit<B: tui::backend::Backend>(
+    app: &mut App,
+    terminal: &mut Terminal<B>,
+    save_rl: &mut RustylineWrapper,
+    unsaved: &[ViewForestIndex],
+) -> Result<bool, Box<dyn Error>> {
+    let mut lines = vec!["Unsaved edits in:".to_owned()];
+    for index in unsaved {
+        if let Some(view) = app.views.index(index) {
+            lines.push(format!("  {}", view.frame().name));
+        }
+    }
+    lines.push(String::new());
+    lines.push("s: Save them, d: Discard them and quit, anything else: Keep going".to_owned());
+    app.set_flash(lines.join("\n"));
+    terminal.draw(app.render(AppRenderMode::Normal))?;
+    let choice = read_char();
+    app.flash = None;
+    match choice {
+        Some('d') => Ok(false),
+        Some('s') => {
+            let read_only = app.read_only;
+            for index in unsaved {
+                terminal.draw(app.render(AppRenderMode::InputEditor))?;
+                let frame = match app.views.index_mut(index) {
+                    Some(view) => view.take_frame(),
+                    None => continue,
+                };
+                let initial = frame.save_path();
+                let view = match &mut frame.view {
+                    View::Json(Some(view)) => view,
+                    _ => continue,
+                };
+                // Cancelling a save cancels quitting, so nothing's lost.
+                let path = match save_rl.readline_with_initial("Save to:", (&initial, "")) {
+                    Ok(path) => path,
+                    Err(_) => return Ok(true),
+                };
+                if app::refuses_to_save_over(read_only, &path) {
+                    app.set_flash(format!("Read-only: not saving over {}", path));
+                    return Ok(true);
+                }
+                if let Err(err) = view.save_to(&path) {
+                    app.set_flash(format!("Error saving json:\n{:?}", err));
+                    return Ok(true);
+                }
+                view.modified = false;
+            }
+            Ok(false)
+        }
+        _ => Ok(true),
+    }
+}
 
-fn run(json_path: String) -> Result<(), Box<dyn Error>> {
+// After the focused view is saved to `path` with folds, offers to save them next to it.
+fn offer_fold_sidecar<B: tui::backend::Backend>(
+    app: &mut App,
+    terminal: &mut Terminal<B>,
+    path: &str,
+) -> Result<(), Box<dyn Error>> {
+    let sidecar = session::fold_sidecar_path(path);
+    app.set_flash(format!(
+        "Also save the folds to {}, so it opens folded the same way?\n\ny: Save them, anything else: Don't",
+        sidecar
+    ));
+    terminal.draw(app.render(AppRenderMode::Normal))?;
+    let choice = read_char();
+    app.flash = None;
+    if choice != Some('y') {
+        return Ok(());
+    }
+    let result = match &app.focused_view().frame().view {
+        View::Json(Some(view)) => session::save_fold_sidecar(path, view),
+        View::Json(None) | View::Error(_) => return Ok(()),
+    };
+    match result {
+        Ok(()) => app.set_status(format!("Saved the folds to {}", sidecar)),
+        Err(err) => app.set_flash(format!("Error saving folds:\n{:?}", err)),
+    }
+    Ok(())
+}
+
+// Reads a search pattern, moving to the first match after the cursor as each key is typed. Up and
+// Down go through the search history, and Esc puts the cursor back where it was.
+fn run_search<B: tui::backend::Backend>(
+    app: &mut App,
+    terminal: &mut Terminal<B>,
+    search_rl: &mut RustylineWrapper,
+) -> Result<(), Box<dyn Error>> {
+    let origin = match app.search_origin() {
+        Some(origin) => origin,
+        None => return Ok(()),
+    };
+    let history = search_rl.history_entries();
+    // Up and Down's place in the history, which is past the end for what's being typed.
+    let mut position = history.len();
+    let mut pattern = String::new();
+    loop {
+        let prompt = format!("Search:{}", pattern);
+        terminal.draw(app.render(AppRenderMode::Prompt(prompt)))?;
+        let key = match event::read()? {
+            event::Event::Key(key) => key,
+            event::Event::Resize(..) => {
+                terminal.autoresize()?;
+                app.resize(app.layout(terminal.get_frame().size()));
+                continue;
+            }
+            event::Event::Mouse(_) => continue,
+        };
+        let control = key.modifiers.contains(KeyModifiers::CONTROL);
+        match key.code {
+            KeyCode::Esc => {
+                app.cancel_search(origin);
+                return Ok(());
+            }
+            KeyCode::Char('c') if control => {
+                app.cancel_search(origin);
+                return Ok(());
+            }
+            KeyCode::Enter => {
+                if !pattern.is_empty() {
+                    search_rl.add_history(&pattern);
+                }
+                return Ok(());
+            }
+            KeyCode::Backspace => {
+                pattern.pop();
+            }
+            KeyCode::Up if position > 0 => {
+                position -= 1;
+                pattern = history[position].clone();
+            }
+            KeyCode::Down if position < history.len() => {
+                position += 1;
+                pattern = history.get(position).cloned().unwrap_or_default();
+            }
+            KeyCode::Char(c) if !control => pattern.push(c),
+            _ => continue,
+        }
+        app.preview_search(&pattern, &origin);
+    }
+}
+
+// Shows the command palette until a command is chosen or it's closed, returning the chosen
+// command's key.
+fn run_palette<B: tui::backend::Backend>(
+    app: &mut App,
+    terminal: &mut Terminal<B>,
+) -> Result<Option<KeyEvent>, Box<dyn Error>> {
+    let mut palette = Palette::new(app::controls_help());
+    loop {
+        app.set_flash(palette.text());
+        terminal.draw(app.render(AppRenderMode::Normal))?;
+        let key = match event::read()? {
+            event::Event::Key(key) => key,
+            event::Event::Resize(..) => {
+                terminal.autoresize()?;
+                app.resize(app.layout(terminal.get_frame().size()));
+                continue;
+            }
+            event::Event::Mouse(_) => continue,
+        };
+        match palette.handle_key(key) {
+            PaletteAction::Continue => {}
+            PaletteAction::Close => {
+                app.flash = None;
+                return Ok(None);
+            }
+            PaletteAction::Run(key) => {
+                app.flash = None;
+                return Ok(Some(key));
+            }
+        }
+    }
+}
+
+// Validates the focused view against `schema_path`, listing any errors and offering to jump the
+// cursor to one of them.
+fn validate_and_jump<B: tui::backend::Backend>(
+    app: &mut App,
+    terminal: &mut Terminal<B>,
+    errors_rl: &mut RustylineWrapper,
+    schema_path: &str,
+) -> Result<(), Box<dyn Error>> {
+    let errors = match app.validate_focused(schema_path) {
+        Ok(errors) => errors,
+        Err(err) => {
+            app.set_flash(format!("Error loading schema {}:\n{}", schema_path, err));
+            return Ok(());
+        }
+    };
+    if errors.is_empty() {
+        app.set_flash(format!("Valid against {}", schema_path));
+        return Ok(());
+    }
+    let listing: Vec<String> = errors
+        .iter()
+        .enumerate()
+        .map(|(i, err)| format!("{}. {}", i + 1, err))
+        .collect();
+    app.set_flash(listing.join("\n"));
+    terminal.draw(app.render(AppRenderMode::InputEditor))?;
+    let selection = errors_rl.readline("Jump to error:");
+    app.flash = None;
+    if let Ok(selection) = selection {
+        let error = selection
+            .trim()
+            .parse::<usize>()
+            .ok()
+            .and_then(|i| errors.get(i.checked_sub(1)?));
+        match error {
+            Some(error) => {
+                if !app.jump_focused_to(error.top_index, &error.path) {
+                    app.set_flash(format!("Couldn't find {}", error));
+                }
+            }
+            None => app.set_flash(format!("No error {:?}", selection)),
+        }
+    }
+    redraw_after_prompt(app, terminal)?;
+    Ok(())
+}
+
+fn run(
+    input: Option<Input>,
+    jq_options: JQOptions,
+    render_options: RenderOptions,
+    history_limit: usize,
+    schema_path: Option<String>,
+    save_session: bool,
+    load_options: LoadOptions,
+) -> Result<(), Box<dyn Error>> {
+    let LoadOptions {
+        lossy_utf8,
+        mmap,
+        query,
+        max_results,
+        safe_mode,
+        print_on_exit,
+        read_only,
+        autosave,
+        show_tree,
+        at,
+    } = load_options;
+    if autosave && read_only {
+        return Err("Give either --autosave or --read-only, not both".into());
+    }
+    let project_dirs =
+        directories::ProjectDirs::from("", "", "jex").ok_or("Error getting project dirs")?;
+    let cache_dir = project_dirs.cache_dir();
+    let config = Config::load(&project_dirs.config_dir().join("config.json"))?;
+    let depth_palette = config.depth_palette()?.unwrap_or_default();
+    let scalar_glyphs = config.scalar_glyphs()?;
+    let session_path = cache_dir.join("session.json");
+    // Remembering files goes along with saving the session.
+    let files_path = cache_dir.join("files.json");
+    let file_states = if save_session {
+        Some(FileStates::load(&files_path).unwrap_or_else(|err| {
+            warn!("Error loading remembered files: {:?}", err);
+            FileStates::default()
+        }))
+    } else {
+        None
+    };
+    let session = match &input {
+        Some(_) => None,
+        None if session_path.exists() && confirm_restore_session()? => {
+            Some(Session::load(&session_path)?)
+        }
+        None => return Err("No json path given".into()),
+    };
+    if let Some(query) = &query {
+        // Checked before the terminal is taken over, so scripts see the error.
+        JQ::compile_with(query, &jq_options).map_err(|errors| QueryError(errors.join("\n")))?;
+    }
+    // Likewise, a command is run before, so its errors are printed.
+    let mut command_output = match &input {
+        Some(Input::Command(command)) => Some(formats::open_command(command, lossy_utf8)?),
+        Some(Input::File(_)) | None => None,
+    };
     enable_raw_mode().expect("Failed to enter raw mode");
 
     let mut stdout = io::stdout();
     execute!(stdout, EnterAlternateScreen).expect("Failed to enter alternate screen");
     let default_panic_handler = panic::take_hook();
     panic::set_hook(Box::new(move |p| {
+        if CATCHING_PANICS.with(Cell::get) {
+            CAUGHT_PANIC.with(|caught| *caught.borrow_mut() = Some(p.to_string()));
+            return;
+        }
         disable_raw_mode().expect("Failed to disable raw mode");
         execute!(io::stdout(), LeaveAlternateScreen).expect("Failed to leave alternate screen");
         default_panic_handler(p);
     }));
-    let _defer = DeferRestoreTerminal {};
-    let f = fs::File::open(&json_path)?;
-    let r = io::BufReader::new(f);
+    let defer_restore = DeferRestoreTerminal {};
     let stdout = io::stdout();
     let backend = CrosstermBackend::new(stdout);
     let mut terminal = Terminal::new(backend)?;
-    let initial_layout = JexLayout::new(terminal.get_frame().size(), false);
-    let mut app = App::new(r, json_path, initial_layout)?;
+    let show_tree = show_tree || config.show_tree;
+    let initial_layout = JexLayout::new(terminal.get_frame().size(), show_tree);
+    let mut events = EventLoop::new();
+    let mut app = match (input, session) {
+        (_, Some(session)) => {
+            let mut app = session.restore(jq_options, lossy_utf8, initial_layout)?;
+            app.file_states = file_states;
+            app.max_results = max_results;
+            app.safe_mode = safe_mode;
+            app
+        }
+        (Some(input), None) => {
+            let file_path = match &input {
+                Input::File(json_path) => Some(json_path.clone()),
+                Input::Command(_) => None,
+            };
+            let mut app = match input {
+                // Replacing invalid UTF-8 means copying the file anyway, so it isn't mapped.
+                Input::File(json_path) if mmap && !lossy_utf8 => {
+                    App::new_mapped(json_path, initial_layout)?
+                }
+                Input::File(json_path) => {
+                    let mut r = formats::open_input(&json_path, lossy_utf8)?;
+                    // Uncompressed json is read just as it is on disk, so its size says how far
+                    // along loading is.
+                    if formats::is_plain_json(&json_path) {
+                        let total = fs::metadata(&json_path)?.len();
+                        let sender = events.sender();
+                        r = Box::new(ProgressRead::new(r, app::LOADING_TASK, total, sender));
+                    }
+                    App::new_streaming(r, json_path, initial_layout)?
+                }
+                Input::Command(command) => {
+                    let r = command_output.take().expect("command should have run");
+                    App::new_streaming_json(r, command, initial_layout)?
+                }
+            };
+            app.jq_options = jq_options;
+            app.file_states = file_states;
+            app.max_results = max_results;
+            app.safe_mode = safe_mode;
+            app.report_size(0);
+            if let Some(path) = file_path {
+                app.views.trees[0].view_frame.source_path = Some(PathBuf::from(&path));
+                app.restore_file_state(0, &path, initial_layout);
+                app.restore_fold_sidecar(0, &path);
+            }
+            if let Some(path) = at {
+                app.jump_once_loaded(path);
+            }
+            if let Some(query) = query {
+                app.set_right_query(query, initial_layout);
+            }
+            app
+        }
+        (None, None) => unreachable!("checked above"),
+    };
+    app.set_render_options(RenderOptions {
+        max_value_chars: config.max_value_chars,
+        depth_palette,
+        scalar_glyphs,
+        ..render_options
+    });
+    app.history_limit = history_limit;
+    app.lossy_utf8 = lossy_utf8;
+    app.read_only = read_only;
+    if autosave {
+        app.autosave = Some(config.autosave_delay()?);
+    }
+    app.show_tree = show_tree;
     terminal.draw(app.render(AppRenderMode::Normal))?;
-    let project_dirs =
-        directories::ProjectDirs::from("", "", "jex").ok_or("Error getting project dirs")?;
-    let cache_dir = project_dirs.cache_dir();
-    let mut query_rl = RustylineWrapper::new(cache_dir.join("query_history"))?;
-    let mut search_rl = RustylineWrapper::new(cache_dir.join("search_history"))?;
-    let mut open_rl = RustylineWrapper::new(cache_dir.join("open_history"))?;
-    let mut rename_rl = RustylineWrapper::new(cache_dir.join("rename_history"))?;
-    let mut save_rl = RustylineWrapper::new(cache_dir.join("save_history"))?;
+    let history = |name: &str| {
+        RustylineWrapper::new(
+            config.history.path(cache_dir, name),
+            config.history.max_entries(name),
+            config.history.is_shared(name),
+        )
+    };
+    let mut query_rl = history("query")?;
+    let mut search_rl = history("search")?;
+    let mut open_rl = history("open")?;
+    let mut rename_rl = history("rename")?;
+    let mut save_rl = history("save")?;
+    let mut pipe_rl = history("pipe")?;
+    let mut library_rl = history("library")?;
+    let library_path = project_dirs.config_dir().join("queries.json");
+    let mut library = QueryLibrary::load(&library_path)?;
+    let mut schema_rl = history("schema")?;
+    let mut edit_rl = history("edit")?;
+    let mut sort_rl = history("sort")?;
+    let mut fold_rl = history("fold")?;
+    let mut line_rl = history("line")?;
+    let mut export_rl = history("export")?;
+    let mut schema_errors_rl = history("schema_errors")?;
 
     open_rl.editor.set_helper(Some(Helper::new()));
     save_rl.editor.set_helper(Some(Helper::new()));
+    export_rl.editor.set_helper(Some(Helper::new()));
+    schema_rl.editor.set_helper(Some(Helper::new()));
+    query_rl.editor.set_helper(Some(Helper::new_jq()));
+    if let Some(schema_path) = schema_path {
+        validate_and_jump(&mut app, &mut terminal, &mut schema_errors_rl, &schema_path)?;
+        terminal.draw(app.render(AppRenderMode::Normal))?;
+    }
+    // A key chosen from the command palette, handled as if it had been pressed.
+    let mut pending: Option<event::Event> = None;
     loop {
-        let event = event::read().expect("Error getting next event");
-        debug!("Event: {:?}", event);
-        let c = match event {
-            event::Event::Key(c) => c,
-            event::Event::Mouse(_) => panic!("Mouse events aren't enabled!"),
-            event::Event::Resize(width, height) => {
-                let rect = Rect {
-                    x: 0,
-                    y: 0,
-                    width,
-                    height,
-                };
-                let layout = JexLayout::new(rect, app.show_tree);
-                app.resize(layout);
+        let next = match pending.take() {
+            Some(event) => AppEvent::Input(event),
+            None => events.next(app.is_busy())?,
+        };
+        let event = match next {
+            AppEvent::Input(event) => event,
+            AppEvent::Tick => {
+                app.tick(app.layout(terminal.get_frame().size()));
+                terminal.draw(app.render(AppRenderMode::Normal))?;
+                continue;
+            }
+            AppEvent::Progress(progress) => {
+                app.update_task(progress);
                 terminal.draw(app.render(AppRenderMode::Normal))?;
                 continue;
             }
         };
-        let layout = JexLayout::new(terminal.get_frame().size(), app.show_tree);
-        if let Some(flash) = app.flash.as_mut() {
+        debug!("Event: {:?}", event);
+        // Returns whether to keep going. A panic here is shown as an error rather than exiting, so
+        // a bug in one command doesn't lose everything that's open.
+        let handled = catch_panic(|| -> Result<bool, Box<dyn Error>> {
+            let c = match event {
+                event::Event::Key(c) => c,
+                event::Event::Mouse(_) => panic!("Mouse events aren't enabled!"),
+                event::Event::Resize(width, height) => {
+                    let rect = Rect {
+                        x: 0,
+                        y: 0,
+                        width,
+                        height,
+                    };
+                    let layout = app.layout(rect);
+                    app.resize(layout);
+                    terminal.draw(app.render(AppRenderMode::Normal))?;
+                    return Ok(true);
+                }
+            };
+            let layout = app.layout(terminal.get_frame().size());
+            if let Some(flash) = app.flash.as_mut() {
+                match c.code {
+                    KeyCode::Esc => {
+                        app.flash = None;
+                    }
+                    KeyCode::Down => {
+                        flash.scroll = flash.scroll.saturating_add(1);
+                    }
+                    KeyCode::Up => {
+                        flash.scroll = flash.scroll.saturating_sub(1);
+                    }
+                    _ => {}
+                }
+                terminal.draw(app.render(AppRenderMode::Normal))?;
+                return Ok(true);
+            }
             match c.code {
-                KeyCode::Esc => {
-                    app.flash = None;
+                KeyCode::Esc | KeyCode::Char('T') if app.outline.is_some() => {
+                    app.outline = None;
+                    app.resize(app.layout(terminal.get_frame().size()));
                 }
-                KeyCode::Down => {
-                    flash.scroll = flash.scroll.saturating_add(1);
+                KeyCode::Char('T') => {
+                    app.toggle_outline();
+                    app.resize(app.layout(terminal.get_frame().size()));
                 }
-                KeyCode::Up => {
-                    flash.scroll = flash.scroll.saturating_sub(1);
+                KeyCode::Char('j') if app.outline.is_some() => app.move_outline_selection(1),
+                KeyCode::Char('k') if app.outline.is_some() => app.move_outline_selection(-1),
+                KeyCode::Enter if app.outline.is_some() => app.jump_to_outline_selection(),
+                KeyCode::Right | KeyCode::Left if app.outline.is_some() => {
+                    let changed = app.expand_outline_selection(c.code == KeyCode::Right);
+                    if !changed {
+                        app.set_status("Nothing to expand or collapse here");
+                    }
                 }
-                _ => {}
-            }
-            terminal.draw(app.render(AppRenderMode::Normal))?;
-            continue;
-        }
-        match c.code {
-            KeyCode::Esc => break,
-            KeyCode::Char('t') => {
-                app.show_tree = !app.show_tree;
-            }
-            KeyCode::Char('q') => {
-                if app.focused_query_mut().is_some() {
-                    terminal.draw(app.render(AppRenderMode::InputEditor))?;
-                    let query = app.focused_query_mut().unwrap();
-                    match query_rl.editor.readline_with_initial("", (&*query, "")) {
-                        Ok(new_query) => {
-                            *query = new_query;
-                            // Just in case rustyline messed stuff up
-                            force_draw(&mut terminal, app.render(AppRenderMode::Normal))?;
-                            app.recompute_focused_view(layout.right);
-                        }
-                        Err(_) => {}
+                KeyCode::Esc => {
+                    let unsaved = app.unsaved_views();
+                    if unsaved.is_empty() {
+                        return Ok(false);
                     }
+                    return confirm_quit(&mut app, &mut terminal, &mut save_rl, &unsaved);
                 }
-            }
-            KeyCode::Tab => {
-                app.focus = app.focus.swap();
-                debug!("Swapped focus to {:?}", app.focus);
-            }
-            KeyCode::Char('+') => {
-                let (index, rect) = match app.focus {
-                    Focus::Left => (&app.left_index, layout.left),
-                    Focus::Right => (&app.right_index, layout.right),
-                };
-                let tree = app.views.trees[index.tree]
-                    .index_tree_mut(&index.within_tree.path)
-                    .expect("App index invalidated");
-                tree.push_trivial_child(rect);
-            }
-            KeyCode::Char('j') => match app.focus {
-                Focus::Left => {
-                    app.left_index.advance(&app.views);
+                _ if c == palette::palette_key() => {
+                    pending = run_palette(&mut app, &mut terminal)?.map(event::Event::Key);
+                }
+                KeyCode::Char(c @ ']') | KeyCode::Char(c @ '}') => {
+                    let fetching = app.fetch_more_results(c == '}');
+                    if !fetching {
+                        app.set_status("No query is stopped at --max-results");
+                    }
                 }
-                Focus::Right => {
-                    app.right_index.advance(&app.views);
+                KeyCode::Char('J') => {
+                    let mut command = app.focused_jq_command();
+                    if command.ends_with(app::INPUT_PLACEHOLDER) {
+                        command.push_str(&format!(
+                            "\n\nThis view wasn't opened from a json file, so replace {} with one.",
+                            app::INPUT_PLACEHOLDER
+                        ));
+                    }
+                    app.set_flash(format!(
+                        "To run this view's query outside jex:\n\n{}",
+                        command
+                    ));
                 }
-            },
-            KeyCode::Char('k') => match app.focus {
-                Focus::Left => {
-                    app.left_index.regress(&app.views);
+                KeyCode::Char('t') => {
+                    app.show_tree = !app.show_tree;
+                    app.resize(app.layout(terminal.get_frame().size()));
                 }
-                Focus::Right => {
-                    app.right_index.regress(&app.views);
+                KeyCode::Char('.') => {
+                    app.repeat_last_query(layout);
                 }
-            },
-            KeyCode::Char('r') => {
-                terminal.draw(app.render(AppRenderMode::InputEditor))?;
-                let mut view_with_parent = app.focused_view_mut();
-                let frame = view_with_parent.frame();
-                match rename_rl
-                    .editor
-                    .readline_with_initial("New Title:", (&frame.name, ""))
+                KeyCode::Char('Q') | KeyCode::Char('q')
+                    if matches!(app.focused_query(),
+                        Some(query) if c.code == KeyCode::Char('Q') || query.contains('\n')) =>
                 {
-                    Ok(new_name) => {
-                        frame.name = new_name;
+                    if app.safe_mode {
+                        let feature = "Editing queries in $VISUAL or $EDITOR";
+                        app.set_flash(app::disabled_in_safe_mode(feature));
+                    } else {
+                        let query = app.focused_query_mut().unwrap();
+                        let edited = edit_in_external_editor(query);
+                        let layout = redraw_after_prompt(&mut app, &mut terminal)?;
+                        match edited {
+                            Ok(new_query) => {
+                                *app.focused_query_mut().unwrap() = new_query;
+                                app.recompute_focused_view(layout);
+                            }
+                            Err(err) => app.set_flash(format!("Error editing query:\n{}", err)),
+                        }
                     }
-                    Err(_) => {}
                 }
-                force_draw(&mut terminal, app.render(AppRenderMode::Normal))?;
-            }
-            KeyCode::Char('s') => {
-                terminal.draw(app.render(AppRenderMode::InputEditor))?;
-                let mut view_with_parent = app.focused_view_mut();
-                let frame = view_with_parent.frame();
-                let flash = {
-                    if let View::Json(Some(view)) = &frame.view {
-                        match save_rl
-                            .editor
-                            .readline_with_initial("Save to:", (&frame.name, ""))
-                        {
-                            Ok(path) => {
-                                if let Err(err) = view.save_to(&path) {
-                                    Some(format!("Error saving json:\n{:?}", err))
-                                } else {
-                                    frame.name = path;
-                                    let focused_index = app.focused_index().clone();
-                                    app.re_root(&focused_index);
-                                    None
-                                }
+                KeyCode::Char('q') | KeyCode::Char('Q') => {
+                    if app.focused_query().is_none() {
+                        app.set_status(ROOT_HAS_NO_QUERY);
+                    } else {
+                        if let Some(helper) = query_rl.editor.helper_mut() {
+                            helper.set_keys(app.focused_query_input_keys());
+                        }
+                        terminal.draw(app.render(AppRenderMode::InputEditor))?;
+                        let query = app.focused_query_mut().unwrap();
+                        match query_rl.readline_with_initial("", (&*query, "")) {
+                            Ok(new_query) => {
+                                *query = new_query;
+                                // Just in case rustyline messed stuff up
+                                let layout = redraw_after_prompt(&mut app, &mut terminal)?;
+                                app.recompute_focused_view(layout);
                             }
-                            Err(_) => None,
+                            Err(_) => {}
                         }
+                    }
+                }
+                KeyCode::Char('+') => {
+                    let (index, rect) = match app.focus {
+                        Focus::Left => (&app.left_index, layout.left),
+                        Focus::Right => (&app.right_index, layout.right),
+                    };
+                    // Show the new view in the edit tree.
+                    app.views
+                        .folds
+                        .remove(&(index.tree, index.within_tree.path.clone()));
+                    let tree = app.views.trees[index.tree]
+                        .index_tree_mut(&index.within_tree.path)
+                        .expect("App index invalidated");
+                    tree.push_trivial_child(rect);
+                }
+                KeyCode::Char('P') => {
+                    app.read_only = !app.read_only;
+                    app.set_status(if app.read_only {
+                        "Read-only: edits are turned off"
                     } else {
-                        None
+                        "Edits are allowed"
+                    });
+                }
+                KeyCode::Char('e')
+                | KeyCode::Char('d')
+                | KeyCode::Char('D')
+                | KeyCode::Char('O')
+                | KeyCode::Char('u')
+                    if app.read_only =>
+                {
+                    app.set_flash(READ_ONLY_MESSAGE.to_owned());
+                }
+                KeyCode::Char('r')
+                    if app.read_only && c.modifiers.contains(KeyModifiers::CONTROL) =>
+                {
+                    app.set_flash(READ_ONLY_MESSAGE.to_owned());
+                }
+                // Before prompting for an edit that would be refused.
+                KeyCode::Char('e') | KeyCode::Char('O') if app.refuses_edit() => {}
+                KeyCode::Char('e') => {
+                    if let Some(text) = app.focused_leaf_text() {
+                        terminal.draw(app.render(AppRenderMode::InputEditor))?;
+                        let edited = edit_rl.readline_with_initial("Edit value:", (&text, ""));
+                        let layout = redraw_after_prompt(&mut app, &mut terminal)?;
+                        if let Ok(edited) = edited {
+                            app.edit_focused_leaf(&edited, layout);
+                        }
+                    } else {
+                        app.set_status("Only strings, numbers, booleans and null can be edited");
                     }
-                };
-                if let Some(flash) = flash {
-                    app.set_flash(flash);
                 }
-                force_draw(&mut terminal, app.render(AppRenderMode::Normal))?;
-            }
-            KeyCode::Char('o') => {
-                terminal.draw(app.render(AppRenderMode::InputEditor))?;
-                let flash = {
-                    match open_rl.editor.readline("Open:") {
-                        Ok(path) => app.open_file(path, layout).err().map(|err| err.to_string()),
-                        Err(_) => None,
+                KeyCode::Char('d') => {
+                    app.delete_focused(layout);
+                }
+                KeyCode::Char('D') => {
+                    app.duplicate_focused(layout);
+                }
+                KeyCode::Char('c') => {
+                    app.copy_focused_to_root(layout);
+                }
+                KeyCode::Char('O') => {
+                    terminal.draw(app.render(AppRenderMode::InputEditor))?;
+                    let key = sort_rl.readline_with_initial("Sort by:", ("", ""));
+                    let layout = redraw_after_prompt(&mut app, &mut terminal)?;
+                    if let Ok(key) = key {
+                        app.sort_focused(&key, layout);
                     }
-                };
-                if let Some(flash) = flash {
-                    app.set_flash(flash);
                 }
-                force_draw(&mut terminal, app.render(AppRenderMode::Normal))?;
-            }
-            KeyCode::Char('h') | KeyCode::Char('?') | KeyCode::F(1) => {
-                app.show_help();
-            }
-            _ => {}
-        }
-        let view_rect = match app.focus {
-            Focus::Left => layout.left,
-            Focus::Right => layout.right,
-        };
-        let mut view_with_parent = app.focused_view_mut();
-        let view_frame = view_with_parent.frame();
-        let json_rect = Block::default().borders(Borders::ALL).inner(view_rect);
-        match &mut view_frame.view {
-            View::Error(_) => {}
-            View::Json(None) => {}
-            View::Json(Some(view)) => {
-                view.resize_to(json_rect);
-                match c.code {
-                    KeyCode::Down => {
-                        view.advance_cursor();
+                KeyCode::Char('u') => {
+                    app.undo_focused(layout);
+                }
+                KeyCode::Char('r') if c.modifiers.contains(KeyModifiers::CONTROL) => {
+                    app.redo_focused(layout);
+                }
+                KeyCode::Char('r') => {
+                    terminal.draw(app.render(AppRenderMode::InputEditor))?;
+                    let mut view_with_parent = app.focused_view_mut();
+                    let frame = view_with_parent.frame();
+                    match rename_rl.readline_with_initial("New Title:", (&frame.name, "")) {
+                        Ok(new_name) => {
+                            frame.name = new_name;
+                        }
+                        Err(_) => {}
                     }
-                    KeyCode::Up => {
-                        view.regress_cursor();
+                    redraw_after_prompt(&mut app, &mut terminal)?;
+                }
+                KeyCode::Char('s') => {
+                    app.finish_query(layout);
+                    terminal.draw(app.render(AppRenderMode::InputEditor))?;
+                    let read_only = app.read_only;
+                    let mut view_with_parent = app.focused_view_mut();
+                    let frame = view_with_parent.frame();
+                    let initial = frame.save_path();
+                    let mut saved_folds = None;
+                    let flash = {
+                        if let View::Json(Some(view)) = &mut frame.view {
+                            match save_rl.readline_with_initial("Save to:", (&initial, "")) {
+                                Ok(path) if app::refuses_to_save_over(read_only, &path) => {
+                                    Some(format!("Read-only: not saving over {}", path))
+                                }
+                                Ok(path) => {
+                                    if let Err(err) = view.save_to(&path) {
+                                        Some(format!("Error saving json:\n{:?}", err))
+                                    } else {
+                                        view.modified = false;
+                                        if !view.folds.is_empty() {
+                                            saved_folds = Some(path.clone());
+                                        }
+                                        frame.source_path = Some(PathBuf::from(&path));
+                                        frame.name = path;
+                                        let focused_index = app.focused_index().clone();
+                                        app.re_root(&focused_index);
+                                        None
+                                    }
+                                }
+                                Err(_) => None,
+                            }
+                        } else {
+                            None
+                        }
+                    };
+                    if let Some(flash) = flash {
+                        app.set_flash(flash);
                     }
-                    KeyCode::PageDown => {
-                        view.page_down();
+                    if let Some(path) = saved_folds {
+                        offer_fold_sidecar(&mut app, &mut terminal, &path)?;
                     }
-                    KeyCode::PageUp => {
-                        view.page_up();
+                    redraw_after_prompt(&mut app, &mut terminal)?;
+                }
+                KeyCode::Char(c @ 'x') | KeyCode::Char(c @ 'X') => {
+                    let whole_document = c == 'X';
+                    if whole_document {
+                        app.finish_query(layout);
                     }
-                    KeyCode::Char('z') => {
-                        view.toggle_fold();
+                    let prompt = if whole_document {
+                        "Export all to:"
+                    } else {
+                        "Export to:"
+                    };
+                    terminal.draw(app.render(AppRenderMode::InputEditor))?;
+                    if let Ok(path) = export_rl.readline(prompt) {
+                        if app::refuses_to_save_over(app.read_only, &path) {
+                            app.set_flash(format!("Read-only: not exporting over {}", path));
+                        } else if let Err(err) = app.export_focused(&path, whole_document) {
+                            app.set_flash(format!("Error exporting text:\n{:?}", err));
+                        }
+                    }
+                    redraw_after_prompt(&mut app, &mut terminal)?;
+                }
+                KeyCode::Char('o') => {
+                    terminal.draw(app.render(AppRenderMode::InputEditor))?;
+                    let opened = open_rl.readline("Open:").ok().map(|path| {
+                        let name = rename_rl
+                            .readline_with_initial("Title:", (&path, ""))
+                            .unwrap_or_default();
+                        (path, name)
+                    });
+                    let layout = redraw_after_prompt(&mut app, &mut terminal)?;
+                    if let Some((path, name)) = opened {
+                        if let Err(err) = app.open_file(path, name, layout) {
+                            app.set_flash(err.to_string());
+                        }
                     }
-                    KeyCode::Char('/') => {
+                }
+                KeyCode::Char('a') => {
+                    if let Some(query) = app.focused_query().cloned() {
                         terminal.draw(app.render(AppRenderMode::InputEditor))?;
-                        match search_rl.editor.readline_with_initial("Search:", ("", "")) {
-                            Ok(new_search) => {
-                                // Just in case rustyline messed stuff up
-                                force_draw(&mut terminal, app.render(AppRenderMode::Normal))?;
-                                app.search_re = Regex::new(new_search.as_ref()).ok();
-                                app.search(false);
+                        if let Ok(name) = library_rl.readline("Save query as:") {
+                            library.insert(name, query);
+                            if let Err(err) = library.save(&library_path) {
+                                app.set_flash(format!("Error saving query library:\n{:?}", err));
                             }
-                            Err(_) => {}
                         }
+                        redraw_after_prompt(&mut app, &mut terminal)?;
+                    } else {
+                        app.set_status(ROOT_HAS_NO_QUERY);
                     }
-                    KeyCode::Char('n') => {
-                        app.search(false);
+                }
+                KeyCode::Char('l') if app.focused_query().is_none() => {
+                    app.set_status(ROOT_HAS_NO_QUERY);
+                }
+                KeyCode::Char('l') => {
+                    app.set_flash(library.listing());
+                    terminal.draw(app.render(AppRenderMode::InputEditor))?;
+                    let selection = library_rl.readline("Load query:");
+                    app.flash = None;
+                    let layout = redraw_after_prompt(&mut app, &mut terminal)?;
+                    if let Ok(selection) = selection {
+                        match library.get(&selection) {
+                            Some(new_query) => {
+                                *app.focused_query_mut().unwrap() = new_query.to_owned();
+                                app.recompute_focused_view(layout);
+                            }
+                            None => app.set_flash(format!("No saved query {:?}", selection)),
+                        }
                     }
-                    KeyCode::Char('N') => {
-                        app.search(true);
+                }
+                KeyCode::Char('|') if app.safe_mode => {
+                    app.set_flash(app::disabled_in_safe_mode("Piping to a command"));
+                }
+                KeyCode::Char('|') => {
+                    terminal.draw(app.render(AppRenderMode::InputEditor))?;
+                    let command = pipe_rl.readline("Pipe to:");
+                    let layout = redraw_after_prompt(&mut app, &mut terminal)?;
+                    if let Ok(command) = command {
+                        if let Err(err) = app.pipe_focused_value(command, layout) {
+                            app.set_flash(err.to_string());
+                        }
                     }
-                    KeyCode::Home => {
-                        view.scroll =
-                            GlobalCursor::new(view.values.clone(), view.rect.width, &view.folds)
-                                .expect("values should still exist");
-                        view.cursor = view.scroll.value_cursor.clone();
+                }
+                KeyCode::Char('S') => {
+                    app.open_transformed(
+                        "Schema of",
+                        |values| vec![schema::infer_schema(values)],
+                        layout,
+                    );
+                }
+                KeyCode::Char('F') => {
+                    app.open_transformed(
+                        "Flattened",
+                        |values| values.iter().map(|jv| flatten::flatten(jv, '.')).collect(),
+                        layout,
+                    );
+                }
+                KeyCode::Char('m') => {
+                    if let Some(mark) = read_char() {
+                        app.set_focused_mark(mark);
                     }
-                    KeyCode::End => {
-                        view.scroll = GlobalCursor::new_end(
-                            view.values.clone(),
-                            view.rect.width,
-                            &view.folds,
-                        )
-                        .expect("values should still exist");
-                        view.cursor = view.scroll.value_cursor.clone();
+                }
+                KeyCode::Char('\'') => {
+                    app.show_focused_marks();
+                    terminal.draw(app.render(AppRenderMode::Normal))?;
+                    let mark = read_char();
+                    app.flash = None;
+                    if let Some(mark) = mark {
+                        app.jump_focused_to_mark(mark);
                     }
-                    _ => {}
-                };
+                }
+                KeyCode::Char('=') => {
+                    app.show_focused_marks();
+                    terminal.draw(app.render(AppRenderMode::Normal))?;
+                    let marks = read_char().and_then(|a| Some((a, read_char()?)));
+                    app.flash = None;
+                    if let Some((a, b)) = marks {
+                        app.diff_focused_marks(a, b, layout);
+                    }
+                }
+                KeyCode::Char(':') => {
+                    terminal.draw(app.render(AppRenderMode::InputEditor))?;
+                    if let Ok(line) = line_rl.readline(":") {
+                        app.jump_focused_to_line(&line);
+                    }
+                    redraw_after_prompt(&mut app, &mut terminal)?;
+                }
+                KeyCode::Char('G') => {
+                    app.open_search_matches(layout);
+                }
+                KeyCode::Char('i') => {
+                    app.show_focused_info();
+                }
+                KeyCode::Char('V') => {
+                    terminal.draw(app.render(AppRenderMode::InputEditor))?;
+                    match schema_rl.readline("Validate against schema:") {
+                        Ok(schema_path) => validate_and_jump(
+                            &mut app,
+                            &mut terminal,
+                            &mut schema_errors_rl,
+                            &schema_path,
+                        )?,
+                        Err(_) => {
+                            redraw_after_prompt(&mut app, &mut terminal)?;
+                        }
+                    }
+                }
+                KeyCode::Char('p') => {
+                    app.diff_focused_against_clipboard(layout);
+                }
+                KeyCode::Char('h') | KeyCode::Char('?') | KeyCode::F(1) => {
+                    app.show_help();
+                }
+                _ => {
+                    embed::handle_app_key(&mut app, c.code);
+                }
+            }
+            // Prompts and toggles above may have changed the layout.
+            let layout = app.layout(terminal.get_frame().size());
+            let view_rect = match app.focus {
+                Focus::Left => layout.left,
+                Focus::Right => layout.right,
+            };
+            if !embed::handle_view_key(&mut app, c.code, view_rect, config.page_size) {
+                // The view keys that need a prompt.
+                let mut view_with_parent = app.focused_view_mut();
+                let view_frame = view_with_parent.frame();
+                if let View::Json(Some(view)) = &mut view_frame.view {
+                    match c.code {
+                        KeyCode::Char('Z') => {
+                            terminal.draw(app.render(AppRenderMode::InputEditor))?;
+                            let pattern = fold_rl.readline_with_initial("Fold matching:", ("", ""));
+                            if let Ok(pattern) = pattern {
+                                app.fold_focused_matching(&pattern);
+                            }
+                            redraw_after_prompt(&mut app, &mut terminal)?;
+                        }
+                        KeyCode::Char('A') => {
+                            let types = read_char().and_then(AbstractTypes::from_char);
+                            if let Some(types) = types {
+                                let abstracted = view.toggle_abstracted(types);
+                                app.set_status(match abstracted.describe() {
+                                    Some(types) => format!("Showing {} as placeholders", types),
+                                    None => "Showing all values".to_owned(),
+                                });
+                            }
+                        }
+                        KeyCode::Char('/') => {
+                            run_search(&mut app, &mut terminal, &mut search_rl)?;
+                        }
+                        _ => {}
+                    }
+                }
+            }
+            terminal.draw(app.render(AppRenderMode::Normal))?;
+            Ok(true)
+        });
+        match handled {
+            Ok(keep_going) => {
+                if !keep_going? {
+                    break;
+                }
+            }
+            Err(message) => {
+                warn!("Recovered from panic: {}", message);
+                app.set_flash(format!(
+                    "Jex hit an internal error:\n{}\n\n\
+                     Press Esc to go back to your files, then Esc again to quit if things look wrong.",
+                    message
+                ));
+                // If even drawing the error fails, the app is too broken to keep going.
+                catch_panic(|| force_draw(&mut terminal, app.render(AppRenderMode::Normal)))
+                    .map_err(|message| format!("Jex hit an internal error:\n{}", message))??;
             }
         }
-        terminal.draw(app.render(AppRenderMode::Normal))?;
     }
+    if save_session {
+        if let Err(err) = Session::new(&app).save(&session_path) {
+            warn!("Error saving session: {:?}", err);
+        }
+    }
+    if let Some(mut file_states) = app.file_states.take() {
+        file_states.remember(&app);
+        file_states.prune();
+        if let Err(err) = file_states.save(&files_path) {
+            warn!("Error saving remembered files: {:?}", err);
+        }
+    }
+    let exit_output = if print_on_exit {
+        app.finish_query(app.layout(terminal.get_frame().size()));
+        app.focused_plain_text(true)
+    } else {
+        None
+    };
     // Gracefully freeing the JV values can take a significant amount of time and doesn't actually
     // benefit anything: the OS will clean up after us when we exit.
     std::mem::forget(app);
+    // Printed after leaving the alternate screen, or it would vanish along with it.
+    drop(defer_restore);
+    if let Some(lines) = exit_output {
+        let mut stdout = io::stdout();
+        for line in lines {
+            writeln!(stdout, "{}", line)?;
+        }
+    }
     Ok(())
 }
 
 #[cfg(feature = "dev-tools")]
-fn bench(json_path: String) -> Result<(), io::Error> {
+// Prints how long each phase took as json, so runs can be compared across versions. With a query,
+// also how long it took to compile and run on the whole file.
+fn bench(
+    json_path: String,
+    query: Option<String>,
+    jq_options: JQOptions,
+) -> Result<(), Box<dyn Error>> {
     let mut profiler = PROFILER.lock().unwrap();
     profiler.start("profile").unwrap();
     let f = fs::File::open(&json_path)?;
@@ -509,16 +1676,37 @@ fn bench(json_path: String) -> Result<(), io::Error> {
             width: 100,
             height: 100,
         },
-        query: Rect {
+        breadcrumb: Rect {
             x: 0,
             y: 100,
             width: 100,
             height: 1,
         },
+        query: Rect {
+            x: 0,
+            y: 101,
+            width: 100,
+            height: 1,
+        },
         tree: None,
     };
-    let mut app = App::new(r, json_path, initial_layout)?;
+    let (app, mut timings) = App::new_timed(r, json_path, initial_layout)?;
+    let mut terminal = Terminal::new(TestBackend::new(200, 102))?;
+    let start = Instant::now();
+    terminal.draw(app.render(AppRenderMode::Normal))?;
+    timings.render_ms = millis(start.elapsed());
+    let mut report = serde_json::to_value(&timings)?;
+    if let Some(query) = query {
+        let values: &[JV] = match &app.views.trees[0].view_frame.view {
+            View::Json(Some(view)) => &view.values,
+            View::Json(None) | View::Error(_) => &[],
+        };
+        let query_timings = time_query(values, &query, &jq_options)
+            .map_err(|errors| QueryError(errors.join("\n")))?;
+        report["query"] = serde_json::to_value(&query_timings)?;
+    }
     std::mem::forget(app);
     profiler.stop().unwrap();
+    println!("{}", serde_json::to_string_pretty(&report)?);
     Ok(())
 }
@@ -0,0 +1,228 @@
+use crate::{cursor::PathElement, jq::jv::JV};
+use std::collections::HashSet;
+use tui::{
+    style::{Color, Modifier, Style},
+    text::{Span, Spans},
+    widgets::Paragraph,
+};
+
+// Past this many children, the rest of a container is one entry that jumps to the container.
+const CHILD_LIMIT: usize = 1000;
+
+// A table of contents for the focused view's document: its top level keys or indices, and the
+// children of any that have been expanded.
+#[derive(Debug, Clone, Default)]
+pub struct Outline {
+    pub selected: usize,
+    expanded: HashSet<(usize, Vec<PathElement>)>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct OutlineEntry {
+    pub top_index: usize,
+    pub path: Vec<PathElement>,
+    pub label: String,
+    pub depth: usize,
+    pub expandable: bool,
+}
+
+impl Outline {
+    // With more than one top level value, each is an entry of its own, named like `$1`.
+    pub fn entries(&self, values: &[JV]) -> Vec<OutlineEntry> {
+        let mut out = Vec::new();
+        match values {
+            [value] => self.push_children(0, value, &[], 0, &mut out),
+            _ => {
+                for (i, value) in values.iter().enumerate().take(CHILD_LIMIT) {
+                    let entry = OutlineEntry {
+                        top_index: i,
+                        path: Vec::new(),
+                        label: format!("${}", i),
+                        depth: 0,
+                        expandable: has_children(value),
+                    };
+                    self.push_entry(entry, value, &mut out);
+                }
+                if values.len() > CHILD_LIMIT {
+                    out.push(OutlineEntry {
+                        top_index: CHILD_LIMIT,
+                        path: Vec::new(),
+                        label: format!("… {} more", values.len() - CHILD_LIMIT),
+                        depth: 0,
+                        expandable: false,
+                    });
+                }
+            }
+        }
+        out
+    }
+    fn push_entry(&self, entry: OutlineEntry, value: &JV, out: &mut Vec<OutlineEntry>) {
+        let expanded = entry.expandable && self.is_expanded(&entry);
+        let (top_index, path, depth) = (entry.top_index, entry.path.clone(), entry.depth);
+        out.push(entry);
+        if expanded {
+            self.push_children(top_index, value, &path, depth + 1, out);
+        }
+    }
+    fn push_children(
+        &self,
+        top_index: usize,
+        value: &JV,
+        path: &[PathElement],
+        depth: usize,
+        out: &mut Vec<OutlineEntry>,
+    ) {
+        let (children, len): (Box<dyn Iterator<Item = (PathElement, JV)>>, i32) = match value {
+            JV::Object(obj) => (
+                Box::new(
+                    value
+                        .object_entries()
+                        .map(|(key, child)| (PathElement::Key(key), child)),
+                ),
+                obj.len(),
+            ),
+            JV::Array(arr) => (
+                Box::new(
+                    value
+                        .array_elements()
+                        .enumerate()
+                        .map(|(i, child)| (PathElement::Index(i), child)),
+                ),
+                arr.len(),
+            ),
+            _ => return,
+        };
+        for (element, child) in children.take(CHILD_LIMIT) {
+            let label = match &element {
+                PathElement::Key(key) => key.clone(),
+                PathElement::Index(i) => format!("[{}]", i),
+            };
+            let mut child_path = path.to_vec();
+            child_path.push(element);
+            let entry = OutlineEntry {
+                top_index,
+                path: child_path,
+                label,
+                depth,
+                expandable: has_children(&child),
+            };
+            self.push_entry(entry, &child, out);
+        }
+        let len = len as usize;
+        if len > CHILD_LIMIT {
+            out.push(OutlineEntry {
+                top_index,
+                path: path.to_vec(),
+                label: format!("… {} more", len - CHILD_LIMIT),
+                depth,
+                expandable: false,
+            });
+        }
+    }
+    fn is_expanded(&self, entry: &OutlineEntry) -> bool {
+        self.expanded
+            .contains(&(entry.top_index, entry.path.clone()))
+    }
+    // Returns false, doing nothing, if the entry has no children to show or hide.
+    pub fn set_expanded(&mut self, entry: &OutlineEntry, expanded: bool) -> bool {
+        if !entry.expandable {
+            return false;
+        }
+        let key = (entry.top_index, entry.path.clone());
+        if expanded {
+            self.expanded.insert(key)
+        } else {
+            self.expanded.remove(&key)
+        }
+    }
+    // Moves the selection by `delta` entries, stopping at either end.
+    pub fn move_selection(&mut self, delta: isize, len: usize) {
+        let selected = self.selected as isize + delta;
+        self.selected = selected.clamp(0, len.saturating_sub(1) as isize) as usize;
+    }
+    // Scrolled so the selection is on screen.
+    pub fn render(&self, entries: &[OutlineEntry], height: u16) -> Paragraph<'static> {
+        let selected = self.selected.min(entries.len().saturating_sub(1));
+        let lines: Vec<Spans> = entries
+            .iter()
+            .enumerate()
+            .map(|(i, entry)| {
+                let marker = match (entry.expandable, self.is_expanded(entry)) {
+                    (false, _) => ' ',
+                    (true, false) => '▸',
+                    (true, true) => '▾',
+                };
+                let text = format!("{}{} {}", "  ".repeat(entry.depth), marker, entry.label);
+                let style = if i == selected {
+                    Style::default().add_modifier(Modifier::REVERSED)
+                } else {
+                    Style::default()
+                };
+                Spans::from(Span::styled(text, style))
+            })
+            .collect();
+        let offset = (selected + 1).saturating_sub(height as usize);
+        Paragraph::new(lines)
+            .style(Style::default().fg(Color::White).bg(Color::Black))
+            .scroll((offset as u16, 0))
+    }
+}
+
+fn has_children(value: &JV) -> bool {
+    match value {
+        JV::Object(obj) => !obj.is_empty(),
+        JV::Array(arr) => !arr.is_empty(),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Outline, CHILD_LIMIT};
+    use crate::{cursor::PathElement, jq::jv::JV};
+    use serde_json::json;
+
+    fn labels(outline: &Outline, values: &[JV]) -> Vec<String> {
+        outline
+            .entries(values)
+            .into_iter()
+            .map(|entry| format!("{}{}", "  ".repeat(entry.depth), entry.label))
+            .collect()
+    }
+    #[test]
+    fn unit_outline_entries() {
+        let values: Vec<JV> = vec![(&json!({"a": {"b": 1, "c": []}, "d": [5, 6]})).into()];
+        let mut outline = Outline::default();
+        assert_eq!(labels(&outline, &values), vec!["a", "d"]);
+        let entries = outline.entries(&values);
+        assert!(outline.set_expanded(&entries[0], true));
+        assert_eq!(labels(&outline, &values), vec!["a", "  b", "  c", "d"]);
+        let entries = outline.entries(&values);
+        assert_eq!(
+            entries[1].path,
+            vec![
+                PathElement::Key("a".to_owned()),
+                PathElement::Key("b".to_owned())
+            ]
+        );
+        // Leaves and empty containers can't be expanded.
+        assert!(!outline.set_expanded(&entries[1], true));
+        assert!(!outline.set_expanded(&entries[2], true));
+        assert!(outline.set_expanded(&entries[3], true));
+        assert_eq!(
+            labels(&outline, &values),
+            vec!["a", "  b", "  c", "d", "  [0]", "  [1]"]
+        );
+    }
+    #[test]
+    fn unit_outline_many() {
+        let values: Vec<JV> = vec![(&json!(1)).into(), (&json!([1])).into()];
+        let outline = Outline::default();
+        assert_eq!(labels(&outline, &values), vec!["$0", "$1"]);
+        let long: Vec<JV> = vec![(&json!(vec![0; CHILD_LIMIT + 5])).into()];
+        let entries = outline.entries(&long);
+        assert_eq!(entries.len(), CHILD_LIMIT + 1);
+        assert_eq!(entries[CHILD_LIMIT].label, "… 5 more");
+        assert!(entries[CHILD_LIMIT].path.is_empty());
+    }
+}
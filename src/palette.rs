@@ -0,0 +1,239 @@
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+// The most commands listed at once. The rest are reached by moving the selection or filtering.
+const SHOWN: usize = 15;
+
+// A command the palette can run, taken from a line of the help text.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PaletteCommand {
+    // The key as the help text gives it, like `Ctrl-r` or `A, then s, n, b or a`.
+    pub binding: String,
+    pub description: String,
+    // What running it presses.
+    pub key: KeyEvent,
+}
+
+// Reads the commands out of help text with a `- key: what it does` line for each. Lines whose key
+// isn't one key press, and the palette's own line, are left out.
+pub fn commands(help: &str) -> Vec<PaletteCommand> {
+    help.lines()
+        .filter_map(|line| {
+            let (binding, description) = line.strip_prefix("- ")?.split_once(": ")?;
+            let key = parse_key(binding)?;
+            if key == palette_key() {
+                return None;
+            }
+            // Just the first sentence, to keep the list to a line each.
+            let description = description.split(". ").next().unwrap_or(description);
+            Some(PaletteCommand {
+                binding: binding.to_owned(),
+                description: description.trim_end_matches('.').to_owned(),
+                key,
+            })
+        })
+        .collect()
+}
+
+// The key that opens the palette.
+pub fn palette_key() -> KeyEvent {
+    KeyEvent::new(KeyCode::Char('p'), KeyModifiers::CONTROL)
+}
+
+// Bindings like `h,? or F1` and `Up/down` give several keys: the first is used.
+fn parse_key(binding: &str) -> Option<KeyEvent> {
+    let first = if binding.chars().count() == 1 {
+        binding
+    } else {
+        binding.split(&[',', '/', ' '][..]).next()?
+    };
+    let code = match first {
+        "Tab" => KeyCode::Tab,
+        "Home" => KeyCode::Home,
+        "End" => KeyCode::End,
+        "Esc" => KeyCode::Esc,
+        "Enter" => KeyCode::Enter,
+        "Up" => KeyCode::Up,
+        "Down" => KeyCode::Down,
+        "F1" => KeyCode::F(1),
+        _ => {
+            let (modifiers, key) = match first.strip_prefix("Ctrl-") {
+                Some(key) => (KeyModifiers::CONTROL, key),
+                None => (KeyModifiers::NONE, first),
+            };
+            let mut chars = key.chars();
+            return match (chars.next(), chars.next()) {
+                (Some(c), None) => Some(KeyEvent::new(KeyCode::Char(c), modifiers)),
+                _ => None,
+            };
+        }
+    };
+    Some(KeyEvent::new(code, KeyModifiers::NONE))
+}
+
+// How well `pattern` matches `text`, if its characters all appear in order, ignoring case. Runs of
+// consecutive characters and characters starting words score higher.
+pub fn fuzzy_score(pattern: &str, text: &str) -> Option<usize> {
+    let text: Vec<char> = text.chars().flat_map(char::to_lowercase).collect();
+    let mut score = 0;
+    let mut next = 0;
+    let mut previous: Option<usize> = None;
+    for c in pattern.chars().flat_map(char::to_lowercase) {
+        let found = next + text[next..].iter().position(|&t| t == c)?;
+        score += 1;
+        if previous.is_some_and(|previous| previous + 1 == found) {
+            score += 5;
+        }
+        if found == 0 || !text[found - 1].is_alphanumeric() {
+            score += 3;
+        }
+        previous = Some(found);
+        next = found + 1;
+    }
+    Some(score)
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum PaletteAction {
+    // Keep showing the palette.
+    Continue,
+    Close,
+    // Close the palette and press this key.
+    Run(KeyEvent),
+}
+
+pub struct Palette {
+    commands: Vec<PaletteCommand>,
+    pub input: String,
+    // An index into `matches()`.
+    selected: usize,
+}
+
+impl Palette {
+    pub fn new(help: &str) -> Self {
+        Palette {
+            commands: commands(help),
+            input: String::new(),
+            selected: 0,
+        }
+    }
+    // The commands matching the input, best first. Ties keep the help's order.
+    pub fn matches(&self) -> Vec<&PaletteCommand> {
+        let mut matches: Vec<(usize, &PaletteCommand)> = self
+            .commands
+            .iter()
+            .filter_map(|command| {
+                let text = format!("{} {}", command.binding, command.description);
+                fuzzy_score(&self.input, &text).map(|score| (score, command))
+            })
+            .collect();
+        matches.sort_by(|(a, _), (b, _)| b.cmp(a));
+        matches.into_iter().map(|(_, command)| command).collect()
+    }
+    pub fn handle_key(&mut self, key: KeyEvent) -> PaletteAction {
+        match key.code {
+            KeyCode::Esc => return PaletteAction::Close,
+            _ if key == palette_key() => return PaletteAction::Close,
+            KeyCode::Enter => {
+                return match self.matches().get(self.selected) {
+                    Some(command) => PaletteAction::Run(command.key),
+                    None => PaletteAction::Close,
+                }
+            }
+            KeyCode::Up => self.selected = self.selected.saturating_sub(1),
+            KeyCode::Down => {
+                let last = self.matches().len().saturating_sub(1);
+                self.selected = std::cmp::min(self.selected + 1, last);
+            }
+            KeyCode::Backspace => {
+                self.input.pop();
+                self.selected = 0;
+            }
+            KeyCode::Char(c) => {
+                self.input.push(c);
+                self.selected = 0;
+            }
+            _ => {}
+        }
+        PaletteAction::Continue
+    }
+    // The palette as shown in a popup: the input, then the matching commands with their keys.
+    pub fn text(&self) -> String {
+        let matches = self.matches();
+        let mut lines = vec![
+            "Type to find a command, Up/Down to choose, Enter to run it, Esc to cancel".to_owned(),
+            format!("> {}", self.input),
+            String::new(),
+        ];
+        if matches.is_empty() {
+            lines.push("No matching commands".to_owned());
+        }
+        let start = (self.selected + 1).saturating_sub(SHOWN);
+        for (i, command) in matches.iter().enumerate().skip(start).take(SHOWN) {
+            let marker = if i == self.selected { "▶" } else { " " };
+            lines.push(format!(
+                "{} {}  ({})",
+                marker, command.description, command.binding
+            ));
+        }
+        lines.join("\n")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{commands, fuzzy_score, Palette, PaletteAction};
+    use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+    const HELP: &str = "- Up/down: Scroll through the current pane
+- z: Fold the object or array under the cursor
+- :: Jump to a line number. Folded values count as one line.
+- Ctrl-r: Redo the last undone edit
+- Ctrl-p: Open the command palette
+- h,? or F1: Show this help text
+- A, then s or n: Toggle placeholders";
+
+    fn key(code: KeyCode) -> KeyEvent {
+        KeyEvent::new(code, KeyModifiers::NONE)
+    }
+    #[test]
+    fn unit_palette_commands() {
+        let commands = commands(HELP);
+        let keys: Vec<KeyEvent> = commands.iter().map(|command| command.key).collect();
+        assert_eq!(
+            keys,
+            vec![
+                key(KeyCode::Up),
+                key(KeyCode::Char('z')),
+                key(KeyCode::Char(':')),
+                KeyEvent::new(KeyCode::Char('r'), KeyModifiers::CONTROL),
+                key(KeyCode::Char('h')),
+                key(KeyCode::Char('A')),
+            ]
+        );
+        assert_eq!(commands[2].description, "Jump to a line number");
+    }
+    #[test]
+    fn unit_palette_filter() {
+        assert!(fuzzy_score("fld", "Fold the object").is_some());
+        assert!(fuzzy_score("dlf", "Fold the object").is_none());
+        // Word starts and runs beat scattered letters.
+        assert!(fuzzy_score("redo", "Redo the edit") > fuzzy_score("redo", "rename the document"));
+        let mut palette = Palette::new(HELP);
+        for c in "redo".chars() {
+            assert_eq!(
+                palette.handle_key(key(KeyCode::Char(c))),
+                PaletteAction::Continue
+            );
+        }
+        assert_eq!(palette.matches()[0].binding, "Ctrl-r");
+        palette.handle_key(key(KeyCode::Backspace));
+        palette.handle_key(key(KeyCode::Backspace));
+        palette.handle_key(key(KeyCode::Backspace));
+        palette.handle_key(key(KeyCode::Down));
+        assert_eq!(
+            palette.handle_key(key(KeyCode::Enter)),
+            PaletteAction::Run(palette.matches()[1].key)
+        );
+        assert_eq!(palette.handle_key(key(KeyCode::Esc)), PaletteAction::Close);
+    }
+}
@@ -0,0 +1,157 @@
+use crate::{cursor::PathElement, jq::jv::JV};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Segment {
+    Key(String),
+    Index(usize),
+    // `*`: any one key, or `[*]`: any one index.
+    AnyKey,
+    AnyIndex,
+    // `**`: any number of keys and indices, including none.
+    AnyPath,
+}
+
+// A pattern for paths like `.items[*].metadata` or `**.metadata`, matched against whole paths
+// from the root.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PathGlob(Vec<Segment>);
+
+impl PathGlob {
+    pub fn parse(pattern: &str) -> Result<Self, String> {
+        let pattern = pattern.trim();
+        let mut segments = Vec::new();
+        let mut rest = pattern.strip_prefix('.').unwrap_or(pattern);
+        while !rest.is_empty() {
+            if let Some(bracketed) = rest.strip_prefix('[') {
+                let close = bracketed
+                    .find(']')
+                    .ok_or_else(|| format!("Unclosed `[` in {}", pattern))?;
+                segments.push(match &bracketed[..close] {
+                    "*" => Segment::AnyIndex,
+                    index => Segment::Index(
+                        index
+                            .parse()
+                            .map_err(|_| format!("Invalid index `{}` in {}", index, pattern))?,
+                    ),
+                });
+                rest = &bracketed[close + 1..];
+            } else {
+                let end = rest.find(['.', '[']).unwrap_or(rest.len());
+                segments.push(match &rest[..end] {
+                    "" => return Err(format!("Empty key in {}", pattern)),
+                    "*" => Segment::AnyKey,
+                    "**" => Segment::AnyPath,
+                    key => Segment::Key(key.to_owned()),
+                });
+                rest = &rest[end..];
+            }
+            rest = rest.strip_prefix('.').unwrap_or(rest);
+        }
+        if segments.is_empty() {
+            return Err("Empty pattern".to_owned());
+        }
+        Ok(PathGlob(segments))
+    }
+    pub fn matches(&self, path: &[PathElement]) -> bool {
+        matches_from(&self.0, path)
+    }
+    // The paths of every array and object in `values` that match.
+    pub fn find_containers(&self, values: &[JV]) -> Vec<(usize, Vec<PathElement>)> {
+        let mut found = Vec::new();
+        for (top_index, value) in values.iter().enumerate() {
+            self.find_containers_in(value, top_index, &mut Vec::new(), &mut found);
+        }
+        found
+    }
+    fn find_containers_in(
+        &self,
+        jv: &JV,
+        top_index: usize,
+        path: &mut Vec<PathElement>,
+        found: &mut Vec<(usize, Vec<PathElement>)>,
+    ) {
+        let children: Vec<(PathElement, JV)> = match jv {
+            JV::Array(arr) => arr
+                .iter()
+                .enumerate()
+                .map(|(i, child)| (PathElement::Index(i), child))
+                .collect(),
+            JV::Object(obj) => obj
+                .iter()
+                .map(|(key, child)| (PathElement::Key(key.to_owned()), child))
+                .collect(),
+            _ => return,
+        };
+        if self.matches(path) {
+            found.push((top_index, path.clone()));
+        }
+        for (element, child) in children {
+            path.push(element);
+            self.find_containers_in(&child, top_index, path, found);
+            path.pop();
+        }
+    }
+}
+
+fn matches_from(segments: &[Segment], path: &[PathElement]) -> bool {
+    let (segment, rest) = match segments.split_first() {
+        None => return path.is_empty(),
+        Some(split) => split,
+    };
+    if *segment == Segment::AnyPath {
+        return (0..=path.len()).any(|skip| matches_from(rest, &path[skip..]));
+    }
+    let (element, path_rest) = match path.split_first() {
+        None => return false,
+        Some(split) => split,
+    };
+    let segment_matches = match (segment, element) {
+        (Segment::Key(expected), PathElement::Key(key)) => expected == key,
+        (Segment::Index(expected), PathElement::Index(i)) => expected == i,
+        (Segment::AnyKey, PathElement::Key(_)) | (Segment::AnyIndex, PathElement::Index(_)) => true,
+        _ => false,
+    };
+    segment_matches && matches_from(rest, path_rest)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::PathGlob;
+    use crate::{cursor::PathElement, jq::jv::JV};
+    use serde_json::json;
+    fn key(k: &str) -> PathElement {
+        PathElement::Key(k.to_owned())
+    }
+    #[test]
+    fn unit_matches() {
+        let glob = PathGlob::parse(".items[*].metadata").unwrap();
+        assert!(glob.matches(&[key("items"), PathElement::Index(3), key("metadata")]));
+        assert!(!glob.matches(&[key("items"), key("x"), key("metadata")]));
+        assert!(!glob.matches(&[key("items"), PathElement::Index(3)]));
+        let glob = PathGlob::parse("**.metadata").unwrap();
+        assert!(glob.matches(&[key("metadata")]));
+        assert!(glob.matches(&[key("a"), PathElement::Index(0), key("metadata")]));
+        assert!(!glob.matches(&[key("metadata"), key("a")]));
+        assert!(PathGlob::parse("*.a[1]").unwrap().matches(&[
+            key("x"),
+            key("a"),
+            PathElement::Index(1)
+        ]));
+        assert!(PathGlob::parse("").is_err());
+        assert!(PathGlob::parse(".a[x]").is_err());
+        assert!(PathGlob::parse(".a..b").is_err());
+    }
+    #[test]
+    fn unit_find_containers() {
+        let values: Vec<JV> = vec![
+            (&json!({"metadata": {}, "a": {"metadata": [1], "b": {"metadata": 2}}})).into(),
+            (&json!([{"metadata": null}])).into(),
+        ];
+        let glob = PathGlob::parse("**.metadata").unwrap();
+        // Scalars can't be folded, so they aren't found.
+        let found = glob.find_containers(&values);
+        assert_eq!(found.len(), 2);
+        assert!(found.contains(&(0, vec![key("metadata")])));
+        assert!(found.contains(&(0, vec![key("a"), key("metadata")])));
+    }
+}
@@ -0,0 +1,79 @@
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeMap, fs, io, path::Path};
+
+// Named jq queries, persisted as a json object of name -> query.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct QueryLibrary {
+    pub queries: BTreeMap<String, String>,
+}
+
+impl QueryLibrary {
+    pub fn load(path: &Path) -> io::Result<Self> {
+        match fs::File::open(path) {
+            Ok(f) => Ok(serde_json::from_reader(io::BufReader::new(f))?),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(QueryLibrary::default()),
+            Err(err) => Err(err),
+        }
+    }
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let f = fs::File::create(path)?;
+        serde_json::to_writer_pretty(f, self)?;
+        Ok(())
+    }
+    pub fn insert(&mut self, name: String, query: String) {
+        self.queries.insert(name, query);
+    }
+    // Looks a query up by name, or by its 1-based position in the listing.
+    pub fn get(&self, selection: &str) -> Option<&str> {
+        let selection = selection.trim();
+        if let Some(query) = self.queries.get(selection) {
+            return Some(query);
+        }
+        let i: usize = selection.parse().ok()?;
+        self.queries
+            .values()
+            .nth(i.checked_sub(1)?)
+            .map(String::as_str)
+    }
+    pub fn listing(&self) -> String {
+        if self.queries.is_empty() {
+            return "No saved queries".to_owned();
+        }
+        self.queries
+            .iter()
+            .enumerate()
+            .map(|(i, (name, query))| format!("{}. {}: {}", i + 1, name, query))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::QueryLibrary;
+    #[test]
+    fn unit_library_get() {
+        let mut library = QueryLibrary::default();
+        library.insert("keys".to_owned(), "keys".to_owned());
+        library.insert("first".to_owned(), ".[0]".to_owned());
+        assert_eq!(library.get("keys"), Some("keys"));
+        assert_eq!(library.get("1"), Some(".[0]"));
+        assert_eq!(library.get(" 2 "), Some("keys"));
+        assert_eq!(library.get("3"), None);
+        assert_eq!(library.get("0"), None);
+        assert_eq!(library.listing(), "1. first: .[0]\n2. keys: keys");
+    }
+    #[test]
+    fn unit_library_roundtrip() {
+        let path = std::env::temp_dir().join("jex_unit_library_roundtrip.json");
+        let mut library = QueryLibrary::default();
+        library.insert("keys".to_owned(), "keys".to_owned());
+        library.save(&path).unwrap();
+        assert_eq!(QueryLibrary::load(&path).unwrap(), library);
+        std::fs::remove_file(&path).unwrap();
+    }
+}
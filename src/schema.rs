@@ -0,0 +1,228 @@
+use crate::{cursor::PathElement, inspect::type_name, jq::jv::JV};
+use jsonschema::{error::ValidationErrorKind, paths::PathChunk, Draft, JSONSchema};
+use serde_json::{json, map::Map, value::Value};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt,
+};
+
+// Accumulates everything seen at one position in the document. Objects track the keys present in
+// every instance (to become `required`), and array items are merged into a single shape.
+#[derive(Debug, Default)]
+struct Shape {
+    types: BTreeSet<&'static str>,
+    properties: BTreeMap<String, Shape>,
+    required: Option<BTreeSet<String>>,
+    items: Option<Box<Shape>>,
+}
+
+impl Shape {
+    fn add(&mut self, jv: &JV) {
+        self.types.insert(type_name(jv));
+        match jv {
+            JV::Object(obj) => {
+                let mut keys = BTreeSet::new();
+                for (k, v) in obj.iter() {
+                    keys.insert(k.to_owned());
+                    self.properties.entry(k.to_owned()).or_default().add(&v);
+                }
+                self.required = Some(match self.required.take() {
+                    None => keys,
+                    Some(required) => required.intersection(&keys).cloned().collect(),
+                });
+            }
+            JV::Array(arr) => {
+                let items = self.items.get_or_insert_with(Default::default);
+                for child in arr.iter() {
+                    items.add(&child);
+                }
+            }
+            _ => {}
+        }
+    }
+    fn to_schema(&self) -> Map<String, Value> {
+        let mut schema = Map::new();
+        let types: Vec<&str> = self.types.iter().copied().collect();
+        match types.as_slice() {
+            [] => {}
+            [single] => {
+                schema.insert("type".to_owned(), json!(single));
+            }
+            _ => {
+                schema.insert("type".to_owned(), json!(types));
+            }
+        }
+        if self.types.contains("object") {
+            let properties: Map<String, Value> = self
+                .properties
+                .iter()
+                .map(|(k, shape)| (k.clone(), Value::Object(shape.to_schema())))
+                .collect();
+            schema.insert("properties".to_owned(), Value::Object(properties));
+            if let Some(required) = &self.required {
+                if !required.is_empty() {
+                    schema.insert("required".to_owned(), json!(required));
+                }
+            }
+        }
+        if let Some(items) = &self.items {
+            if !items.types.is_empty() {
+                schema.insert("items".to_owned(), Value::Object(items.to_schema()));
+            }
+        }
+        schema
+    }
+}
+
+// Infers a draft-07 schema that every one of `values` satisfies.
+pub fn infer_schema(values: &[JV]) -> JV {
+    let mut shape = Shape::default();
+    for value in values {
+        shape.add(value);
+    }
+    let mut schema = Map::new();
+    schema.insert(
+        "$schema".to_owned(),
+        json!("http://json-schema.org/draft-07/schema#"),
+    );
+    schema.extend(shape.to_schema());
+    (&Value::Object(schema)).into()
+}
+
+// A place in the document that didn't satisfy the schema.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ValidationError {
+    pub top_index: usize,
+    pub path: Vec<PathElement>,
+    pub message: String,
+}
+
+impl fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "${}", self.top_index)?;
+        for element in self.path.iter() {
+            write!(f, "{}", element)?;
+        }
+        write!(f, ": {}", self.message)
+    }
+}
+
+// Checks each of `values` against a draft-07 schema. Errors in the schema itself, like a `$ref`
+// that can't be resolved, come back as `Err`.
+pub fn validate(schema: &Value, values: &[JV]) -> Result<Vec<ValidationError>, String> {
+    let compiled = JSONSchema::options()
+        .with_draft(Draft::Draft7)
+        .compile(schema)
+        .map_err(|err| err.to_string())?;
+    let mut errors = Vec::new();
+    for (top_index, value) in values.iter().enumerate() {
+        let instance: Value = value.into();
+        if let Err(found) = compiled.validate(&instance) {
+            for err in found {
+                if let ValidationErrorKind::Resolver { .. } = err.kind {
+                    return Err(err.to_string());
+                }
+                errors.push(ValidationError {
+                    top_index,
+                    path: err.instance_path.iter().filter_map(path_element).collect(),
+                    message: err.to_string(),
+                });
+            }
+        }
+    }
+    Ok(errors)
+}
+
+fn path_element(chunk: &PathChunk) -> Option<PathElement> {
+    match chunk {
+        PathChunk::Property(key) => Some(PathElement::Key(key.to_string())),
+        PathChunk::Index(i) => Some(PathElement::Index(*i)),
+        PathChunk::Keyword(_) => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{infer_schema, validate};
+    use crate::{cursor::PathElement, jq::jv::JV};
+    use serde_json::{json, value::Value};
+    #[test]
+    fn unit_infer_schema() {
+        let values: Vec<JV> = vec![
+            (&json!({"id": 1, "tags": ["a"], "name": "x"})).into(),
+            (&json!({"id": 2, "tags": [], "parent": null})).into(),
+        ];
+        let schema: Value = (&infer_schema(&values)).into();
+        let expected = json!({
+            "$schema": "http://json-schema.org/draft-07/schema#",
+            "type": "object",
+            "properties": {
+                "id": {"type": "number"},
+                "name": {"type": "string"},
+                "parent": {"type": "null"},
+                "tags": {"type": "array", "items": {"type": "string"}},
+            },
+            "required": ["id", "tags"],
+        });
+        assert_eq!(schema, expected);
+    }
+    #[test]
+    fn unit_infer_schema_mixed() {
+        let values: Vec<JV> = vec![(&json!([1, "a", [true]])).into()];
+        let schema: Value = (&infer_schema(&values)).into();
+        let expected = json!({
+            "$schema": "http://json-schema.org/draft-07/schema#",
+            "type": "array",
+            "items": {
+                "type": ["array", "number", "string"],
+                "items": {"type": "boolean"},
+            },
+        });
+        assert_eq!(schema, expected);
+    }
+    #[test]
+    fn unit_validate() {
+        let schema = json!({
+            "definitions": {"id": {"type": "integer", "minimum": 1}},
+            "type": "object",
+            "required": ["id", "name"],
+            "properties": {
+                "id": {"$ref": "#/definitions/id"},
+                "tags": {"type": "array", "items": {"enum": ["a", "b"]}},
+            },
+            "additionalProperties": {"type": "string"},
+        });
+        let values: Vec<JV> = vec![
+            (&json!({"id": 1, "name": "x", "tags": ["a"]})).into(),
+            (&json!({"id": 0, "tags": ["a", "c"], "other": 2})).into(),
+        ];
+        let mut errors: Vec<(usize, Vec<PathElement>)> = validate(&schema, &values)
+            .unwrap()
+            .into_iter()
+            .map(|err| (err.top_index, err.path))
+            .collect();
+        // The order jsonschema reports them in isn't specified.
+        errors.sort_by_key(|(_, path)| (path.len(), format!("{:?}", path)));
+        assert_eq!(
+            errors,
+            vec![
+                (1, vec![]),
+                (1, vec![PathElement::Key("id".to_owned())]),
+                (1, vec![PathElement::Key("other".to_owned())]),
+                (
+                    1,
+                    vec![PathElement::Key("tags".to_owned()), PathElement::Index(1)]
+                ),
+            ]
+        );
+    }
+    #[test]
+    fn unit_validate_schema_errors() {
+        let values: Vec<JV> = vec![(&json!({"a": "x"})).into()];
+        let schema = json!({"properties": {"a": {"format": "email"}}});
+        assert_eq!(validate(&schema, &values).unwrap().len(), 1);
+        let schema = json!({"properties": {"a": {"$ref": "#/definitions/missing"}}});
+        assert!(validate(&schema, &values).is_err());
+        assert!(validate(&json!({"type": 1}), &values).is_err());
+    }
+}
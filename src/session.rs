@@ -0,0 +1,489 @@
+use crate::{
+    app::{App, Focus},
+    cursor::PathElement,
+    formats::{self, InputFormat},
+    jq::query::JQOptions,
+    layout::JexLayout,
+    view_tree::{JsonView, NamedView, View, ViewForestIndex, ViewTree, ViewTreeIndex},
+};
+use log::warn;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, HashSet},
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+// Everything needed to rebuild the view forest: root views are reloaded from the files they were
+// opened from, and their descendents are recomputed by re-running the queries. Roots that didn't
+// come from a file, like --exec output or copies, aren't kept.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Session {
+    pub trees: Vec<TreeSession>,
+    pub left_index: (usize, Vec<usize>),
+    pub right_index: (usize, Vec<usize>),
+    pub focus: Focus,
+    // For ., so it picks up where it left off. Sessions saved before it was added don't have one.
+    #[serde(default)]
+    pub last_query: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TreeSession {
+    pub name: String,
+    // The file a root is reloaded from. It's always set now, but sessions saved before it was
+    // recorded only have the name, which was the path then.
+    #[serde(default)]
+    pub source_path: Option<PathBuf>,
+    pub folds: HashSet<(usize, Vec<usize>)>,
+    // (query, tree)
+    pub children: Vec<(String, TreeSession)>,
+}
+
+impl TreeSession {
+    fn new(tree: &ViewTree) -> Self {
+        let folds = match &tree.view_frame.view {
+            View::Json(Some(view)) => view.folds.clone(),
+            View::Json(None) | View::Error(_) => HashSet::new(),
+        };
+        TreeSession {
+            name: tree.view_frame.name.clone(),
+            source_path: tree.view_frame.source_path.clone(),
+            folds,
+            children: tree
+                .children
+                .iter()
+                .map(|(query, child)| (query.clone(), TreeSession::new(child)))
+                .collect(),
+        }
+    }
+    // Where a root is reloaded from. Only a session from before source_path has no source_path.
+    pub fn source(&self) -> PathBuf {
+        match &self.source_path {
+            Some(path) => path.clone(),
+            None => PathBuf::from(&self.name),
+        }
+    }
+    fn restore_children(self, tree: &mut ViewTree, jq_options: &JQOptions, layout: JexLayout) {
+        if let View::Json(Some(view)) = &mut tree.view_frame.view {
+            view.folds = self.folds;
+        }
+        restore_queries(self.children, tree, jq_options, layout);
+    }
+}
+
+// Replaces the children of `tree` with the results of the queries in `children`, and theirs in
+// turn.
+fn restore_queries(
+    children: Vec<(String, TreeSession)>,
+    tree: &mut ViewTree,
+    jq_options: &JQOptions,
+    layout: JexLayout,
+) {
+    tree.children.clear();
+    for (query, child_session) in children {
+        let view = match &tree.view_frame.view {
+            View::Json(Some(parent)) => parent.apply_query(&query, jq_options, layout.right),
+            View::Json(None) | View::Error(_) => View::Json(None),
+        };
+        let mut child = ViewTree {
+            view_frame: NamedView {
+                view,
+                name: child_session.name.clone(),
+                source_path: None,
+            },
+            children: Vec::new(),
+        };
+        child_session.restore_children(&mut child, jq_options, layout);
+        tree.children.push((query, child));
+    }
+}
+
+impl Session {
+    pub fn new(app: &App) -> Self {
+        let kept: Vec<usize> = (0..app.views.trees.len())
+            .filter(|&i| app.views.trees[i].view_frame.source_path.is_some())
+            .collect();
+        // A pane showing a tree that isn't kept goes back to the first one.
+        let index = |index: &ViewForestIndex, default: Vec<usize>| {
+            let tree = kept.iter().position(|&i| i == index.tree);
+            match tree {
+                Some(tree) => (tree, index.within_tree.path.clone()),
+                None => (0, default),
+            }
+        };
+        Session {
+            trees: kept
+                .iter()
+                .map(|&i| TreeSession::new(&app.views.trees[i]))
+                .collect(),
+            left_index: index(&app.left_index, Vec::new()),
+            right_index: index(&app.right_index, vec![0]),
+            focus: app.focus,
+            last_query: app.last_query.clone(),
+        }
+    }
+    pub fn load(path: &Path) -> io::Result<Self> {
+        let f = fs::File::open(path)?;
+        let session = serde_json::from_reader(io::BufReader::new(f))?;
+        Ok(session)
+    }
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let f = fs::File::create(path)?;
+        serde_json::to_writer(f, self)?;
+        Ok(())
+    }
+    // Trees whose files can no longer be loaded are dropped. If that invalidates the pane indices,
+    // they're reset to the first tree.
+    pub fn restore(
+        self,
+        jq_options: JQOptions,
+        lossy_utf8: bool,
+        layout: JexLayout,
+    ) -> io::Result<App> {
+        let mut trees = Vec::new();
+        let mut tree_ix_map = Vec::new();
+        for tree_session in self.trees {
+            let path = tree_session.source();
+            let loaded = formats::open_input(&path, lossy_utf8).and_then(|r| {
+                let format = InputFormat::from_path(&path);
+                ViewTree::new_from_reader(r, format, tree_session.name.clone(), layout)
+            });
+            match loaded {
+                Ok(mut tree) => {
+                    tree.view_frame.source_path = Some(path);
+                    tree_ix_map.push(Some(trees.len()));
+                    tree_session.restore_children(&mut tree, &jq_options, layout);
+                    trees.push(tree);
+                }
+                Err(err) => {
+                    warn!("Couldn't restore {}: {:?}", path.display(), err);
+                    tree_ix_map.push(None);
+                }
+            }
+        }
+        let mut trees = trees.into_iter();
+        let mut app = match trees.next() {
+            Some(first) => App::from_tree(first),
+            None => {
+                return Err(io::Error::new(
+                    io::ErrorKind::NotFound,
+                    "No files from the last session could be loaded",
+                ))
+            }
+        };
+        app.views.trees.extend(trees);
+        let views = &app.views;
+        let restore_index = |(tree, path): (usize, Vec<usize>), default: Vec<usize>| {
+            let index = tree_ix_map
+                .get(tree)
+                .copied()
+                .flatten()
+                .map(|tree| ViewForestIndex {
+                    tree,
+                    within_tree: ViewTreeIndex { path },
+                });
+            match index {
+                Some(index) if views.index(&index).is_some() => index,
+                _ => ViewForestIndex {
+                    tree: 0,
+                    within_tree: ViewTreeIndex { path: default },
+                },
+            }
+        };
+        let left_index = restore_index(self.left_index, Vec::new());
+        let mut right_index = restore_index(self.right_index, vec![0]);
+        if views.index(&right_index).is_none() {
+            right_index.within_tree.path.clear();
+        }
+        app.left_index = left_index;
+        app.right_index = right_index;
+        app.focus = self.focus;
+        app.jq_options = jq_options;
+        app.lossy_utf8 = lossy_utf8;
+        app.last_query = self.last_query;
+        Ok(app)
+    }
+}
+
+// What's remembered of each file between runs, so opening it again picks up where it was left.
+// Files are keyed by their canonical path, so it doesn't matter how they're named when opened.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct FileStates {
+    pub files: BTreeMap<String, FileState>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FileState {
+    // The folds and cursor of the file's own view, by key rather than by position, since the file
+    // may have changed since.
+    pub folds: Vec<(usize, Vec<PathElement>)>,
+    pub cursor: (usize, Vec<PathElement>),
+    // (query, tree)
+    pub children: Vec<(String, TreeSession)>,
+}
+
+fn file_key<P: AsRef<Path>>(path: P) -> Option<String> {
+    let path = fs::canonicalize(path).ok()?;
+    Some(path.to_string_lossy().into_owned())
+}
+
+impl FileStates {
+    pub fn load(path: &Path) -> io::Result<Self> {
+        match fs::File::open(path) {
+            Ok(f) => Ok(serde_json::from_reader(io::BufReader::new(f))?),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(FileStates::default()),
+            Err(err) => Err(err),
+        }
+    }
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let f = fs::File::create(path)?;
+        serde_json::to_writer(f, self)?;
+        Ok(())
+    }
+    // Records the state of every open tree that's a file, replacing what was remembered of it.
+    pub fn remember(&mut self, app: &App) {
+        for tree in app.views.trees.iter() {
+            let view = match &tree.view_frame.view {
+                View::Json(Some(view)) => view,
+                View::Json(None) | View::Error(_) => continue,
+            };
+            let key = match tree.view_frame.source_path.as_ref().and_then(file_key) {
+                Some(key) => key,
+                None => continue,
+            };
+            let state = FileState {
+                folds: view.json_folds(),
+                cursor: (view.cursor.top_index, view.cursor.to_json_path()),
+                children: TreeSession::new(tree).children,
+            };
+            self.files.insert(key, state);
+        }
+    }
+    // Forgets files that no longer exist.
+    pub fn prune(&mut self) {
+        self.files.retain(|path, _| Path::new(path).exists());
+    }
+    // Restores what was remembered of the file at `path` into `tree`, which was just loaded from
+    // it. Returns false if nothing was.
+    pub fn restore(
+        &self,
+        path: &str,
+        tree: &mut ViewTree,
+        jq_options: &JQOptions,
+        layout: JexLayout,
+    ) -> bool {
+        let state = match file_key(path).and_then(|key| self.files.get(&key)) {
+            Some(state) => state.clone(),
+            None => return false,
+        };
+        if let View::Json(Some(view)) = &mut tree.view_frame.view {
+            view.restore_position(&state.folds, &state.cursor);
+        }
+        restore_queries(state.children, tree, jq_options, layout);
+        true
+    }
+}
+
+// (top index, path) of each folded value.
+type Folds = Vec<(usize, Vec<PathElement>)>;
+
+// A saved file's folds go next to it, as a list of jq paths (like `path(..)` gives), so whoever
+// opens it sees it folded the same way. In files with several values, paths start with the
+// value's index, as if they were slurped.
+pub fn fold_sidecar_path(path: &str) -> String {
+    format!("{}.folds.json", path)
+}
+
+pub fn save_fold_sidecar(path: &str, view: &JsonView) -> io::Result<()> {
+    let slurped = view.values.len() > 1;
+    let mut paths: Vec<Vec<PathElement>> = view
+        .json_folds()
+        .into_iter()
+        .map(|(top_index, mut path)| {
+            if slurped {
+                path.insert(0, PathElement::Index(top_index));
+            }
+            path
+        })
+        .collect();
+    // Parents before their children, and otherwise the same every time.
+    paths.sort_by_key(|path| path.iter().map(PathElement::to_string).collect::<String>());
+    let f = fs::File::create(fold_sidecar_path(path))?;
+    serde_json::to_writer_pretty(f, &paths)?;
+    Ok(())
+}
+
+// The folds in the sidecar of the file at `path`, just loaded as `view`, if it has one.
+pub fn load_fold_sidecar(path: &str, view: &JsonView) -> io::Result<Option<Folds>> {
+    let f = match fs::File::open(fold_sidecar_path(path)) {
+        Ok(f) => f,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(err) => return Err(err),
+    };
+    let paths: Vec<Vec<PathElement>> = serde_json::from_reader(io::BufReader::new(f))?;
+    let slurped = view.values.len() > 1;
+    let folds = paths
+        .into_iter()
+        .filter_map(|mut path| {
+            if !slurped {
+                return Some((0, path));
+            }
+            match path.first() {
+                Some(&PathElement::Index(top_index)) => {
+                    path.remove(0);
+                    Some((top_index, path))
+                }
+                _ => None,
+            }
+        })
+        .collect();
+    Ok(Some(folds))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{load_fold_sidecar, save_fold_sidecar, FileStates, Session};
+    use crate::{
+        app::{App, Focus},
+        cursor::PathElement,
+        jq::query::JQOptions,
+        layout::JexLayout,
+        view_tree::View,
+    };
+    use tui::layout::Rect;
+    const DUMMY_RECT: Rect = Rect {
+        x: 0,
+        y: 0,
+        width: 100,
+        height: 50,
+    };
+    #[test]
+    fn unit_session_roundtrip() {
+        let path = std::env::temp_dir().join("jex_unit_session_roundtrip.json");
+        std::fs::write(&path, r#"{"a": [1, 2, 3], "b": {"c": null}}"#).unwrap();
+        let layout = JexLayout::new(DUMMY_RECT, false);
+        // Titled, so it's reloaded from its path rather than its name.
+        let mut app = App::new(
+            &b"{\"a\": [1, 2, 3], \"b\": {\"c\": null}}"[..],
+            "Data".to_owned(),
+            layout,
+        )
+        .expect("couldn't initialize app");
+        app.views.trees[0].view_frame.source_path = Some(path.clone());
+        app.focus = Focus::Right;
+        *app.focused_query_mut().unwrap() = ".a".to_owned();
+        app.recompute_focused_view(layout);
+        let session = Session::new(&app);
+        assert_eq!(session.last_query.as_deref(), Some(".a"));
+        let restored = session
+            .clone()
+            .restore(JQOptions::default(), false, layout)
+            .unwrap();
+        assert_eq!(Session::new(&restored), session);
+        std::fs::remove_file(&path).unwrap();
+    }
+    #[test]
+    fn unit_session_skips_unsourced_trees() {
+        let layout = JexLayout::new(DUMMY_RECT, false);
+        let mut app = App::new(&b"[1]"[..], "Data".to_owned(), layout).unwrap();
+        let path = std::env::temp_dir().join(format!("jex_unsourced_{}.json", std::process::id()));
+        std::fs::write(&path, "[1]").unwrap();
+        app.copy_focused_to_root(layout);
+        app.views.trees[1].view_frame.source_path = Some(path.clone());
+        app.focus = Focus::Left;
+        app.left_index.tree = 1;
+        app.left_index.within_tree.path.clear();
+        app.right_index.tree = 0;
+        // Only the tree from a file is kept, and the panes follow it.
+        let session = Session::new(&app);
+        assert_eq!(session.trees.len(), 1);
+        assert_eq!(session.trees[0].source_path, Some(path.clone()));
+        assert_eq!(session.left_index, (0, vec![]));
+        assert_eq!(session.right_index, (0, vec![0]));
+        let restored = session
+            .restore(JQOptions::default(), false, layout)
+            .unwrap();
+        assert_eq!(restored.views.trees.len(), 1);
+        std::fs::remove_file(&path).unwrap();
+    }
+    #[test]
+    fn unit_file_states() {
+        let path =
+            std::env::temp_dir().join(format!("jex_file_states_{}.json", std::process::id()));
+        let json = br#"{"a": [1, 2, 3], "b": {"c": null}}"#;
+        std::fs::write(&path, json).unwrap();
+        let layout = JexLayout::new(DUMMY_RECT, false);
+        let name = path.to_str().unwrap().to_owned();
+        let open = |states: &FileStates| {
+            let mut app = App::new(&json[..], "Data".to_owned(), layout).unwrap();
+            app.views.trees[0].view_frame.source_path = Some(path.clone());
+            app.file_states = Some(states.clone());
+            app.restore_file_state(0, &name, layout);
+            app
+        };
+        let mut app = open(&FileStates::default());
+        app.set_right_query(".b".to_owned(), layout);
+        assert!(app.jump_focused_to(0, &[PathElement::Key("a".to_owned())]));
+        if let View::Json(Some(view)) = &mut app.focused_view_mut().frame().view {
+            assert!(view.toggle_fold());
+        }
+        let mut states = FileStates::default();
+        states.remember(&app);
+        let state = states.files.values().next().unwrap();
+        assert_eq!(state.cursor, (0, vec![PathElement::Key("a".to_owned())]));
+        assert_eq!(state.folds, vec![state.cursor.clone()]);
+        // Opened again, it's as it was left.
+        let restored = open(&states);
+        assert_eq!(Session::new(&restored), Session::new(&app));
+        let mut restored_states = FileStates::default();
+        restored_states.remember(&restored);
+        assert_eq!(restored_states, states);
+        assert_eq!(
+            restored.focused_view().frame().title(),
+            app.focused_view().frame().title()
+        );
+        std::fs::remove_file(&path).unwrap();
+        states.prune();
+        assert!(states.files.is_empty());
+    }
+    #[test]
+    fn unit_fold_sidecar() {
+        let path =
+            std::env::temp_dir().join(format!("jex_fold_sidecar_{}.json", std::process::id()));
+        let name = path.to_str().unwrap().to_owned();
+        let json = b"{\"a\": [1, {\"b\": 2}]}\n{\"c\": []}";
+        std::fs::write(&path, json).unwrap();
+        let layout = JexLayout::new(DUMMY_RECT, false);
+        let mut app = App::new(&json[..], name.clone(), layout).unwrap();
+        let folds = vec![
+            (0, vec![PathElement::Key("a".to_owned())]),
+            (
+                0,
+                vec![PathElement::Key("a".to_owned()), PathElement::Index(1)],
+            ),
+            (1, vec![]),
+        ];
+        if let View::Json(Some(view)) = &mut app.focused_view_mut().frame().view {
+            view.set_json_folds(&folds);
+            save_fold_sidecar(&name, view).unwrap();
+        }
+        let sidecar = format!("{}.folds.json", name);
+        let paths: serde_json::Value =
+            serde_json::from_str(&std::fs::read_to_string(&sidecar).unwrap()).unwrap();
+        assert_eq!(paths, serde_json::json!([[0, "a"], [0, "a", 1], [1]]));
+        app.open_file(name.clone(), String::new(), layout).unwrap();
+        if let View::Json(Some(view)) = &app.views.trees[1].view_frame.view {
+            assert_eq!(load_fold_sidecar(&name, view).unwrap(), Some(folds));
+            assert_eq!(view.folds.len(), 3);
+        }
+        std::fs::remove_file(&sidecar).unwrap();
+        std::fs::remove_file(&path).unwrap();
+    }
+}
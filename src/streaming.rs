@@ -0,0 +1,284 @@
+use crate::{
+    duplicate_keys::CheckKeys,
+    formats::{json_error, CountingRead, SkipBom, Utf8Check},
+    jq::jv::{JVArray, JV},
+};
+use serde::{
+    de::{self, value::MapAccessDeserializer, DeserializeSeed, MapAccess, SeqAccess, Visitor},
+    Deserialize, Deserializer,
+};
+use serde_json::Value;
+use std::{
+    fmt, io,
+    sync::mpsc::{self, Receiver, Sender, TryRecvError},
+    thread,
+};
+
+// JV isn't Send, so values cross from the parsing thread as serde_json Values.
+enum Parsed {
+    Value(Value),
+    ArrayStart,
+    Element(Value),
+    ArrayEnd,
+    // Paths of keys that appeared more than once in an object, sent once everything's parsed.
+    Duplicates(Vec<String>),
+    Error(String),
+}
+
+// Json parsed on a background thread, so it can be shown before it's all arrived. Each complete
+// top level value is available as soon as it's parsed, and so is each element of a top level
+// array, with the array shown as however much of it has been read.
+pub struct StreamingLoad {
+    receiver: Receiver<Parsed>,
+    complete: Vec<JV>,
+    // A top level array that's still being read, which elements are appended to as they arrive.
+    open_array: Option<JVArray>,
+    duplicates: Vec<String>,
+    done: bool,
+}
+
+impl StreamingLoad {
+    pub fn spawn<R: io::Read + Send + 'static>(r: R) -> Self {
+        let (sender, receiver) = mpsc::channel();
+        thread::spawn(move || {
+            let r = io::BufReader::new(Utf8Check::new(SkipBom::new(r)));
+            let (r, bytes_read) = CountingRead::new(r);
+            let (r, keys) = CheckKeys::new(r);
+            let mut deserializer = serde_json::Deserializer::from_reader(r);
+            while deserializer.end().is_err() {
+                if let Err(err) = TopLevel(&sender).deserialize(&mut deserializer) {
+                    let err = json_error(&err, bytes_read.get());
+                    // If the receiver is gone there's no one to tell.
+                    let _ = sender.send(Parsed::Error(err));
+                    return;
+                }
+            }
+            let duplicates = keys.borrow().found().to_vec();
+            if !duplicates.is_empty() {
+                let _ = sender.send(Parsed::Duplicates(duplicates));
+            }
+        });
+        StreamingLoad {
+            receiver,
+            complete: Vec::new(),
+            open_array: None,
+            duplicates: Vec::new(),
+            done: false,
+        }
+    }
+    pub fn is_done(&self) -> bool {
+        self.done
+    }
+    // Takes in everything parsed since the last call. Returns whether anything changed, and the
+    // error that stopped parsing, if there was one.
+    pub fn receive(&mut self) -> (bool, Option<String>) {
+        let mut changed = false;
+        loop {
+            match self.receiver.try_recv() {
+                Ok(parsed) => {
+                    changed = true;
+                    if let Some(err) = self.take(parsed) {
+                        return (changed, Some(err));
+                    }
+                }
+                Err(TryRecvError::Empty) => return (changed, None),
+                Err(TryRecvError::Disconnected) => {
+                    self.done = true;
+                    return (changed, None);
+                }
+            }
+        }
+    }
+    // Blocks until there's something to show, or parsing stops, returning the error that stopped
+    // it if there was one.
+    pub fn wait(&mut self) -> Option<String> {
+        while self.complete.is_empty() && self.open_array.is_none() && !self.done {
+            match self.receiver.recv() {
+                Ok(parsed) => {
+                    if let Some(err) = self.take(parsed) {
+                        return Some(err);
+                    }
+                }
+                Err(_) => self.done = true,
+            }
+        }
+        None
+    }
+    fn take(&mut self, parsed: Parsed) -> Option<String> {
+        match parsed {
+            Parsed::Value(value) => self.complete.push((&value).into()),
+            Parsed::ArrayStart => self.open_array = Some(JVArray::new()),
+            Parsed::Element(value) => {
+                if let Some(arr) = self.open_array.as_mut() {
+                    arr.set(arr.len(), (&value).into());
+                }
+            }
+            Parsed::ArrayEnd => {
+                if let Some(arr) = self.open_array.take() {
+                    self.complete.push(arr.into());
+                }
+            }
+            Parsed::Duplicates(duplicates) => self.duplicates = duplicates,
+            Parsed::Error(err) => {
+                self.done = true;
+                return Some(err);
+            }
+        }
+        None
+    }
+    // Paths of duplicate keys that have been received and not yet taken. They're only known once
+    // the whole file is parsed.
+    pub fn take_duplicates(&mut self) -> Vec<String> {
+        std::mem::take(&mut self.duplicates)
+    }
+    // Everything parsed so far. The values are shared rather than copied, since cloning a JV
+    // only adds a reference.
+    pub fn values(&self) -> Vec<JV> {
+        let mut values = self.complete.clone();
+        if let Some(arr) = &self.open_array {
+            values.push(arr.clone().into());
+        }
+        values
+    }
+}
+
+// Sends one top level value, streaming the elements if it's an array.
+struct TopLevel<'a>(&'a Sender<Parsed>);
+
+impl<'a> TopLevel<'a> {
+    fn send<E: de::Error>(&self, parsed: Parsed) -> Result<(), E> {
+        self.0
+            .send(parsed)
+            .map_err(|_| E::custom("nothing is waiting for the values"))
+    }
+}
+
+impl<'de, 'a> DeserializeSeed<'de> for TopLevel<'a> {
+    type Value = ();
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<(), D::Error> {
+        deserializer.deserialize_any(self)
+    }
+}
+
+impl<'de, 'a> Visitor<'de> for TopLevel<'a> {
+    type Value = ();
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "a json value")
+    }
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<(), A::Error> {
+        self.send(Parsed::ArrayStart)?;
+        while let Some(element) = seq.next_element::<Value>()? {
+            self.send(Parsed::Element(element))?;
+        }
+        self.send(Parsed::ArrayEnd)
+    }
+    fn visit_map<A: MapAccess<'de>>(self, map: A) -> Result<(), A::Error> {
+        let value = Value::deserialize(MapAccessDeserializer::new(map))?;
+        self.send(Parsed::Value(value))
+    }
+    fn visit_bool<E: de::Error>(self, b: bool) -> Result<(), E> {
+        self.send(Parsed::Value(Value::from(b)))
+    }
+    fn visit_i64<E: de::Error>(self, x: i64) -> Result<(), E> {
+        self.send(Parsed::Value(Value::from(x)))
+    }
+    fn visit_u64<E: de::Error>(self, x: u64) -> Result<(), E> {
+        self.send(Parsed::Value(Value::from(x)))
+    }
+    fn visit_f64<E: de::Error>(self, x: f64) -> Result<(), E> {
+        self.send(Parsed::Value(Value::from(x)))
+    }
+    fn visit_str<E: de::Error>(self, s: &str) -> Result<(), E> {
+        self.send(Parsed::Value(Value::from(s)))
+    }
+    fn visit_string<E: de::Error>(self, s: String) -> Result<(), E> {
+        self.send(Parsed::Value(Value::from(s)))
+    }
+    fn visit_unit<E: de::Error>(self) -> Result<(), E> {
+        self.send(Parsed::Value(Value::Null))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::StreamingLoad;
+    use crate::jq::jv::JV;
+    use serde_json::json;
+    use std::{
+        io::{self, Read},
+        sync::mpsc,
+    };
+
+    // Hands out chunks of input as they're sent, like a slow network stream.
+    struct ChunkReader(mpsc::Receiver<&'static [u8]>, &'static [u8]);
+    impl Read for ChunkReader {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            if self.1.is_empty() {
+                self.1 = self.0.recv().unwrap_or(&[]);
+            }
+            let n = self.1.len().min(buf.len());
+            buf[..n].copy_from_slice(&self.1[..n]);
+            self.1 = &self.1[n..];
+            Ok(n)
+        }
+    }
+    fn receive_until(load: &mut StreamingLoad, len: usize) -> Vec<JV> {
+        loop {
+            load.receive();
+            let values = load.values();
+            let received = match values.last() {
+                Some(JV::Array(arr)) if values.len() == 1 => arr.len() as usize,
+                _ => values.len(),
+            };
+            if received >= len || load.is_done() {
+                return values;
+            }
+            std::thread::yield_now();
+        }
+    }
+    #[test]
+    fn unit_streaming_array() {
+        let (sender, receiver) = mpsc::channel();
+        let mut load = StreamingLoad::spawn(ChunkReader(receiver, &[]));
+        sender.send(&b"[{\"a\": 1}, 2, "[..]).unwrap();
+        let expected: JV = (&json!([{"a": 1}, 2])).into();
+        assert_eq!(receive_until(&mut load, 2), vec![expected]);
+        assert!(!load.is_done());
+        sender.send(&b"3]"[..]).unwrap();
+        drop(sender);
+        let expected: JV = (&json!([{"a": 1}, 2, 3])).into();
+        while !load.is_done() {
+            load.receive();
+        }
+        assert_eq!(load.values(), vec![expected]);
+    }
+    #[test]
+    fn unit_streaming_truncated() {
+        let mut load = StreamingLoad::spawn(&b"{\"a\": 1} \"b\" {\"c\": "[..]);
+        assert_eq!(load.wait(), None);
+        let mut error = None;
+        while !load.is_done() {
+            error = error.or(load.receive().1);
+        }
+        assert!(error.is_some());
+        let expected: Vec<JV> = vec![(&json!({"a": 1})).into(), (&json!("b")).into()];
+        assert_eq!(load.values(), expected);
+        let mut load = StreamingLoad::spawn(&b"{\"a\": 1} junk"[..]);
+        assert_eq!(load.wait(), None);
+        let mut error = None;
+        while !load.is_done() {
+            error = error.or(load.receive().1);
+        }
+        assert!(error.unwrap().starts_with("Invalid json at byte 9:"));
+    }
+    #[test]
+    fn unit_streaming_bom() {
+        let mut load = StreamingLoad::spawn(&b"\xEF\xBB\xBF{\"a\": 1}"[..]);
+        assert_eq!(load.wait(), None);
+        while !load.is_done() {
+            assert_eq!(load.receive().1, None);
+        }
+        let expected: Vec<JV> = vec![(&json!({"a": 1})).into()];
+        assert_eq!(load.values(), expected);
+    }
+}
@@ -1,15 +1,26 @@
 use crate::{
-    cursor::{FocusPosition, GlobalCursor, GlobalPath, LeafCursor, ValuePath},
+    cursor::{FocusPosition, GlobalCursor, GlobalPath, LeafCursor, PathElement, ValuePath},
+    formats::InputFormat,
     jq::{
         jv::JV,
-        query::{run_jq_query, JQ},
+        query::{run_jq_query, JQOptions, QueryRun, JQ},
     },
     layout::JexLayout,
-    lines::LineCursor,
+    lines::{AbstractTypes, GlyphMode, RenderOptions},
+    path_glob::PathGlob,
 };
 use log::trace;
-use serde_json::Deserializer;
-use std::{cmp::Ordering, collections::HashSet, io, io::Write, ops::RangeInclusive, rc::Rc};
+use std::{
+    borrow::Cow,
+    cmp::Ordering,
+    collections::{BTreeMap, HashSet, VecDeque},
+    io,
+    io::Write,
+    ops::RangeInclusive,
+    path::PathBuf,
+    rc::Rc,
+    time::{Duration, Instant},
+};
 use tui::{
     layout::{Alignment, Rect},
     style::{Color, Style},
@@ -20,8 +31,12 @@ use tui::{
 #[derive(Debug, Clone)]
 pub struct ViewForest {
     pub trees: Vec<ViewTree>,
+    // Views whose children are hidden in the edit tree, as (tree, path). j and k skip them too.
+    pub folds: HashSet<(usize, Vec<usize>)>,
 }
 
+// Roots are the views of opened files, which are the unfiltered source documents, so they have
+// no query. Every other view is a query on its parent.
 #[derive(Debug, Clone, Copy)]
 pub enum ViewWithParent<'a> {
     Root {
@@ -40,6 +55,23 @@ impl<'a> ViewWithParent<'a> {
             ViewWithParent::Root { frame } | ViewWithParent::Child { frame, .. } => frame,
         }
     }
+    pub fn query(self) -> Option<&'a String> {
+        match self {
+            ViewWithParent::Root { .. } => None,
+            ViewWithParent::Child { query, .. } => Some(query),
+        }
+    }
+    // The frame's title, followed by the query that produced it on one line. Roots are named
+    // after the file they came from.
+    pub fn title(self) -> String {
+        match self {
+            ViewWithParent::Root { frame } => frame.title(),
+            ViewWithParent::Child { frame, query, .. } => {
+                let query: Vec<&str> = query.split_whitespace().collect();
+                format!("{}: {}", frame.title(), query.join(" "))
+            }
+        }
+    }
 }
 
 #[derive(Debug)]
@@ -65,9 +97,60 @@ impl<'a: 'b, 'b> ViewWithParentMut<'a> {
             ViewWithParentMut::Root { frame } | ViewWithParentMut::Child { frame, .. } => frame,
         }
     }
+    pub fn take_query(self) -> Option<&'a mut String> {
+        match self {
+            ViewWithParentMut::Root { .. } => None,
+            ViewWithParentMut::Child { query, .. } => Some(query),
+        }
+    }
 }
 
 impl ViewForest {
+    pub fn new(trees: Vec<ViewTree>) -> Self {
+        ViewForest {
+            trees,
+            folds: HashSet::new(),
+        }
+    }
+    pub fn is_folded(&self, tree: usize, path: &[usize]) -> bool {
+        !self.folds.is_empty() && self.folds.contains(&(tree, path.to_vec()))
+    }
+    // Folds or unfolds the view at `ix` in the edit tree. Returns false if it has no children to
+    // hide.
+    pub fn toggle_fold(&mut self, ix: &ViewForestIndex) -> bool {
+        let key = (ix.tree, ix.within_tree.path.clone());
+        if self.folds.remove(&key) {
+            return true;
+        }
+        let has_children = self.trees[ix.tree]
+            .index_tree(&ix.within_tree.path)
+            .map_or(false, |tree| !tree.children.is_empty());
+        if has_children {
+            self.folds.insert(key);
+        }
+        has_children
+    }
+    // Unfolds everything if anything is folded, and otherwise folds every view with children.
+    // Returns whether views are now folded.
+    pub fn toggle_fold_all(&mut self) -> bool {
+        if !self.folds.is_empty() {
+            self.folds.clear();
+            return false;
+        }
+        for (i, tree) in self.trees.iter().enumerate() {
+            for path in tree.parent_paths() {
+                self.folds.insert((i, path));
+            }
+        }
+        !self.folds.is_empty()
+    }
+    // Moves `ix` up to the outermost folded view containing it, if there is one.
+    pub fn reveal(&self, ix: &mut ViewForestIndex) {
+        let path = &ix.within_tree.path;
+        if let Some(len) = (0..path.len()).find(|&len| self.is_folded(ix.tree, &path[..len])) {
+            ix.within_tree.path.truncate(len);
+        }
+    }
     pub fn index(&self, ix: &ViewForestIndex) -> Option<ViewWithParent> {
         let tree = self.trees.get(ix.tree)?;
         tree.index(&ix.within_tree)
@@ -101,6 +184,8 @@ impl ViewForest {
             };
             render_tree_inner(
                 tree,
+                &mut Vec::new(),
+                &|path| self.is_folded(i, path),
                 "",
                 i == self.trees.len() - 1,
                 left_tree_index,
@@ -122,29 +207,85 @@ pub struct ViewTree {
 #[derive(Debug, Clone)]
 pub struct NamedView {
     pub view: View,
+    // Shown as the view's title. It's only a path until the view is given a title of its own.
     pub name: String,
+    // The file a root was opened from, for anything that reads or writes it again. None for
+    // queries and for roots that aren't files, like --exec output or diffs.
+    pub source_path: Option<PathBuf>,
+}
+
+impl NamedView {
+    pub fn title(&self) -> String {
+        match &self.view {
+            View::Json(Some(view)) if view.modified => format!("{} (modified)", self.name),
+            _ => self.name.clone(),
+        }
+    }
+    // What the save prompt starts with: the file it was opened from, or nothing, since a title
+    // isn't a path.
+    pub fn save_path(&self) -> String {
+        self.source_path
+            .as_ref()
+            .map(|path| path.to_string_lossy().into_owned())
+            .unwrap_or_default()
+    }
 }
 
 impl ViewTree {
-    pub fn new_from_reader<R: io::Read>(r: R, name: String, layout: JexLayout) -> io::Result<Self> {
-        let content: Vec<JV> = Deserializer::from_reader(r)
-            .into_iter::<JV>()
-            .collect::<Result<Vec<JV>, _>>()?;
-        let view = View::new(content, layout.left);
-        let view_frame = NamedView { view, name };
+    pub fn new_from_reader<R: io::Read>(
+        r: R,
+        format: InputFormat,
+        name: String,
+        layout: JexLayout,
+    ) -> io::Result<Self> {
+        let content = format.read_values(r)?;
+        Ok(ViewTree::new_from_values(content, name, layout))
+    }
+    pub fn new_from_values(content: Vec<JV>, name: String, layout: JexLayout) -> Self {
+        let view = View::new(content, layout.left, RenderOptions::default());
+        let view_frame = NamedView {
+            view,
+            name,
+            source_path: None,
+        };
         let mut tree = ViewTree {
             view_frame,
             children: Vec::new(),
         };
         tree.push_trivial_child(layout.right);
-        Ok(tree)
+        tree
+    }
+    // Reruns every query below this tree's view, after its values have changed.
+    pub fn recompute_children(&mut self, options: &JQOptions, target_view_rect: Rect) {
+        let ViewTree {
+            view_frame,
+            children,
+        } = self;
+        for (query, child) in children.iter_mut() {
+            child.view_frame.view.recompute_from(
+                &view_frame.view,
+                query,
+                options,
+                target_view_rect,
+            );
+            child.recompute_children(options, target_view_rect);
+        }
+    }
+    pub fn set_render_options(&mut self, options: RenderOptions) {
+        if let View::Json(Some(view)) = &mut self.view_frame.view {
+            view.set_render_options(options);
+        }
+        for (_, child) in self.children.iter_mut() {
+            child.set_render_options(options);
+        }
     }
     pub fn push_trivial_child(&mut self, target_view_rect: Rect) {
         if let View::Json(Some(view)) = &self.view_frame.view {
             let name = "New Query".into();
             let view_frame = NamedView {
-                view: View::new(view.values.clone(), target_view_rect),
+                view: View::new(view.values.clone(), target_view_rect, view.scroll.options),
                 name,
+                source_path: None,
             };
             let child = ViewTree {
                 view_frame,
@@ -153,6 +294,40 @@ impl ViewTree {
             self.children.push((".".to_string(), child));
         }
     }
+    // Paths to the views in this tree with edits that haven't been saved.
+    pub fn unsaved_paths(&self) -> Vec<Vec<usize>> {
+        let mut paths = Vec::new();
+        if matches!(&self.view_frame.view, View::Json(Some(view)) if view.modified) {
+            paths.push(Vec::new());
+        }
+        for (i, (_, child)) in self.children.iter().enumerate() {
+            for mut path in child.unsaved_paths() {
+                path.insert(0, i);
+                paths.push(path);
+            }
+        }
+        paths
+    }
+    // Paths to the views in this tree that have children.
+    pub fn parent_paths(&self) -> Vec<Vec<usize>> {
+        let mut paths = Vec::new();
+        if !self.children.is_empty() {
+            paths.push(Vec::new());
+        }
+        for (i, (_, child)) in self.children.iter().enumerate() {
+            for mut path in child.parent_paths() {
+                path.insert(0, i);
+                paths.push(path);
+            }
+        }
+        paths
+    }
+    fn descendant_count(&self) -> usize {
+        self.children
+            .iter()
+            .map(|(_, child)| 1 + child.descendant_count())
+            .sum()
+    }
     pub fn index_tree(&self, mut path: &[usize]) -> Option<&Self> {
         let mut focus = self;
         while let Some((&i, new_path)) = path.split_first() {
@@ -208,16 +383,21 @@ impl ViewTree {
     }
 }
 
+#[allow(clippy::too_many_arguments)]
 fn render_tree_inner<'a, 'b>(
     tree: &'a ViewTree,
+    path: &mut Vec<usize>,
+    is_folded: &dyn Fn(&[usize]) -> bool,
     prefix: &str,
     end: bool,
     left_index: Option<BorrowedViewTreeIndex>,
     right_index: Option<BorrowedViewTreeIndex>,
     out: &mut Vec<Spans<'a>>,
 ) {
-    let is_left = left_index.map_or(false, |index| index.parent.is_empty());
-    let is_right = right_index.map_or(false, |index| index.parent.is_empty());
+    let folded = !tree.children.is_empty() && is_folded(path);
+    // A folded view stands in for the views under it.
+    let is_left = left_index.map_or(false, |index| folded || index.parent.is_empty());
+    let is_right = right_index.map_or(false, |index| folded || index.parent.is_empty());
     trace!(
         "{:?}, {:?}, {:?}, {:?}",
         left_index,
@@ -226,24 +406,43 @@ fn render_tree_inner<'a, 'b>(
         is_right
     );
     let mid = if end { "└" } else { "├" };
+    let name: Cow<str> = if folded {
+        format!("{} (+{})", tree.view_frame.name, tree.descendant_count()).into()
+    } else {
+        tree.view_frame.name.as_str().into()
+    };
     out.push(
         vec![
             prefix.to_owned().into(),
             mid.into(),
-            render_tree_entry(&tree.view_frame.name, is_left, is_right),
+            render_tree_entry(name, is_left, is_right),
         ]
         .into(),
     );
+    if folded {
+        return;
+    }
     let new_prefix = format!("{}{}", prefix, if end { ' ' } else { '│' });
     for (i, (_, child)) in tree.children.iter().enumerate() {
         let end = i == tree.children.len() - 1;
         let left_index = left_index.and_then(|index| index.descend(i));
         let right_index = right_index.and_then(|index| index.descend(i));
-        render_tree_inner(child, &new_prefix, end, left_index, right_index, out);
+        path.push(i);
+        render_tree_inner(
+            child,
+            path,
+            is_folded,
+            &new_prefix,
+            end,
+            left_index,
+            right_index,
+            out,
+        );
+        path.pop();
     }
 }
 
-fn render_tree_entry(name: &str, is_parent: bool, is_child: bool) -> Span {
+fn render_tree_entry(name: Cow<str>, is_parent: bool, is_child: bool) -> Span {
     match (is_parent, is_child) {
         (false, false) => Span::raw(name),
         (true, false) => Span::styled(format!("(L) {}", name), Style::default().fg(Color::Blue)),
@@ -260,7 +459,12 @@ pub struct ViewForestIndex {
 
 impl ViewForestIndex {
     pub fn advance(&mut self, forrest: &ViewForest) -> Option<()> {
-        if let Some(()) = self.within_tree.advance(&forrest.trees[self.tree]) {
+        let tree = self.tree;
+        let is_folded = |path: &[usize]| forrest.is_folded(tree, path);
+        if let Some(()) = self
+            .within_tree
+            .advance(&forrest.trees[self.tree], &is_folded)
+        {
             return Some(());
         }
         if self.tree == forrest.trees.len() - 1 {
@@ -271,14 +475,21 @@ impl ViewForestIndex {
         Some(())
     }
     pub fn regress(&mut self, forrest: &ViewForest) -> Option<()> {
-        if let Some(()) = self.within_tree.regress(&forrest.trees[self.tree]) {
+        let tree = self.tree;
+        let is_folded = |path: &[usize]| forrest.is_folded(tree, path);
+        if let Some(()) = self
+            .within_tree
+            .regress(&forrest.trees[self.tree], &is_folded)
+        {
             return Some(());
         }
         if self.tree == 0 {
             return None;
         }
         self.tree -= 1;
-        self.within_tree = ViewTreeIndex::new_at_end(&forrest.trees[self.tree]);
+        let tree = self.tree;
+        self.within_tree =
+            ViewTreeIndex::new_at_end(&forrest.trees[tree], &|path| forrest.is_folded(tree, path));
         Some(())
     }
     // updates self to be consistent with a move of origin to destination.
@@ -316,6 +527,7 @@ impl ViewForestIndex {
                     .within_tree
                     .path
                     .extend(&self.within_tree.path[origin.within_tree.path.len()..]);
+                *self = new_self;
             }
         }
     }
@@ -330,14 +542,24 @@ impl ViewTreeIndex {
     fn borrowed<'a>(&'a self) -> BorrowedViewTreeIndex<'a> {
         BorrowedViewTreeIndex { parent: &self.path }
     }
-    pub fn advance(&mut self, views: &ViewTree) -> Option<()> {
-        self.advance_inner(views, 0)
+    // `is_folded` says which views' children to skip.
+    pub fn advance(
+        &mut self,
+        views: &ViewTree,
+        is_folded: &dyn Fn(&[usize]) -> bool,
+    ) -> Option<()> {
+        self.advance_inner(views, 0, is_folded)
     }
-    fn advance_inner(&mut self, views: &ViewTree, offset: usize) -> Option<()> {
+    fn advance_inner(
+        &mut self,
+        views: &ViewTree,
+        offset: usize,
+        is_folded: &dyn Fn(&[usize]) -> bool,
+    ) -> Option<()> {
         match self.path.get(offset) {
             None => {
                 // We've arrived at the node we're pointing at. Descend into its children if possible.
-                if !views.children.is_empty() {
+                if !views.children.is_empty() && !is_folded(&self.path) {
                     self.path.push(0);
                     Some(())
                 } else {
@@ -346,7 +568,7 @@ impl ViewTreeIndex {
             }
             Some(&child_ix) => {
                 let child = &views.children[child_ix].1;
-                match self.advance_inner(child, offset + 1) {
+                match self.advance_inner(child, offset + 1, is_folded) {
                     Some(()) => Some(()), // child advanced
                     None => {
                         let new_child_ix = child_ix + 1;
@@ -362,26 +584,36 @@ impl ViewTreeIndex {
             }
         }
     }
-    pub fn regress(&mut self, views: &ViewTree) -> Option<()> {
+    pub fn regress(
+        &mut self,
+        views: &ViewTree,
+        is_folded: &dyn Fn(&[usize]) -> bool,
+    ) -> Option<()> {
         let last = self.path.last_mut()?;
         if *last > 0 {
             *last -= 1;
-            let ancestor = views.index_tree(&self.path).unwrap();
-            let to_child = ViewTreeIndex::new_at_end(ancestor);
-            trace!("{:?},{:?}", &*self, &to_child);
-            self.path.extend(to_child.path);
+            let sibling = views.index_tree(&self.path).unwrap();
+            self.descend_to_end(sibling, is_folded);
+            trace!("{:?}", &*self);
         } else {
             self.path.pop();
         }
         Some(())
     }
-    pub fn new_at_end(mut tree: &ViewTree) -> Self {
+    pub fn new_at_end(tree: &ViewTree, is_folded: &dyn Fn(&[usize]) -> bool) -> Self {
         let mut out = ViewTreeIndex { path: Vec::new() };
+        out.descend_to_end(tree, is_folded);
+        out
+    }
+    // Moves from `tree`, which self points at, to the last view shown under it.
+    fn descend_to_end(&mut self, mut tree: &ViewTree, is_folded: &dyn Fn(&[usize]) -> bool) {
         while let Some(last_child) = tree.children.last() {
-            out.path.push(tree.children.len() - 1);
+            if is_folded(&self.path) {
+                break;
+            }
+            self.path.push(tree.children.len() - 1);
             tree = &last_child.1;
         }
-        out
     }
 }
 
@@ -407,9 +639,9 @@ pub enum View {
 }
 
 impl View {
-    pub fn new<V: Into<Rc<[JV]>>>(values: V, view_rect: Rect) -> Self {
+    pub fn new<V: Into<Rc<[JV]>>>(values: V, view_rect: Rect, options: RenderOptions) -> Self {
         let json_rect = Block::default().borders(Borders::ALL).inner(view_rect);
-        View::Json(JsonView::new(values, json_rect))
+        View::Json(JsonView::new(values, json_rect, options))
     }
     pub fn render(&self, rect: Rect, has_focus: bool) -> Paragraph {
         match self {
@@ -427,6 +659,103 @@ impl View {
             }
         }
     }
+    // Reruns `query` against `parent`. Folds and the cursor stay on the same paths if they're
+    // still in the new results.
+    pub fn recompute_from(
+        &mut self,
+        parent: &View,
+        query: &str,
+        options: &JQOptions,
+        target_view_rect: Rect,
+    ) {
+        self.start_recompute_from(parent, query, options, target_view_rect, None, None);
+    }
+    // Like recompute_from, but with a budget it stops once that's passed and there's a screenful
+    // of results, and with a limit it stops after that many, returning the rest of the run for
+    // continue_query.
+    pub fn start_recompute_from(
+        &mut self,
+        parent: &View,
+        query: &str,
+        options: &JQOptions,
+        target_view_rect: Rect,
+        budget: Option<Duration>,
+        limit: Option<usize>,
+    ) -> Option<QueryRun> {
+        let parent = match parent {
+            View::Json(Some(parent)) => parent,
+            View::Json(None) | View::Error(_) => {
+                *self = View::Json(None);
+                return None;
+            }
+        };
+        let previous = match self {
+            View::Json(Some(view)) => Some((
+                view.json_folds(),
+                (view.cursor.top_index, view.cursor.to_json_path()),
+                view.scroll_json_path(),
+                std::mem::take(&mut view.marks),
+            )),
+            View::Json(None) | View::Error(_) => None,
+        };
+        let json_rect = Block::default()
+            .borders(Borders::ALL)
+            .inner(target_view_rect);
+        let mut run = match JQ::compile_with(query, options) {
+            Ok(prog) => QueryRun::new(prog, parent.values.clone()).with_limit(limit),
+            Err(err) => {
+                *self = View::Error(err);
+                return None;
+            }
+        };
+        if let Err(err) = run.run(budget, json_rect.height as usize) {
+            *self = View::Error(vec![err]);
+            return None;
+        }
+        let mut options = parent.scroll.options;
+        if options.auto_raw.applies(run.results()) {
+            options.raw_strings = true;
+        }
+        *self = View::Json(JsonView::new(run.results().to_vec(), json_rect, options));
+        if let (View::Json(Some(view)), Some((folds, cursor, scroll, marks))) =
+            (&mut *self, previous)
+        {
+            view.set_json_folds(&folds);
+            view.marks = marks;
+            view.retain_valid_marks();
+            view.return_to(cursor, scroll);
+        }
+        if run.is_done() {
+            None
+        } else {
+            Some(run)
+        }
+    }
+    // Runs more of a query from start_recompute_from for `budget`, adding its results to the end.
+    // Returns false once the run is over, either finished or stopped by an error, which replaces
+    // the view like it would have in recompute_from.
+    pub fn continue_query(
+        &mut self,
+        run: &mut QueryRun,
+        budget: Duration,
+        target_view_rect: Rect,
+        options: RenderOptions,
+    ) -> bool {
+        let before = run.results().len();
+        if let Err(err) = run.run(Some(budget), 0) {
+            *self = View::Error(vec![err]);
+            return false;
+        }
+        if run.results().len() > before {
+            let values = run.results().to_vec();
+            match self {
+                View::Json(Some(view)) => view.extend_loaded(values),
+                View::Json(None) => *self = View::new(values, target_view_rect, options),
+                View::Error(_) => return false,
+            }
+        }
+        !run.is_done()
+    }
     pub fn resize_to(&mut self, view_rect: Rect) {
         match self {
             View::Json(Some(v)) => {
@@ -438,6 +767,13 @@ impl View {
     }
 }
 
+// A prior state of a view's values, for undo and redo. Cloning the values only clones handles.
+#[derive(Debug, Clone)]
+struct Snapshot {
+    values: Rc<[JV]>,
+    cursor: (usize, Vec<PathElement>),
+}
+
 #[derive(Debug, Clone)]
 pub struct JsonView {
     pub scroll: GlobalCursor,
@@ -445,20 +781,61 @@ pub struct JsonView {
     pub cursor: LeafCursor,
     pub folds: HashSet<(usize, Vec<usize>)>,
     pub rect: Rect,
+    // Whether the values have been edited since they were computed or saved.
+    pub modified: bool,
+    // When the values were last edited, for --autosave to wait for a pause in editing.
+    pub last_edit: Option<Instant>,
+    // Positions saved with `m`, by letter.
+    pub marks: BTreeMap<char, (usize, Vec<PathElement>)>,
+    pub fold_preset: FoldPreset,
+    // The folds from before a preset was chosen, to go back to afterwards.
+    manual_folds: Vec<(usize, Vec<PathElement>)>,
+    undo_stack: VecDeque<Snapshot>,
+    redo_stack: Vec<Snapshot>,
+}
+
+// A movement of the cursor, which the scroll lock repeats in the other pane.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Scroll {
+    Down,
+    Up,
+    // By a number of lines, as with PageDown and PageUp.
+    PageDown(usize),
+    PageUp(usize),
+    Top,
+    Bottom,
+}
+
+// Sets of folds to zoom out and back in with, without losing folds made by hand.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FoldPreset {
+    // Whatever's been folded by hand.
+    Manual,
+    // Everything below the top level folded.
+    Compact,
+    // Nothing folded.
+    Expanded,
 }
 
 impl JsonView {
-    pub fn new<V: Into<Rc<[JV]>>>(values: V, rect: Rect) -> Option<Self> {
+    pub fn new<V: Into<Rc<[JV]>>>(values: V, rect: Rect, options: RenderOptions) -> Option<Self> {
         let values: Rc<[JV]> = values.into();
         let cursor = LeafCursor::new(values.clone())?;
         let folds = HashSet::new();
-        let scroll = GlobalCursor::new(values.clone(), rect.width, &folds)?;
+        let scroll = GlobalCursor::new(values.clone(), rect.width, &folds, options)?;
         Some(JsonView {
             scroll,
             values,
             cursor,
             folds,
             rect,
+            modified: false,
+            last_edit: None,
+            marks: BTreeMap::new(),
+            fold_preset: FoldPreset::Manual,
+            manual_folds: Vec::new(),
+            undo_stack: VecDeque::new(),
+            redo_stack: Vec::new(),
         })
     }
     fn render(&self, rect: Rect, has_focus: bool) -> Paragraph {
@@ -472,13 +849,17 @@ impl JsonView {
             .alignment(Alignment::Left)
         //.wrap(Wrap { trim: false })
     }
-    pub fn apply_query(&self, query: &str, target_view_rect: Rect) -> View {
+    pub fn apply_query(&self, query: &str, options: &JQOptions, target_view_rect: Rect) -> View {
         let target_json_rect = Block::default()
             .borders(Borders::ALL)
             .inner(target_view_rect);
-        match JQ::compile(query) {
+        match JQ::compile_with(query, options) {
             Ok(mut prog) => match run_jq_query(self.values.iter(), &mut prog) {
-                Ok(results) => View::Json(JsonView::new(results, target_json_rect)),
+                Ok(results) => View::Json(JsonView::new(
+                    results,
+                    target_json_rect,
+                    self.scroll.options,
+                )),
                 Err(err) => View::Error(vec![err]),
             },
             Err(err) => View::Error(err),
@@ -501,29 +882,85 @@ impl JsonView {
             end_is_last_line: end_is_line_end,
         }
     }
-    pub fn page_down(&mut self) {
-        for _ in 1..self.rect.height {
+    // How far a page moves: `page_size` lines if it's set, and otherwise the view's height, less
+    // a line to keep in view.
+    pub fn page_lines(&self, page_size: Option<usize>) -> usize {
+        page_size.unwrap_or_else(|| self.rect.height.saturating_sub(1) as usize)
+    }
+    pub fn page_down(&mut self, page_size: Option<usize>) -> bool {
+        self.page_down_lines(self.page_lines(page_size))
+    }
+    pub fn page_up(&mut self, page_size: Option<usize>) -> bool {
+        self.page_up_lines(self.page_lines(page_size))
+    }
+    // Returns whether the cursor moved, except for arrow key movements, which can always at least
+    // scroll.
+    pub fn scroll_by(&mut self, scroll: Scroll) -> bool {
+        match scroll {
+            Scroll::Down => self.advance_cursor(),
+            Scroll::Up => self.regress_cursor(),
+            Scroll::PageDown(lines) => return self.page_down_lines(lines),
+            Scroll::PageUp(lines) => return self.page_up_lines(lines),
+            Scroll::Top => {
+                self.scroll = GlobalCursor::new(
+                    self.values.clone(),
+                    self.rect.width,
+                    &self.folds,
+                    self.scroll.options,
+                )
+                .expect("values should still exist");
+                self.cursor = self.scroll.value_cursor.clone();
+            }
+            Scroll::Bottom => {
+                self.scroll = GlobalCursor::new_end(
+                    self.values.clone(),
+                    self.rect.width,
+                    &self.folds,
+                    self.scroll.options,
+                )
+                .expect("values should still exist");
+                self.cursor = self.scroll.value_cursor.clone();
+            }
+        }
+        true
+    }
+    // Returns whether the cursor moved, which it can't if it's already on the last line.
+    fn page_down_lines(&mut self, lines: usize) -> bool {
+        for _ in 0..lines {
             if let None = self.scroll.advance(&self.folds, self.rect.width) {
                 break;
             };
         }
-        for _ in 1..self.rect.height {
-            if let None = self.cursor.advance(&self.folds) {
+        let mut moved = false;
+        for _ in 0..lines {
+            if let None = self
+                .cursor
+                .advance_visible(&self.folds, self.scroll.options)
+            {
                 break;
             };
+            moved = true;
         }
+        moved
     }
-    pub fn page_up(&mut self) {
-        for _ in 1..self.rect.height {
+    // Returns whether the cursor moved, which it can't if it's already on the first line.
+    fn page_up_lines(&mut self, lines: usize) -> bool {
+        for _ in 0..lines {
             if let None = self.scroll.regress(&self.folds, self.rect.width) {
                 break;
             };
         }
-        for _ in 1..self.rect.height {
-            if let None = self.cursor.regress(&self.folds) {
+        let mut moved = false;
+        for _ in 0..lines {
+            if let None = self
+                .cursor
+                .regress_visible(&self.folds, self.scroll.options)
+            {
                 break;
             };
+            moved = true;
         }
+        moved
     }
     pub fn unfold_around_cursor(&mut self) {
         let mut path = self.cursor.to_path().strip_position();
@@ -532,29 +969,374 @@ impl JsonView {
             path.1.pop();
         }
     }
-    pub fn toggle_fold(&mut self) {
+    // Moves the cursor, unfolding and scrolling as needed to make it visible.
+    // If it's on something hidden, it moves on to the next line that isn't.
+    pub fn jump_to(&mut self, cursor: LeafCursor) {
+        self.cursor = cursor;
+        if self.cursor.is_hidden(self.scroll.options) {
+            self.cursor
+                .advance_visible(&self.folds, self.scroll.options);
+        }
+        self.unfold_around_cursor();
+        self.scroll_to_cursor();
+    }
+    // Moves the cursor to the `line`th (from 1) line of json, counting folded values as one line
+    // and ignoring wrapping. Past the end, it moves to the last line and returns the line count.
+    pub fn jump_to_line(&mut self, line: usize) -> Result<(), usize> {
+        let mut cursor = LeafCursor::new(self.values.clone()).expect("values should still exist");
+        let mut current = 1;
+        while current < line {
+            if cursor
+                .advance_visible(&self.folds, self.scroll.options)
+                .is_none()
+            {
+                break;
+            }
+            current += 1;
+        }
+        self.cursor = cursor;
+        self.scroll_to_cursor();
+        if current < line {
+            Err(current)
+        } else {
+            Ok(())
+        }
+    }
+    fn scroll_to_cursor(&mut self) {
+        if !self
+            .visible_range(&self.folds)
+            .contains_value(&self.cursor.to_path())
+        {
+            self.scroll = GlobalCursor::new_at(
+                self.cursor.clone(),
+                self.rect.width,
+                &self.folds,
+                self.scroll.options,
+            );
+        }
+    }
+    // Folds are stored by child index for speed, but indices shift when the values change, so
+    // they're carried between values by their key paths instead.
+    pub fn json_folds(&self) -> Vec<(usize, Vec<PathElement>)> {
+        self.folds
+            .iter()
+            .map(|fold| {
+                let path = ValuePath::from_stripped(fold.clone());
+                let cursor = LeafCursor::from_path(self.values.clone(), &path);
+                (fold.0, cursor.to_json_path())
+            })
+            .collect()
+    }
+    // Folds each of `folds` that still exists, and scrolls back to the top.
+    pub fn set_json_folds(&mut self, folds: &[(usize, Vec<PathElement>)]) {
+        self.folds = self.folds_from_json(folds);
+        self.cursor = LeafCursor::new(self.values.clone()).expect("values should still exist");
+        self.scroll = GlobalCursor::new_at(
+            self.cursor.clone(),
+            self.rect.width,
+            &self.folds,
+            self.scroll.options,
+        );
+    }
+    // Folds `folds` and moves the cursor to `cursor`, where they still exist, as the view was
+    // left. Unlike jump_to, the cursor's own value stays folded if it was.
+    pub fn restore_position(
+        &mut self,
+        folds: &[(usize, Vec<PathElement>)],
+        cursor: &(usize, Vec<PathElement>),
+    ) {
+        self.set_json_folds(folds);
+        let cursor = match LeafCursor::from_json_path(self.values.clone(), cursor.0, &cursor.1) {
+            Some(cursor) => cursor,
+            None => return,
+        };
+        let mut path = cursor.to_path().strip_position();
+        while path.1.pop().is_some() {
+            self.folds.remove(&path);
+        }
+        self.cursor = cursor;
+        self.scroll = GlobalCursor::new_at(
+            self.scroll.value_cursor.clone(),
+            self.rect.width,
+            &self.folds,
+            self.scroll.options,
+        );
+        self.scroll_to_cursor();
+    }
+    // Skips any that no longer exist or aren't arrays or objects.
+    fn folds_from_json(&self, folds: &[(usize, Vec<PathElement>)]) -> HashSet<(usize, Vec<usize>)> {
+        folds
+            .iter()
+            .filter_map(|(top_index, path)| {
+                let cursor = LeafCursor::from_json_path(self.values.clone(), *top_index, path)?;
+                match cursor.focus {
+                    JV::Array(_) | JV::Object(_) => Some(cursor.to_path().strip_position()),
+                    _ => None,
+                }
+            })
+            .collect()
+    }
+    // Folds every array and object matching `glob`, returning how many matched. If the cursor
+    // ends up hidden, it moves to the outermost fold around it.
+    pub fn fold_matching(&mut self, glob: &PathGlob) -> usize {
+        let matches = glob.find_containers(&self.values);
+        let cursor = self.cursor.clone();
+        let cursor_path = cursor.to_json_path();
+        self.fold_preset = FoldPreset::Manual;
+        let mut folds = self.json_folds();
+        folds.extend(matches.iter().cloned());
+        self.set_json_folds(&folds);
+        let hidden_by = (0..cursor_path.len())
+            .find(|depth| matches.contains(&(cursor.top_index, cursor_path[..*depth].to_vec())));
+        self.cursor = match hidden_by {
+            Some(depth) => LeafCursor::from_json_path(
+                self.values.clone(),
+                cursor.top_index,
+                &cursor_path[..depth],
+            )
+            .expect("folds should exist"),
+            None => cursor,
+        };
+        self.scroll_to_cursor();
+        matches.len()
+    }
+    // Folds everything but the cursor's value and the way to it: the other top level values, and
+    // the siblings of the cursor and each of its parents.
+    pub fn isolate_cursor(&mut self) {
+        self.fold_preset = FoldPreset::Manual;
+        let top_index = self.cursor.top_index;
+        let path = self.cursor.to_json_path();
+        let mut folds: Vec<(usize, Vec<PathElement>)> = (0..self.values.len())
+            .filter(|i| *i != top_index)
+            .map(|i| (i, Vec::new()))
+            .collect();
+        for (depth, step) in path.iter().enumerate() {
+            let parent = LeafCursor::from_json_path(self.values.clone(), top_index, &path[..depth])
+                .expect("the cursor's parents should exist");
+            let siblings: Vec<PathElement> = match &parent.focus {
+                JV::Array(arr) => (0..arr.len() as usize).map(PathElement::Index).collect(),
+                JV::Object(obj) => obj
+                    .iter()
+                    .map(|(k, _)| PathElement::Key(k.to_owned()))
+                    .collect(),
+                _ => continue,
+            };
+            for sibling in siblings.into_iter().filter(|sibling| sibling != step) {
+                let mut sibling_path = path[..depth].to_vec();
+                sibling_path.push(sibling);
+                folds.push((top_index, sibling_path));
+            }
+        }
+        self.restore_position(&folds, &(top_index, path));
+    }
+    pub fn set_mark(&mut self, mark: char) {
+        let position = (self.cursor.top_index, self.cursor.to_json_path());
+        self.marks.insert(mark, position);
+    }
+    // Jumps to the position saved as `mark`, returning false if there isn't one.
+    pub fn jump_to_mark(&mut self, mark: char) -> bool {
+        let cursor = self.marks.get(&mark).and_then(|(top_index, path)| {
+            LeafCursor::from_json_path(self.values.clone(), *top_index, path)
+        });
+        match cursor {
+            Some(cursor) => {
+                self.jump_to(cursor);
+                true
+            }
+            None => false,
+        }
+    }
+    // Drops marks whose paths no longer exist.
+    fn retain_valid_marks(&mut self) {
+        let values = self.values.clone();
+        self.marks.retain(|_, (top_index, path)| {
+            LeafCursor::from_json_path(values.clone(), *top_index, path).is_some()
+        });
+    }
+    fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            values: self.values.clone(),
+            cursor: (self.cursor.top_index, self.cursor.to_json_path()),
+        }
+    }
+    // Swaps in new values, keeping folds and the cursor on the same paths where they still exist.
+    fn restore(&mut self, snapshot: Snapshot) {
+        let folds = self.json_folds();
+        let scroll = self.scroll_json_path();
+        self.values = snapshot.values;
+        self.set_json_folds(&folds);
+        self.retain_valid_marks();
+        self.return_to(snapshot.cursor, scroll);
+    }
+    // The value on the top line, to scroll back to after the values change.
+    fn scroll_json_path(&self) -> (usize, Vec<PathElement>) {
+        let value_cursor = &self.scroll.value_cursor;
+        (value_cursor.top_index, value_cursor.to_json_path())
+    }
+    // After the values change, moves the cursor and scroll back to the same paths where they still
+    // exist, and scrolls to the cursor if that leaves it off screen.
+    fn return_to(&mut self, cursor: (usize, Vec<PathElement>), scroll: (usize, Vec<PathElement>)) {
+        if let Some(cursor) = LeafCursor::from_json_path(self.values.clone(), cursor.0, &cursor.1) {
+            self.cursor = cursor;
+            self.unfold_around_cursor();
+        }
+        if let Some(scroll) = LeafCursor::from_json_path(self.values.clone(), scroll.0, &scroll.1) {
+            self.scroll =
+                GlobalCursor::new_at(scroll, self.rect.width, &self.folds, self.scroll.options);
+        }
+        self.scroll_to_cursor();
+    }
+    // Swaps in more of a file that's still loading, which only adds to the end, so everything
+    // stays where it was. Unlike edits, it isn't undoable and doesn't mark the view as modified.
+    pub fn extend_loaded(&mut self, values: Vec<JV>) {
+        self.restore(Snapshot {
+            values: values.into(),
+            cursor: (self.cursor.top_index, self.cursor.to_json_path()),
+        });
+    }
+    // Replaces the values with an edited version, remembering at most `history_limit` prior
+    // versions for undo. The cursor moves to `cursor` if it exists in the new values. Returns
+    // false, leaving the view unchanged, if `values` is empty.
+    pub fn edit_values(
+        &mut self,
+        values: Vec<JV>,
+        cursor: (usize, Vec<PathElement>),
+        history_limit: usize,
+    ) -> bool {
+        if values.is_empty() {
+            return false;
+        }
+        self.undo_stack.push_back(self.snapshot());
+        while self.undo_stack.len() > history_limit {
+            self.undo_stack.pop_front();
+        }
+        self.redo_stack.clear();
+        self.modified = true;
+        self.last_edit = Some(Instant::now());
+        self.restore(Snapshot {
+            values: values.into(),
+            cursor,
+        });
+        true
+    }
+    // Returns whether there was anything to undo.
+    pub fn undo(&mut self) -> bool {
+        match self.undo_stack.pop_back() {
+            Some(snapshot) => {
+                self.redo_stack.push(self.snapshot());
+                self.restore(snapshot);
+                true
+            }
+            None => false,
+        }
+    }
+    // Returns whether there was anything to redo.
+    pub fn redo(&mut self) -> bool {
+        match self.redo_stack.pop() {
+            Some(snapshot) => {
+                self.undo_stack.push_back(self.snapshot());
+                self.restore(snapshot);
+                true
+            }
+            None => false,
+        }
+    }
+    pub fn toggle_raw_strings(&mut self) {
+        let mut options = self.scroll.options;
+        options.raw_strings = !options.raw_strings;
+        self.set_render_options(options);
+    }
+    pub fn toggle_format_numbers(&mut self) {
+        let mut options = self.scroll.options;
+        options.format_numbers = !options.format_numbers;
+        self.set_render_options(options);
+    }
+    // Cycles booleans and null through being shown as words, with symbols beside them, and as
+    // symbols alone.
+    pub fn cycle_scalar_glyphs(&mut self) -> GlyphMode {
+        let mut options = self.scroll.options;
+        options.scalar_glyphs.mode = options.scalar_glyphs.mode.next();
+        self.set_render_options(options);
+        options.scalar_glyphs.mode
+    }
+    pub fn toggle_wrap_lines(&mut self) {
+        let mut options = self.scroll.options;
+        options.wrap_lines = !options.wrap_lines;
+        self.set_render_options(options);
+    }
+    pub fn toggle_depth_ruler(&mut self) -> bool {
+        let mut options = self.scroll.options;
+        options.depth_ruler = !options.depth_ruler;
+        self.set_render_options(options);
+        options.depth_ruler
+    }
+    pub fn toggle_minimal_punctuation(&mut self) -> bool {
+        let mut options = self.scroll.options;
+        options.minimal_punctuation = !options.minimal_punctuation;
+        self.set_render_options(options);
+        options.minimal_punctuation
+    }
+    // Returns the types that are abstracted afterwards.
+    pub fn toggle_abstracted(&mut self, types: AbstractTypes) -> AbstractTypes {
+        let mut options = self.scroll.options;
+        options.abstracted = options.abstracted.toggled(types);
+        self.set_render_options(options);
+        options.abstracted
+    }
+    pub fn toggle_reveal_whitespace(&mut self) -> bool {
+        let mut options = self.scroll.options;
+        options.reveal_whitespace = !options.reveal_whitespace;
+        self.set_render_options(options);
+        options.reveal_whitespace
+    }
+    pub fn toggle_hide_nulls(&mut self) -> bool {
+        let mut options = self.scroll.options;
+        options.hide_nulls = !options.hide_nulls;
+        self.set_render_options(options);
+        options.hide_nulls
+    }
+    pub fn toggle_hide_empty(&mut self) -> bool {
+        let mut options = self.scroll.options;
+        options.hide_empty = !options.hide_empty;
+        self.set_render_options(options);
+        options.hide_empty
+    }
+    pub fn toggle_index_gutter(&mut self) {
+        let mut options = self.scroll.options;
+        options.index_gutter = !options.index_gutter;
+        self.set_render_options(options);
+    }
+    // Re-renders from the start of the leaf at the top of the screen, since line breaks may have
+    // moved.
+    pub fn set_render_options(&mut self, options: RenderOptions) {
+        let mut top = self.scroll.value_cursor.clone();
+        if top.is_hidden(options) {
+            top.advance_visible(&self.folds, options);
+        }
+        self.scroll = GlobalCursor::new_at(top, self.rect.width, &self.folds, options);
+        self.jump_to(self.cursor.clone());
+    }
+    // Returns false, doing nothing, if the cursor isn't on an array or object.
+    pub fn toggle_fold(&mut self) -> bool {
+        // Folding by hand makes these folds your own.
+        self.fold_preset = FoldPreset::Manual;
         let path = self.cursor.to_path().strip_position();
+        // Keep the cursor on the same line of the screen, rather than everything below the fold
+        // staying put and the cursor jumping.
+        let cursor_row = self.cursor_row();
         if self.folds.contains(&path) {
             self.folds.remove(&path);
         } else {
+            if !matches!(self.cursor.focus, JV::Array(_) | JV::Object(_)) {
+                return false;
+            }
             self.folds.insert(path);
             if let FocusPosition::End = self.cursor.focus_position {
                 self.cursor.focus_position = FocusPosition::Start;
             }
-            if self
-                .scroll
-                .value_cursor
-                .descends_from_or_matches(&self.cursor)
-            {
-                let line = self.cursor.current_line(&self.folds, self.rect.width);
-                let line_cursor = LineCursor::new_at_start(line.render(), self.rect.width);
-                self.scroll = GlobalCursor {
-                    value_cursor: self.cursor.clone(),
-                    // Note: this is okay because you can only fold objects and arrays
-                    line_cursor,
-                };
-            }
         }
+        self.scroll_cursor_to_row(cursor_row);
+        true
     }
     pub fn advance_cursor(&mut self) {
         let visible_range = self.visible_range(&self.folds);
@@ -562,7 +1344,8 @@ impl JsonView {
             self.scroll.advance(&self.folds, self.rect.width);
             return;
         }
-        self.cursor.advance(&self.folds);
+        self.cursor
+            .advance_visible(&self.folds, self.scroll.options);
         if !visible_range.contains_value(&self.cursor.to_path()) {
             self.scroll.advance(&self.folds, self.rect.width);
         }
@@ -573,22 +1356,116 @@ impl JsonView {
             self.scroll.regress(&self.folds, self.rect.width);
             return;
         }
-        self.cursor.regress(&self.folds);
+        self.cursor
+            .regress_visible(&self.folds, self.scroll.options);
         if !visible_range.contains_value(&self.cursor.to_path()) {
             self.scroll.regress(&self.folds, self.rect.width);
         }
     }
+    // Keeps the cursor on the same row of the screen where it fits, since line breaks above it may
+    // have moved.
     pub fn resize_to(&mut self, json_rect: Rect) {
+        let cursor_row = self.cursor_row();
         self.rect = json_rect;
-        self.scroll.resize_to(json_rect);
-        while self.cursor.to_path() < **self.visible_range(&self.folds).value_range().start() {
-            self.scroll.regress(&self.folds, self.rect.width);
+        self.scroll_cursor_to_row(cursor_row);
+    }
+    // Scrolls so the cursor's first line is `row` lines down, or as close as the screen allows.
+    fn scroll_cursor_to_row(&mut self, row: usize) {
+        self.scroll = GlobalCursor::new_at(
+            self.cursor.clone(),
+            self.rect.width,
+            &self.folds,
+            self.scroll.options,
+        );
+        for _ in 0..row.min(self.rect.height.saturating_sub(1) as usize) {
+            if self.scroll.regress(&self.folds, self.rect.width).is_none() {
+                break;
+            }
         }
-        while self.cursor.to_path() > **self.visible_range(&self.folds).value_range().end() {
-            self.scroll.advance(&self.folds, self.rect.width);
+    }
+    // Switches from your own folds to everything folded below the top level, then to nothing
+    // folded, then back to your folds. If the cursor ends up hidden, it moves to the outermost
+    // fold around it.
+    pub fn cycle_fold_preset(&mut self) -> FoldPreset {
+        let cursor_row = self.cursor_row();
+        let (preset, folds) = match self.fold_preset {
+            FoldPreset::Manual => {
+                self.manual_folds = self.json_folds();
+                (FoldPreset::Compact, self.compact_folds())
+            }
+            FoldPreset::Compact => (FoldPreset::Expanded, HashSet::new()),
+            FoldPreset::Expanded => {
+                let manual_folds = std::mem::take(&mut self.manual_folds);
+                (FoldPreset::Manual, self.folds_from_json(&manual_folds))
+            }
+        };
+        self.fold_preset = preset;
+        self.folds = folds;
+        let (top_index, frames) = self.cursor.to_path().strip_position();
+        let hidden_by = (0..frames.len())
+            .find(|depth| self.folds.contains(&(top_index, frames[..*depth].to_vec())));
+        if let Some(depth) = hidden_by {
+            let path = self.cursor.to_json_path();
+            self.cursor =
+                LeafCursor::from_json_path(self.values.clone(), top_index, &path[..depth])
+                    .expect("folds should exist");
+        }
+        if self.folds.contains(&(top_index, frames)) {
+            self.cursor.focus_position = FocusPosition::Start;
+        }
+        self.scroll_cursor_to_row(cursor_row);
+        preset
+    }
+    // Folds for each array and object directly inside a top level value.
+    fn compact_folds(&self) -> HashSet<(usize, Vec<usize>)> {
+        let mut folds = HashSet::new();
+        for (top_index, value) in self.values.iter().enumerate() {
+            let children: Box<dyn Iterator<Item = JV>> = match value {
+                JV::Object(_) => Box::new(value.object_entries().map(|(_, child)| child)),
+                _ => Box::new(value.array_elements()),
+            };
+            for (i, child) in children.enumerate() {
+                if matches!(child, JV::Array(_) | JV::Object(_)) {
+                    folds.insert((top_index, vec![i]));
+                }
+            }
+        }
+        folds
+    }
+    // The row of the screen the cursor's first line is on, or 0 if it's above the screen.
+    fn cursor_row(&self) -> usize {
+        let cursor = self.cursor.to_path();
+        let mut scroll = self.scroll.clone();
+        for row in 0..self.rect.height as usize {
+            if scroll.value_cursor.to_path() == cursor {
+                return row;
+            }
+            if scroll.advance(&self.folds, self.rect.width).is_none() {
+                break;
+            }
+        }
+        0
+    }
+    // The lines as they're shown, with folds but without styling: either what's on screen, or the
+    // whole document from the top.
+    pub fn plain_text(&self, whole_document: bool) -> Vec<String> {
+        if whole_document {
+            let mut scroll = GlobalCursor::new(
+                self.values.clone(),
+                self.rect.width,
+                &self.folds,
+                self.scroll.options,
+            )
+            .expect("values should still exist");
+            scroll.render_plain_lines(&self.folds, self.rect.width, None)
+        } else {
+            let height = Some(self.rect.height as usize);
+            self.scroll
+                .clone()
+                .render_plain_lines(&self.folds, self.rect.width, height)
         }
     }
-    pub fn save_to(&self, path: &str) -> std::io::Result<()> {
+    pub fn save_to<P: AsRef<std::path::Path>>(&self, path: P) -> std::io::Result<()> {
         let mut file = std::fs::File::create(path)?;
         for (i, v) in self.values.iter().enumerate() {
             if i != 0 {
@@ -636,12 +1513,17 @@ impl GlobalPathRange {
 
 #[cfg(test)]
 mod tests {
-    use super::JsonView;
-    use crate::{cursor::GlobalCursor, jq::jv::JV, testing::arb_json};
+    use super::{FoldPreset, JsonView, NamedView, View};
+    use crate::{
+        cursor::{FocusPosition, GlobalCursor, PathElement},
+        jq::jv::JV,
+        lines::{AbstractTypes, RenderOptions},
+        testing::arb_json,
+    };
     use pretty_assertions::assert_eq;
     use proptest::proptest;
-    use serde_json::{Deserializer, Value};
-    use std::{collections::HashSet, fs, io};
+    use serde_json::{json, Deserializer, Value};
+    use std::{collections::HashSet, fs, io, path::PathBuf};
     use tui::layout::Rect;
     const DUMMY_RECT: Rect = Rect {
         x: 1,
@@ -657,7 +1539,7 @@ mod tests {
     };
     fn check_folds(values: Vec<Value>) {
         let jsons: Vec<JV> = values.iter().map(|v| v.into()).collect();
-        let mut view = match JsonView::new(jsons, DUMMY_RECT) {
+        let mut view = match JsonView::new(jsons, DUMMY_RECT, RenderOptions::default()) {
             None => return,
             Some(view) => view,
         };
@@ -688,9 +1570,14 @@ mod tests {
             .into_iter::<JV>()
             .collect::<Result<Vec<JV>, _>>()
             .unwrap();
-        let mut view = JsonView::new(jsons, DUMMY_RECT).unwrap();
-        view.scroll =
-            GlobalCursor::new_end(view.values.clone(), DUMMY_RECT.width, &HashSet::new()).unwrap();
+        let mut view = JsonView::new(jsons, DUMMY_RECT, RenderOptions::default()).unwrap();
+        view.scroll = GlobalCursor::new_end(
+            view.values.clone(),
+            DUMMY_RECT.width,
+            &HashSet::new(),
+            RenderOptions::default(),
+        )
+        .unwrap();
         view.cursor = view.scroll.value_cursor.clone();
         let line_limit = 20;
         let rect = Rect {
@@ -715,12 +1602,12 @@ mod tests {
             .into_iter::<JV>()
             .collect::<Result<Vec<JV>, _>>()
             .unwrap();
-        let mut view = JsonView::new(jsons.clone(), DUMMY_RECT).unwrap();
+        let mut view = JsonView::new(jsons.clone(), DUMMY_RECT, RenderOptions::default()).unwrap();
         let right_rect = Rect {
             x: 138,
             ..DUMMY_RECT
         };
-        let right_view = JsonView::new(jsons, right_rect).unwrap();
+        let right_view = JsonView::new(jsons, right_rect, RenderOptions::default()).unwrap();
         let folds = HashSet::new();
         view.render(DUMMY_RECT, true);
         right_view.render(right_rect, true);
@@ -738,7 +1625,247 @@ mod tests {
             .into_iter::<JV>()
             .collect::<Result<Vec<JV>, _>>()
             .unwrap();
-        let view = JsonView::new(jsons, TINY_RECT).unwrap();
+        let view = JsonView::new(jsons, TINY_RECT, RenderOptions::default()).unwrap();
         view.render(TINY_RECT, true);
     }
+    #[test]
+    fn unit_undo_redo() {
+        let values = |v: Value| -> Vec<JV> { vec![(&v).into()] };
+        let mut view =
+            JsonView::new(values(json!([1])), DUMMY_RECT, RenderOptions::default()).unwrap();
+        for i in 2..5 {
+            assert!(view.edit_values(values(json!([i])), (0, vec![]), 2));
+        }
+        let current = |view: &JsonView| -> Value { (&view.values[0]).into() };
+        assert_eq!(current(&view), json!([4.0]));
+        assert!(view.undo());
+        assert!(view.undo());
+        assert_eq!(current(&view), json!([2.0]));
+        // Only 2 edits are remembered.
+        assert!(!view.undo());
+        assert!(view.redo());
+        assert_eq!(current(&view), json!([3.0]));
+        assert!(view.edit_values(values(json!([5])), (0, vec![]), 2));
+        assert!(!view.redo());
+        assert!(!view.edit_values(vec![], (0, vec![]), 2));
+    }
+    #[test]
+    fn unit_resize_keeps_cursor_row() {
+        let json: JV =
+            (&json!({ "intro": "a".repeat(100), "list": (0..100).collect::<Vec<_>>() })).into();
+        let mut view = JsonView::new(vec![json], DUMMY_RECT, RenderOptions::default()).unwrap();
+        for _ in 0..30 {
+            view.advance_cursor();
+        }
+        let cursor = view.cursor.to_path();
+        let row = view.cursor_row();
+        assert_eq!(row, 30);
+        // Narrower, so the long string above wraps onto more lines.
+        let narrow = Rect {
+            width: 15,
+            ..DUMMY_RECT
+        };
+        view.resize_to(narrow);
+        assert_eq!(view.cursor.to_path(), cursor);
+        assert_eq!(view.cursor_row(), row);
+        // Too short for the cursor's row, so it's at the bottom.
+        view.resize_to(TINY_RECT);
+        assert_eq!(view.cursor_row(), TINY_RECT.height as usize - 1);
+    }
+    #[test]
+    fn unit_fold_keeps_cursor_row() {
+        let list = (0..100).collect::<Vec<_>>();
+        let json: JV = (&json!({ "a": list, "b": list })).into();
+        let mut view = JsonView::new(vec![json], DUMMY_RECT, RenderOptions::default()).unwrap();
+        // Onto the closing bracket of "b", at the bottom of the screen.
+        for _ in 0..204 {
+            view.advance_cursor();
+        }
+        assert_eq!(view.cursor.focus_position, FocusPosition::End);
+        let row = view.cursor_row();
+        assert_eq!(row, DUMMY_RECT.height as usize - 1);
+        assert!(view.toggle_fold());
+        assert_eq!(view.cursor_row(), row);
+        assert!(view.toggle_fold());
+        assert_eq!(view.cursor_row(), row);
+    }
+    #[test]
+    fn unit_page_size() {
+        let json: JV = (&json!((0..100).collect::<Vec<_>>())).into();
+        let mut view = JsonView::new(vec![json], DUMMY_RECT, RenderOptions::default()).unwrap();
+        assert!(view.page_down(Some(10)));
+        assert_eq!(view.cursor.to_json_path(), vec![PathElement::Index(9)]);
+        assert!(view.page_up(Some(3)));
+        assert_eq!(view.cursor.to_json_path(), vec![PathElement::Index(6)]);
+        // Without a page size, it's the height of the view, less a line.
+        assert!(view.page_down(None));
+        let lines = DUMMY_RECT.height as usize - 1;
+        assert_eq!(
+            view.cursor.to_json_path(),
+            vec![PathElement::Index(6 + lines)]
+        );
+    }
+    #[test]
+    fn unit_minimal_punctuation() {
+        let json: JV = (&json!({"a": [1, {}], "b": "x"})).into();
+        let mut view = JsonView::new(vec![json], DUMMY_RECT, RenderOptions::default()).unwrap();
+        let before = view.plain_text(true);
+        assert!(view.toggle_minimal_punctuation());
+        assert_eq!(
+            view.plain_text(true),
+            vec![
+                "",
+                "  \"a\" :",
+                "    1",
+                "    {}",
+                "",
+                "",
+                "  \"b\" : \"x\"",
+                ""
+            ]
+        );
+        // The cursor moves over the same lines.
+        view.advance_cursor();
+        view.advance_cursor();
+        assert_eq!(
+            view.cursor.to_json_path(),
+            vec![PathElement::Key("a".to_owned()), PathElement::Index(0)]
+        );
+        assert!(!view.toggle_minimal_punctuation());
+        assert_eq!(view.plain_text(true), before);
+    }
+    #[test]
+    fn unit_abstracted_types() {
+        let json: JV = (&json!({"a": ["x", 1.5, true], "b": null})).into();
+        let mut view = JsonView::new(vec![json], DUMMY_RECT, RenderOptions::default()).unwrap();
+        let before = view.plain_text(true);
+        let strings = AbstractTypes::from_char('s').unwrap();
+        assert_eq!(
+            view.toggle_abstracted(strings).describe().unwrap(),
+            "strings"
+        );
+        let all = view.toggle_abstracted(AbstractTypes::ALL);
+        assert_eq!(all.describe().unwrap(), "strings, numbers and booleans");
+        assert_eq!(
+            view.plain_text(true),
+            vec![
+                "{",
+                "  \"a\" : [",
+                "    \"…\",",
+                "    #,",
+                "    true/false",
+                "  ],",
+                "  \"b\" : null",
+                "}"
+            ]
+        );
+        // With them all on, all of them go off.
+        assert_eq!(view.toggle_abstracted(AbstractTypes::ALL).describe(), None);
+        assert_eq!(view.plain_text(true), before);
+    }
+    #[test]
+    fn unit_max_value_chars() {
+        let json: JV = (&json!(["abcdef", "abcde", "a\"\nbcdef"])).into();
+        let options = RenderOptions {
+            max_value_chars: Some(5),
+            ..RenderOptions::default()
+        };
+        let view = JsonView::new(vec![json.clone()], DUMMY_RECT, options).unwrap();
+        assert_eq!(
+            view.plain_text(true),
+            vec![
+                "[",
+                "  \"abcde…\",",
+                "  \"abcde\",",
+                "  \"a\\\"\\nbc…\"",
+                "]"
+            ]
+        );
+        // Only the display is cut short.
+        assert_eq!(view.values[0], json);
+    }
+    #[test]
+    fn unit_hide_nulls_and_empty() {
+        let json: JV = (&json!({ "a": null, "b": [], "c": [null], "d": {}, "e": null })).into();
+        let mut view = JsonView::new(vec![json], DUMMY_RECT, RenderOptions::default()).unwrap();
+        // Onto "a", which is hidden next.
+        view.advance_cursor();
+        assert!(view.toggle_hide_empty());
+        assert!(view.toggle_hide_nulls());
+        assert_eq!(
+            view.plain_text(true),
+            vec!["{", "  \"c\" : [", "    null", "  ]", "}"]
+        );
+        assert_eq!(
+            view.cursor.to_json_path(),
+            vec![PathElement::Key("c".to_owned())]
+        );
+        for _ in 0..3 {
+            view.advance_cursor();
+        }
+        assert_eq!(view.cursor.to_json_path(), vec![]);
+        view.regress_cursor();
+        view.regress_cursor();
+        assert_eq!(
+            view.cursor.to_json_path(),
+            vec![PathElement::Key("c".to_owned()), PathElement::Index(0)]
+        );
+        assert!(!view.toggle_hide_empty());
+        assert_eq!(
+            view.plain_text(true),
+            vec![
+                "{",
+                "  \"b\" : [",
+                "  ],",
+                "  \"c\" : [",
+                "    null",
+                "  ],",
+                "  \"d\" : {",
+                "  }",
+                "}"
+            ]
+        );
+    }
+    #[test]
+    fn unit_fold_presets() {
+        let json: JV = (&json!({ "a": [1, 2], "b": { "c": { "d": 3 } }, "e": 4 })).into();
+        let mut view = JsonView::new(vec![json], DUMMY_RECT, RenderOptions::default()).unwrap();
+        // Onto "c", then fold it by hand.
+        for _ in 0..6 {
+            view.advance_cursor();
+        }
+        assert!(view.toggle_fold());
+        let manual = view.json_folds();
+        assert_eq!(view.cycle_fold_preset(), FoldPreset::Compact);
+        let mut folds = view.json_folds();
+        folds.sort_by_key(|(_, path)| format!("{:?}", path));
+        assert_eq!(
+            folds,
+            vec![
+                (0, vec![PathElement::Key("a".to_owned())]),
+                (0, vec![PathElement::Key("b".to_owned())])
+            ]
+        );
+        // "c" is hidden, so the cursor moves to the fold around it.
+        assert_eq!(
+            view.cursor.to_json_path(),
+            vec![PathElement::Key("b".to_owned())]
+        );
+        assert_eq!(view.cycle_fold_preset(), FoldPreset::Expanded);
+        assert!(view.json_folds().is_empty());
+        assert_eq!(view.cycle_fold_preset(), FoldPreset::Manual);
+        assert_eq!(view.json_folds(), manual);
+    }
+    #[test]
+    fn unit_save_path() {
+        let values: Vec<JV> = vec![(&json!(1)).into()];
+        let mut frame = NamedView {
+            view: View::new(values, DUMMY_RECT, RenderOptions::default()),
+            name: "Some title".to_owned(),
+            source_path: None,
+        };
+        assert_eq!(frame.save_path(), "");
+        frame.source_path = Some(PathBuf::from("data/file.json"));
+        assert_eq!(frame.save_path(), "data/file.json");
+    }
 }